@@ -1,7 +1,7 @@
 use pyo3::prelude::*;
 use std::collections::HashMap;
-use skypie_lib::Loader;
-use std::path::PathBuf;
+use skypie_lib::{id_catalogue::IdCatalogue, input_format::InputFormat, Loader};
+use std::path::{Path, PathBuf};
 
 use skypie_lib::{
     object_store::ObjectStore, ApplicationRegion, Decision
@@ -27,7 +27,7 @@ impl Optimizer for PyLoader {
 #[pymethods]
 impl PyLoader {
     #[new]
-    #[pyo3(signature = (network_file, object_store_file, object_stores_considered, application_regions_considered, latency_file_path = None, latency_slo = None, verbose = None, region_selector = None, object_store_selector = None))]
+    #[pyo3(signature = (network_file, object_store_file, object_stores_considered, application_regions_considered, latency_file_path = None, latency_slo = None, verbose = None, region_selector = None, object_store_selector = None, id_catalogue_file = None))]
     pub fn new(
         network_file: &str,
         object_store_file: &str,
@@ -37,8 +37,11 @@ impl PyLoader {
         latency_slo: Option<f64>,
         verbose: Option<i32>,
         region_selector: Option<&str>,
-        object_store_selector: Option<&str>
+        object_store_selector: Option<&str>,
+        id_catalogue_file: Option<&str>
     ) -> Self {
+        // Pin the ids of the regions and object stores to the id catalogue, the ids can have gaps
+        let id_catalogue = id_catalogue_file.map(|f| IdCatalogue::from_file(Path::new(f)).unwrap());
 
         let loader = if object_stores_considered.is_empty() && application_regions_considered.is_empty() && region_selector.is_some() && object_store_selector.is_some() {
             println!("No object stores or application regions considered. Falling back to all object stores and application regions.");
@@ -49,9 +52,16 @@ impl PyLoader {
             let region_selector = region_selector.unwrap();
             let object_store_selector = object_store_selector.unwrap();
             
-            let loader = Loader::new(&network_file, &object_store_file, &region_selector, &object_store_selector, &latency_file_path, &latency_slo, verbose);            
+            let loader = Loader::with_id_catalogue(&network_file, &object_store_file, region_selector, object_store_selector, &latency_file_path, &latency_slo, &None, &InputFormat::default(), id_catalogue.as_ref(), verbose).unwrap();
             loader
         } else {
+            // Considered application regions keep the ids of the id catalogue instead of the given ids
+            let application_regions_considered = match &id_catalogue {
+                Some(id_catalogue) => application_regions_considered.into_keys()
+                    .map(|name| (name, id_catalogue.region_id(name).unwrap_or_else(|| panic!("Region {} is not in the id catalogue", name))))
+                    .collect(),
+                None => application_regions_considered,
+            };
             let loader = Self::load(network_file, object_store_file, object_stores_considered, application_regions_considered, latency_file_path, &latency_slo, verbose);
             loader
         };
@@ -112,6 +122,7 @@ impl PyLoader {
         self.application_regions.iter().map(|r| r.region.name.clone()).collect::<Vec<_>>()
     }
 
+    /// Ids of the application regions by name, the ids of the id catalogue if given
     pub fn application_region_mapping(&self) -> HashMap<String, u64> {
         self.application_regions.iter().map(|r| (r.region.name.clone(), r.region.id as u64)).collect::<HashMap<_,_>>()
     }
@@ -149,7 +149,6 @@ async def precomputation(*, e: Experiment):
         "redundancy-elimination-workers": e.redundancy_elimination_workers,
         #"output_candidates": ""
         "experiment-name": e.experiment_dir_full,
-        "influx-host": "flaminio.millennium.berkeley.edu",
         "num-workers": num_workers,
         "optimizer": e.optimizer,
     }
@@ -168,7 +167,7 @@ async def precomputation(*, e: Experiment):
     # Worker specific args
     optimal_policies_name_prefix = "optimal"
     candidate_policies_name_prefix = "candidates"
-    output_file_extension = "proto.bin"
+    output_file_extension = "proto.bin.zst" if e.compress else "proto.bin"
 
     kwargs_instances={
         i: {"args":(
@@ -232,9 +231,12 @@ async def precomputation(*, e: Experiment):
         s.ports.time_output.send_to(logging_service.ports.time_input.merge())
         s.ports.count_output.send_to(logging_service.ports.count_input.merge())
         s.ports.done_output.send_to(logging_service.ports.done_input.merge())
+        # Workers pull chunks of write choices from the write choices service
+        s.ports.request_output.send_to(write_choices_service.ports.request_input.merge())
 
-    # Send timing information of the write choices service to the logging service
+    # Send timing and progress information of the write choices service to the logging service
     write_choices_service.ports.time_output.send_to(logging_service.ports.time_input.merge())
+    write_choices_service.ports.count_output.send_to(logging_service.ports.count_input.merge())
 
     python_receiver_port = python_receiver_service.client_port()
     logging_service.ports.done_output.send_to(python_receiver_port)
@@ -17,11 +17,29 @@ class Experiment:
     experiment_dir_full: str = "" # This is set in __post_init__
     optimizer: str = "PrimalSimplex"
     use_clarkson: bool = False
+    prune_unread_replicas: bool = False
+    chunk_size: int = 1000
+    metrics_port: int = None
+    metrics_address: str = None
+    log_level: str = "info"
+    log_json: bool = False
+    compress: bool = False
+    checkpoint_interval: int = None
+    resume: bool = False
     output_candidates: bool = False
     latency_slo: float = None
     latency_file: str = None
+    write_latency_slo: float = None
+    write_quorum: int = None
+    min_vendors: int = None
+    distinct_regions: bool = False
+    required_regions: "list[str]" = field(default_factory=list)
 
     def __post_init__(self):
+
+        # Pruned decisions are only dominated by the smaller write choice if no constraint excludes it, see Args::unread_replica_pruning
+        if self.prune_unread_replicas and (self.min_vendors is not None or len(self.required_regions) > 0 or self.write_latency_slo is not None):
+            raise ValueError("prune_unread_replicas cannot be combined with min_vendors, required_regions or write_latency_slo")
         
         # Create a translation table that replaces all unfriendly characters with -
         unfriendly_chars = ["|", "*", " ", "(", ")", "[", "]", "{", "}", ":", ";", ",", ".", "<", ">", "/", "\\", "?", "'", "\"", "\n", "\t", "\r", "\v", "\f"]
@@ -39,9 +57,20 @@ class Experiment:
 
         friendly_latency_slo = f"latency_slo-{str(self.latency_slo).translate(translation_table)}" if self.latency_slo is not None else ""
 
+        friendly_write_latency_slo = f"write_latency_slo-{str(self.write_latency_slo).translate(translation_table)}" + (f"-quorum-{self.write_quorum}" if self.write_quorum is not None else "")
+
+        # Durability constraints on the write choices
+        constraints = ([f"min_vendors-{self.min_vendors}"] if self.min_vendors is not None else []) + \
+            (["distinct_regions"] if self.distinct_regions else []) + \
+            (["prune_unread_replicas"] if self.prune_unread_replicas else []) + \
+            [f"required_region-{r.translate(translation_table)}" for r in self.required_regions]
+        friendly_constraints = "-".join(constraints)
+
         # Create the name of the experiment
         paths = ([self.experiment_name] if self.experiment_name is not None else []) + \
             ([friendly_latency_slo] if self.latency_slo is not None else []) + \
+            ([friendly_write_latency_slo] if self.write_latency_slo is not None else []) + \
+            ([friendly_constraints] if len(friendly_constraints) > 0 else []) + \
             [friendly_region_and_object_store, f"{self.replication_factor}-{self.replication_factor_max or self.replication_factor}", str(self.redundancy_elimination_workers), str(self.batch_size), str(self.optimizer), clarkson]
         self.experiment_dir_full = os.path.join(self.output_dir, *paths)
 
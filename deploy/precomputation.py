@@ -22,18 +22,28 @@ async def precomputation(*, e: Experiment):
         "redundancy-elimination-workers": e.redundancy_elimination_workers,
         #"output_candidates": ""
         "experiment-name": e.experiment_dir_full,
-        "influx-host": "",
         "num-workers": num_workers,
         "optimizer": e.optimizer,
     }
 
+    if e.latency_file is not None:
+        args["latency-file"] = e.latency_file
     if e.latency_slo is not None and e.latency_file is not None:
         args["latency-slo"] = e.latency_slo
-        args["latency-file"] = e.latency_file
+    if e.write_latency_slo is not None:
+        assert e.latency_file is not None, "Write latency SLO requires a latency file"
+        args["write-latency-slo"] = e.write_latency_slo
+        if e.write_quorum is not None:
+            args["write-quorum"] = e.write_quorum
 
     if e.replication_factor_max is not None:
         args["replication-factor-max"] = e.replication_factor_max
 
+    if e.min_vendors is not None:
+        args["min-vendors"] = e.min_vendors
+
+    args["chunk-size"] = e.chunk_size
+    args["log-level"] = e.log_level
 
     # Convert args to a list of strings with --key=value format
     args = [f"--{key}={value}" for key, value in args.items()]
@@ -41,10 +51,28 @@ async def precomputation(*, e: Experiment):
     if e.use_clarkson:
         args.append("--use-clarkson")
 
+    if e.checkpoint_interval is not None:
+        args.append(f"--checkpoint-interval={e.checkpoint_interval}")
+
+    if e.resume:
+        args.append("--resume")
+
+    if e.log_json:
+        args.append("--log-json")
+
+    if e.prune_unread_replicas:
+        args.append("--prune-unread-replicas")
+
+    if e.distinct_regions:
+        args.append("--distinct-regions")
+
+    for r in e.required_regions:
+        args.append(f"--required-region={r}")
+
     # Worker specific args
     optimal_policies_name_prefix = "optimal"
     candidate_policies_name_prefix = "candidates"
-    output_file_extension = "proto.bin"
+    output_file_extension = "proto.bin.zst" if e.compress else "proto.bin"
 
     kwargs_instances={
         i: {"args":(
@@ -84,7 +112,8 @@ async def precomputation(*, e: Experiment):
             '--worker-id', "10001",
             '-o', os.path.join(e.experiment_dir_full, f'{optimal_policies_name_prefix}.{output_file_extension}'),
             "--output-candidates-file-name", os.path.join(e.experiment_dir_full, f"{candidate_policies_name_prefix}.{output_file_extension}")
-        ]
+        ] + ([f"--metrics-port={e.metrics_port}"] if e.metrics_port is not None else [])
+          + ([f"--metrics-address={e.metrics_address}"] if e.metrics_address is not None else [])
     )
     
     candidates_service = [s for s in create_scale_up_service(deployment,
@@ -108,9 +137,12 @@ async def precomputation(*, e: Experiment):
         s.ports.time_output.send_to(logging_service.ports.time_input.merge())
         s.ports.count_output.send_to(logging_service.ports.count_input.merge())
         s.ports.done_output.send_to(logging_service.ports.done_input.merge())
+        # Workers pull chunks of write choices from the write choices service
+        s.ports.request_output.send_to(write_choices_service.ports.request_input.merge())
 
-    # Send timing information of the write choices service to the logging service
+    # Send timing and progress information of the write choices service to the logging service
     write_choices_service.ports.time_output.send_to(logging_service.ports.time_input.merge())
+    write_choices_service.ports.count_output.send_to(logging_service.ports.count_input.merge())
 
     python_receiver_port = python_receiver_service.client_port()
     logging_service.ports.done_output.send_to(python_receiver_port)
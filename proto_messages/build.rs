@@ -13,6 +13,9 @@ fn main() -> Result<()> {
         "./proto/scheme.proto",
         "./proto/tier_advise.proto",
         "./proto/wrapper.proto",
+        "./proto/provenance.proto",
+        "./proto/id_mapping.proto",
+        "./proto/oracle_container.proto",
         ],
     &["./proto/"])?;
     Ok(())
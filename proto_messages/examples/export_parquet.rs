@@ -0,0 +1,20 @@
+//! Exports oracle files to Parquet with one row per decision.
+//!
+//! Usage: `cargo run --release -p sky-pie-precomputer-proto-messages --features parquet --example export_parquet -- <output.parquet> <oracle files...>`
+
+use std::path::Path;
+
+use skypie_proto_messages::parquet_export::export_parquet;
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.len() < 2 {
+        eprintln!("Usage: export_parquet <output.parquet> <oracle files...>");
+        std::process::exit(1);
+    }
+
+    let output = Path::new(&args[0]);
+    let paths = args[1..].iter().map(Path::new).collect::<Vec<_>>();
+    let no_decisions = export_parquet(&paths, output).expect("Failed to export oracle to Parquet");
+    println!("Exported {} decisions to {}", no_decisions, output.to_string_lossy());
+}
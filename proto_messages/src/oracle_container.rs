@@ -0,0 +1,412 @@
+//! Oracle container: length-delimited messages framed by a header and, once sealed, a footer index.
+//!
+//! Layout: `magic | header | message* | index | index offset (u64 LE) | magic`.
+//! The header and index are length-delimited `OracleHeader` and `OracleIndex` messages.
+//! Until sealed, the container ends after the last message, so it can be truncated to any message offset to resume writing.
+//! Compressed files are streams with the header but without index, since their messages cannot be accessed at an offset.
+//!
+//! Schema versions: 1 stores names in decisions, 2 stores indices into the dictionaries of the header instead, see `Scheme`.
+
+use std::{collections::HashMap, fs::{File, OpenOptions}, io::{self, Write}, ops::Range, path::{Path, PathBuf}, sync::{Arc, Mutex, OnceLock}, time::SystemTime};
+
+use bytes::BufMut;
+use memmap2::Mmap;
+use prost::Message;
+
+use crate::{OracleHeader, OracleIndex, ProtobufFileReader, ProtobufFileSink};
+
+pub const CONTAINER_MAGIC: [u8; 8] = *b"SKYPIEOC";
+pub const SCHEMA_VERSION: u32 = 3;
+/// Index offset and magic at the end of a sealed container
+const TRAILER_LEN: usize = 8 + CONTAINER_MAGIC.len();
+/// Containers kept open by `open_cached`
+const CACHE_SIZE: usize = 16;
+
+fn invalid_data<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Location of the header, messages and index in a container
+#[derive(Debug)]
+pub struct Layout {
+    pub header: OracleHeader,
+    pub data_start: u64,
+    pub data_end: u64,
+    /// Offsets of the messages from the index, None until the container is sealed
+    pub offsets: Option<Vec<u64>>,
+}
+
+/// Layout of the container in the buffer, None for a bare file of messages
+pub fn read_layout(buf: &[u8]) -> io::Result<Option<Layout>> {
+    if !buf.starts_with(&CONTAINER_MAGIC) {
+        return Ok(None);
+    }
+
+    let mut rest = &buf[CONTAINER_MAGIC.len()..];
+    let header = OracleHeader::decode_length_delimited(&mut rest).map_err(invalid_data)?;
+    if header.schema_version > SCHEMA_VERSION {
+        return Err(invalid_data(format!("Unsupported oracle schema version {}", header.schema_version)));
+    }
+    header.validate()?;
+    let data_start = (buf.len() - rest.len()) as u64;
+
+    let (data_end, offsets) = if rest.len() >= TRAILER_LEN && buf.ends_with(&CONTAINER_MAGIC) {
+        let (index_offset, offsets) = read_index(buf, data_start)?;
+        (index_offset, Some(offsets))
+    } else {
+        (buf.len() as u64, None)
+    };
+
+    Ok(Some(Layout { header, data_start, data_end, offsets }))
+}
+
+/// Offset and message offsets of the index before the trailer, which must exactly span the space up to the trailer.
+/// The message offsets must be increasing and within the messages, so that a corrupt trailer is not mistaken for an index.
+fn read_index(buf: &[u8], data_start: u64) -> io::Result<(u64, Vec<u64>)> {
+    let trailer_start = buf.len() - TRAILER_LEN;
+    let index_offset = u64::from_le_bytes(buf[trailer_start..trailer_start + 8].try_into().unwrap());
+    if index_offset < data_start || index_offset > trailer_start as u64 {
+        return Err(invalid_data("Invalid index offset in oracle trailer"));
+    }
+
+    let mut index_buf = &buf[index_offset as usize..trailer_start];
+    let len = prost::encoding::decode_varint(&mut index_buf).map_err(invalid_data)?;
+    if len != index_buf.len() as u64 {
+        return Err(invalid_data("Oracle index does not end at the trailer"));
+    }
+    let offsets = OracleIndex::decode(index_buf).map_err(invalid_data)?.offsets;
+
+    let in_bounds = offsets.iter().all(|o| (data_start..index_offset).contains(o));
+    let increasing = offsets.windows(2).all(|w| w[0] < w[1]);
+    if !in_bounds || !increasing {
+        return Err(invalid_data("Invalid message offsets in oracle index"));
+    }
+    Ok((index_offset, offsets))
+}
+
+/// Creates a container with the header for writing messages, which is compressed if the file name ends with ".zst".
+/// Offsets of the sink include the header, so they can be used to resume with `ProtobufFileSink::open_at`.
+pub fn create(file_name: &Path, header: &OracleHeader, capacity: usize, spare_capacity: usize) -> io::Result<ProtobufFileSink> {
+    let mut sink = ProtobufFileSink::new(file_name, capacity, spare_capacity)?;
+    sink.put_slice(&CONTAINER_MAGIC);
+    sink.write(header.clone())?;
+    Ok(sink)
+}
+
+/// Appends the index of the message offsets to a container, so that messages can be accessed by their id.
+/// Sealing a sealed container does nothing, neither does sealing bare, compressed or special files such as /dev/null.
+pub fn seal(file_name: &Path) -> io::Result<()> {
+    let file = File::open(file_name)?;
+    if !file.metadata()?.is_file() {
+        return Ok(());
+    }
+
+    let offsets = {
+        // Safety: The container is not modified while being scanned
+        let mmap = unsafe { Mmap::map(&file)? };
+        let layout = match read_layout(&mmap)? {
+            Some(layout) if layout.offsets.is_none() => layout,
+            _ => return Ok(()),
+        };
+
+        let mut offsets = Vec::new();
+        let mut rest = &mmap[layout.data_start as usize..];
+        while !rest.is_empty() {
+            offsets.push((mmap.len() - rest.len()) as u64);
+            let len = prost::encoding::decode_varint(&mut rest).map_err(invalid_data)? as usize;
+            if len > rest.len() {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated message in oracle container"));
+            }
+            rest = &rest[len..];
+        }
+        offsets
+    };
+
+    let mut file = OpenOptions::new().append(true).open(file_name)?;
+    let index_offset = file.metadata()?.len();
+    let no_messages = offsets.len();
+    file.write_all(&OracleIndex { offsets }.encode_length_delimited_to_vec())?;
+    file.write_all(&index_offset.to_le_bytes())?;
+    file.write_all(&CONTAINER_MAGIC)?;
+    file.flush()?;
+
+    tracing::debug!(file = %file_name.display(), no_messages, "Sealed oracle container");
+    Ok(())
+}
+
+/// Random access to the messages of a sealed container
+pub struct OracleContainer {
+    mmap: Mmap,
+    header: OracleHeader,
+    offsets: Vec<u64>,
+    data_end: u64,
+}
+
+impl OracleContainer {
+    pub fn open(file_name: &Path) -> io::Result<Self> {
+        let file = File::open(file_name)?;
+        // Safety: Oracle files are not modified while being read
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let layout = read_layout(&mmap)?.ok_or_else(|| invalid_data("Not an oracle container"))?;
+        let offsets = layout.offsets.ok_or_else(|| invalid_data("Oracle container is not sealed"))?;
+
+        Ok(Self { header: layout.header, offsets, data_end: layout.data_end, mmap })
+    }
+
+    /// Opens the container or reuses it if it was opened before and did not change since, e.g., for repeated random access
+    pub fn open_cached(file_name: &Path) -> io::Result<Arc<Self>> {
+        // Containers by file name, length and modification time
+        type Cache = Mutex<HashMap<(PathBuf, u64, Option<SystemTime>), Arc<OracleContainer>>>;
+        static CACHE: OnceLock<Cache> = OnceLock::new();
+
+        let metadata = std::fs::metadata(file_name)?;
+        let key = (file_name.to_path_buf(), metadata.len(), metadata.modified().ok());
+        let mut cache = CACHE.get_or_init(Default::default).lock().unwrap();
+        if let Some(container) = cache.get(&key) {
+            return Ok(container.clone());
+        }
+
+        let container = Arc::new(Self::open(file_name)?);
+        // Drop stale versions of the file and bound the number of open containers
+        cache.retain(|(path, _, _), _| path != file_name);
+        if cache.len() >= CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(key, container.clone());
+        Ok(container)
+    }
+
+    pub fn header(&self) -> &OracleHeader {
+        &self.header
+    }
+
+    /// Number of messages, without decoding them
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Decodes the message with the id, i.e., its position in the container
+    pub fn get<M>(&self, id: usize) -> io::Result<M>
+    where
+        M: Message + Default,
+    {
+        let offset = *self.offsets.get(id).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No message with id {}", id)))?;
+        M::decode_length_delimited(&self.mmap[offset as usize..self.data_end as usize]).map_err(invalid_data)
+    }
+
+    pub fn iter_range<M>(&self, ids: Range<usize>) -> impl Iterator<Item = io::Result<M>> + '_
+    where
+        M: Message + Default,
+    {
+        ids.map(move |id| self.get(id))
+    }
+
+    /// Splits the ids into at most `no_chunks` ranges of similar size for parallel decoding
+    pub fn chunks(&self, no_chunks: usize) -> Vec<Range<usize>> {
+        let chunk_size = std::cmp::max(1, (self.len() + no_chunks.max(1) - 1) / no_chunks.max(1));
+        (0..self.len()).step_by(chunk_size).map(|start| start..self.len().min(start + chunk_size)).collect()
+    }
+}
+
+/// Number of messages in a file, from the index of sealed containers and without decoding messages otherwise
+pub fn count_messages(file_name: &Path) -> io::Result<usize> {
+    match OracleContainer::open(file_name) {
+        Ok(container) => Ok(container.len()),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            let mut reader = ProtobufFileReader::new(file_name)?;
+            let mut count = 0;
+            while reader.has_data() {
+                reader.skip_next()?;
+                count += 1;
+            }
+            Ok(count)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, sync::Arc};
+
+    use prost::Message;
+
+    use crate::{oracle_container::{count_messages, create, read_layout, seal, OracleContainer, CONTAINER_MAGIC, SCHEMA_VERSION, TRAILER_LEN}, load_decisions, Assignment, Decision, OracleHeader, OracleIndex, ProtobufFileReader, ProtobufFileSink, Scheme};
+
+    #[test]
+    fn test_container_random_access() {
+        let file_name = std::env::temp_dir().join(format!("oracle_container_test_{}.proto.bin", std::process::id()));
+        let header = OracleHeader {
+            schema_version: SCHEMA_VERSION,
+            no_dimensions: 3,
+            app_regions: vec!["aws-eu-west-1".to_string()],
+            object_stores: vec!["aws-eu-west-1-General Purpose".to_string()],
+            ..Default::default()
+        };
+        let messages = (0..10).map(|i| "x".repeat(i * 100)).collect::<Vec<_>>();
+
+        let mut sink = create(&file_name, &header, 1024, 64).unwrap();
+        for m in &messages[..5] {
+            sink.write(m.clone()).unwrap();
+        }
+        let offset = sink.offset().unwrap();
+        sink.write("lost".to_string()).unwrap();
+        sink.flush().unwrap();
+        seal(&file_name).unwrap();
+
+        // Resuming drops the index with everything after the offset
+        let mut sink = ProtobufFileSink::open_at(&file_name, offset, 1024, 64).unwrap();
+        for m in &messages[5..] {
+            sink.write(m.clone()).unwrap();
+        }
+        sink.flush().unwrap();
+        assert!(OracleContainer::open(&file_name).is_err());
+        assert_eq!(count_messages(&file_name).unwrap(), 10);
+
+        seal(&file_name).unwrap();
+        seal(&file_name).unwrap();
+        let container = OracleContainer::open(&file_name).unwrap();
+        assert_eq!(container.header(), &header);
+        assert_eq!(container.len(), 10);
+        assert_eq!(container.get::<String>(7).unwrap(), messages[7]);
+        assert_eq!(container.get::<String>(10).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(container.chunks(3), vec![0..4, 4..8, 8..10]);
+        assert_eq!(container.iter_range::<String>(8..10).collect::<io::Result<Vec<_>>>().unwrap(), messages[8..]);
+
+        // Sequential readers skip the header and index
+        let reader = ProtobufFileReader::new(&file_name).unwrap();
+        assert_eq!(reader.header(), Some(&header));
+        assert_eq!(reader.into_iter_all::<String>().collect::<io::Result<Vec<_>>>().unwrap(), messages);
+        assert_eq!(count_messages(&file_name).unwrap(), 10);
+
+        std::fs::remove_file(file_name).unwrap();
+    }
+
+    #[test]
+    fn test_compact_decisions() {
+        let dir = std::env::temp_dir();
+        let compact_file = dir.join(format!("oracle_compact_test_{}.proto.bin.zst", std::process::id()));
+        let names_file = dir.join(format!("oracle_names_test_{}.proto.bin", std::process::id()));
+        let header = OracleHeader {
+            schema_version: SCHEMA_VERSION,
+            no_dimensions: 3,
+            app_regions: vec!["aws-eu-west-1".to_string(), "aws-us-east-1".to_string()],
+            object_stores: vec!["aws-eu-west-1-General Purpose".to_string(), "aws-us-east-1-General Purpose".to_string()],
+            ..Default::default()
+        };
+        let assignment = |app: usize, o: usize| Assignment { app: header.app_regions[app].clone(), object_store: header.object_stores[o].clone() };
+        let expanded = Decision {
+            replication_scheme: Some(Scheme {
+                object_stores: header.object_stores.clone(),
+                app_assignments: vec![assignment(0, 0), assignment(1, 1)],
+                object_store_ids: vec![0, 1],
+                app_object_store_ids: vec![0, 1],
+            }),
+            cost_wl_halfplane: vec![1.0, 2.0, 3.0],
+            ..Default::default()
+        };
+
+        let mut sink = create(&compact_file, &header, 1024, 64).unwrap();
+        let mut compact = expanded.clone();
+        let scheme = compact.replication_scheme.as_mut().unwrap();
+        scheme.object_stores.clear();
+        scheme.app_assignments.clear();
+        sink.write(compact.clone()).unwrap();
+        sink.write(compact).unwrap();
+        sink.flush().unwrap();
+        // Compressed containers are not sealed
+        seal(&compact_file).unwrap();
+        assert_eq!(ProtobufFileReader::new(&compact_file).unwrap().header(), Some(&header));
+
+        // Files without header store the names
+        let mut sink = ProtobufFileSink::new(&names_file, 1024, 64).unwrap();
+        let mut names = expanded.clone();
+        let scheme = names.replication_scheme.as_mut().unwrap();
+        scheme.object_store_ids.clear();
+        scheme.app_object_store_ids.clear();
+        sink.write(names.clone()).unwrap();
+        sink.flush().unwrap();
+
+        let decisions = load_decisions(vec![&compact_file, &names_file], false).unwrap();
+        assert_eq!(decisions[..2], [expanded.clone(), expanded.clone()]);
+        assert_eq!(decisions[2], names);
+
+        // Compaction keeps the app names of the first decision only
+        let decisions = load_decisions(vec![&compact_file], true).unwrap();
+        let apps = |d: &Decision| d.replication_scheme.as_ref().unwrap().app_assignments.iter().map(|a| a.app.clone()).collect::<Vec<_>>();
+        assert_eq!(apps(&decisions[0]), header.app_regions);
+        assert!(apps(&decisions[1]).is_empty());
+
+        std::fs::remove_file(compact_file).unwrap();
+        std::fs::remove_file(names_file).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_index() {
+        let file_name = std::env::temp_dir().join(format!("oracle_corrupt_test_{}.proto.bin", std::process::id()));
+        let mut sink = create(&file_name, &OracleHeader::default(), 1024, 64).unwrap();
+        for i in 0..3 {
+            sink.write(format!("message {}", i)).unwrap();
+        }
+        sink.flush().unwrap();
+        seal(&file_name).unwrap();
+        let sealed = std::fs::read(&file_name).unwrap();
+        let layout = read_layout(&sealed).unwrap().unwrap();
+        assert_eq!(layout.offsets.as_ref().map(|o| o.len()), Some(3));
+
+        let trailer_start = sealed.len() - TRAILER_LEN;
+        let with_index_offset = |index_offset: u64| {
+            let mut buf = sealed.clone();
+            buf[trailer_start..trailer_start + 8].copy_from_slice(&index_offset.to_le_bytes());
+            buf
+        };
+        // Index offset beyond the trailer or pointing into the messages instead of the index
+        assert_eq!(read_layout(&with_index_offset(sealed.len() as u64)).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(read_layout(&with_index_offset(layout.offsets.as_ref().unwrap()[1])).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Offsets in the index beyond the messages
+        let mut buf = sealed[..layout.data_end as usize].to_vec();
+        buf.extend(OracleIndex { offsets: vec![layout.data_start, layout.data_end + 1] }.encode_length_delimited_to_vec());
+        buf.extend(layout.data_end.to_le_bytes());
+        buf.extend(CONTAINER_MAGIC);
+        assert_eq!(read_layout(&buf).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        std::fs::write(&file_name, with_index_offset(0)).unwrap();
+        assert_eq!(OracleContainer::open(&file_name).err().unwrap().kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(file_name).unwrap();
+    }
+
+    #[test]
+    fn test_open_cached() {
+        let file_name = std::env::temp_dir().join(format!("oracle_cached_test_{}.proto.bin", std::process::id()));
+        let write = |messages: &[&str]| {
+            let mut sink = create(&file_name, &OracleHeader::default(), 1024, 64).unwrap();
+            for m in messages {
+                sink.write(m.to_string()).unwrap();
+            }
+            sink.flush().unwrap();
+            seal(&file_name).unwrap();
+        };
+
+        write(&["a", "b"]);
+        let container = OracleContainer::open_cached(&file_name).unwrap();
+        assert!(Arc::ptr_eq(&container, &OracleContainer::open_cached(&file_name).unwrap()));
+
+        // A rewritten file is opened again
+        write(&["a", "b", "c"]);
+        let container = OracleContainer::open_cached(&file_name).unwrap();
+        assert_eq!(container.len(), 3);
+        assert_eq!(container.get::<String>(2).unwrap(), "c");
+
+        std::fs::remove_file(file_name).unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+//! Export of oracles to Parquet with one row per decision, e.g., for analysis with pandas or polars.
+//!
+//! Columns: `id`, the halfplane coefficients named by dimension (`intercept`, `storage`, `put`,
+//! `get_<region>`, `ingress_<region>`, `egress_<region>`, `cost`), the write choice as list of object stores,
+//! and the assigned object store of each app region as `assignment_<region>`.
+
+use std::{fs::File, io, path::Path, sync::Arc};
+
+use arrow_array::{builder::{ArrayBuilder, Float64Builder, ListBuilder, StringBuilder, UInt64Builder}, ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::{arrow::ArrowWriter, basic::{Compression, ZstdLevel}, file::properties::WriterProperties};
+
+use crate::{dimension_names, Decision, ProtobufFileReader};
+
+/// Decisions per record batch, bounds the memory of the export
+const BATCH_SIZE: usize = 64 * 1024;
+
+fn other<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+fn invalid_data<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn schema(app_regions: &[String]) -> SchemaRef {
+    let mut fields = vec![Field::new("id", DataType::UInt64, false)];
+    fields.extend(dimension_names(app_regions).into_iter().map(|name| Field::new(name, DataType::Float64, false)));
+    fields.push(Field::new("write_choice", DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))), false));
+    fields.extend(app_regions.iter().map(|r| Field::new(format!("assignment_{}", r), DataType::Utf8, false)));
+    Arc::new(Schema::new(fields))
+}
+
+struct Export {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+    app_regions: Vec<String>,
+    ids: UInt64Builder,
+    dimensions: Vec<Float64Builder>,
+    write_choices: ListBuilder<StringBuilder>,
+    assignments: Vec<StringBuilder>,
+    no_decisions: usize,
+}
+
+impl Export {
+    fn new(output: &Path, app_regions: Vec<String>) -> io::Result<Self> {
+        let schema = schema(&app_regions);
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .build();
+        let writer = ArrowWriter::try_new(File::create(output)?, schema.clone(), Some(properties)).map_err(other)?;
+
+        Ok(Self {
+            writer,
+            schema,
+            ids: UInt64Builder::new(),
+            dimensions: (0..3 * app_regions.len() + 4).map(|_| Float64Builder::new()).collect(),
+            write_choices: ListBuilder::new(StringBuilder::new()),
+            assignments: app_regions.iter().map(|_| StringBuilder::new()).collect(),
+            app_regions,
+            no_decisions: 0,
+        })
+    }
+
+    /// Appends a decision with names, i.e., expanded with the dictionaries of its oracle
+    fn append(&mut self, decision: &Decision) -> io::Result<()> {
+        if decision.cost_wl_halfplane.len() != self.dimensions.len() {
+            return Err(invalid_data(format!("Decision {} has {} dimensions, expected {}", self.no_decisions, decision.cost_wl_halfplane.len(), self.dimensions.len())));
+        }
+        let scheme = decision.replication_scheme.clone().unwrap_or_default();
+        if scheme.app_assignments.len() != self.assignments.len() {
+            return Err(invalid_data(format!("Decision {} assigns {} app regions, expected {}", self.no_decisions, scheme.app_assignments.len(), self.assignments.len())));
+        }
+
+        self.ids.append_value(self.no_decisions as u64);
+        for (builder, &coefficient) in self.dimensions.iter_mut().zip(&decision.cost_wl_halfplane) {
+            builder.append_value(coefficient);
+        }
+        for object_store in &scheme.object_stores {
+            self.write_choices.values().append_value(object_store);
+        }
+        self.write_choices.append(true);
+        for (builder, assignment) in self.assignments.iter_mut().zip(&scheme.app_assignments) {
+            builder.append_value(&assignment.object_store);
+        }
+
+        self.no_decisions += 1;
+        if self.ids.len() >= BATCH_SIZE {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    fn write_batch(&mut self) -> io::Result<()> {
+        let mut columns: Vec<ArrayRef> = vec![Arc::new(self.ids.finish())];
+        columns.extend(self.dimensions.iter_mut().map(|b| Arc::new(b.finish()) as ArrayRef));
+        columns.push(Arc::new(self.write_choices.finish()));
+        columns.extend(self.assignments.iter_mut().map(|b| Arc::new(b.finish()) as ArrayRef));
+
+        let batch = RecordBatch::try_new(self.schema.clone(), columns).map_err(invalid_data)?;
+        self.writer.write(&batch).map_err(other)
+    }
+
+    fn close(mut self) -> io::Result<usize> {
+        if !self.ids.is_empty() {
+            self.write_batch()?;
+        }
+        self.writer.close().map_err(other)?;
+        Ok(self.no_decisions)
+    }
+}
+
+/// Exports the decisions of the oracle files to a Parquet file and returns the number of decisions.
+/// Ids number the decisions in the order of the files. All files must have the same app regions.
+pub fn export_parquet(paths: &[&Path], output: &Path) -> io::Result<usize> {
+    let mut export: Option<Export> = None;
+
+    for path in paths {
+        let reader = ProtobufFileReader::new(path)?;
+        let dictionary = reader.header().cloned();
+        let mut decisions = reader.into_iter_all::<Decision>()
+            .map(|decision| match &dictionary {
+                Some(dictionary) => decision.and_then(|d| d.expand(dictionary)),
+                None => decision,
+            })
+            .peekable();
+
+        // Files without header name the app regions in every decision
+        let app_regions = match (&dictionary, decisions.peek()) {
+            (Some(dictionary), _) => dictionary.app_regions.clone(),
+            (None, Some(Ok(first))) => first.replication_scheme.iter()
+                .flat_map(|s| s.app_assignments.iter().map(|a| a.app.clone()))
+                .collect(),
+            (None, Some(Err(_))) => return decisions.next().unwrap().map(|_| 0),
+            (None, None) => continue,
+        };
+
+        match &export {
+            Some(export) if export.app_regions != app_regions => {
+                return Err(invalid_data(format!("App regions of {} differ from the previous oracle files", path.to_string_lossy())));
+            },
+            Some(_) => {},
+            None => export = Some(Export::new(output, app_regions)?),
+        }
+        let export = export.as_mut().unwrap();
+
+        for decision in decisions {
+            export.append(&decision?)?;
+        }
+    }
+
+    match export {
+        Some(export) => export.close(),
+        None => Export::new(output, Vec::new())?.close(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{oracle_container::{create, seal, SCHEMA_VERSION}, OracleHeader, Scheme};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    #[test]
+    fn test_export_parquet() {
+        let dir = std::env::temp_dir();
+        let oracle_file = dir.join(format!("parquet_export_test_{}.proto.bin", std::process::id()));
+        let output = dir.join(format!("parquet_export_test_{}.parquet", std::process::id()));
+        let header = OracleHeader {
+            schema_version: SCHEMA_VERSION,
+            no_dimensions: 10,
+            app_regions: vec!["aws-eu-west-1".to_string(), "aws-us-east-1".to_string()],
+            object_stores: vec!["aws-eu-west-1-General Purpose".to_string()],
+            ..Default::default()
+        };
+        let decision = Decision {
+            replication_scheme: Some(Scheme { object_store_ids: vec![0], app_object_store_ids: vec![0, 0], ..Default::default() }),
+            cost_wl_halfplane: (0..10).map(|x| x as f64).collect(),
+            ..Default::default()
+        };
+
+        let mut sink = create(&oracle_file, &header, 1024, 64).unwrap();
+        for _ in 0..3 {
+            sink.write(decision.clone()).unwrap();
+        }
+        sink.flush().unwrap();
+        seal(&oracle_file).unwrap();
+
+        assert_eq!(export_parquet(&[&oracle_file, &oracle_file], &output).unwrap(), 6);
+
+        let reader = SerializedFileReader::new(File::open(&output).unwrap()).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 6);
+        let columns = metadata.schema_descr().columns().iter().map(|c| c.path().string()).collect::<Vec<_>>();
+        assert_eq!(columns[..4], ["id", "intercept", "storage", "put"]);
+        assert!(columns.contains(&"egress_aws-us-east-1".to_string()));
+        assert!(columns.contains(&"assignment_aws-eu-west-1".to_string()));
+
+        std::fs::remove_file(&oracle_file).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+}
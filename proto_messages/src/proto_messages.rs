@@ -1,3 +1,6 @@
+pub mod oracle_container;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
 pub mod protobuf_file_reader;
 pub mod protobuf_file_sink;
 
@@ -6,17 +9,19 @@ pub use protobuf_file_sink::ProtobufFileSink;
 
 #[allow(non_snake_case)]
 mod messages {
-    use crate::{ProtobufFileReader, ProtobufFileSink};
+    use crate::{oracle_container::{count_messages, OracleContainer}, ProtobufFileReader, ProtobufFileSink};
     use hydroflow::futures::SinkExt;
     use prost::Message;
     #[cfg(feature = "python-module")]
     use pyo3::prelude::*;
     use rayon::prelude::*;
-    use std::{collections::HashMap, path::Path, fs};
+    use sha2::{Digest, Sha256};
+    use std::{collections::{HashMap, HashSet}, path::Path, fs, io};
 
     include!(concat!(env!("OUT_DIR"), "/skypie.rs"));
 
     impl Wrapper {
+        #[allow(clippy::too_many_arguments)]
         pub fn new(
             object_stores: Vec<String>,
             candidate_partitions: Vec<String>,
@@ -44,16 +49,99 @@ mod messages {
 
             let tier_advise = Some(TierAdvise::new(replication_factor, run));
 
-            Self { tier_advise }
+            Self { tier_advise, provenance: None, id_mapping: None }
         }
 
-        pub fn combine(&mut self, other: &Self, original_path: &Path, output_path: &Path, output_path_suffix: &Path, replace_with_candidates: bool) {
-            println!("Combining wrapper");
+        /// Combines the runs of the other stats into the runs with the same replication factor and name,
+        /// or adds them. The partitions of the other stats must already be relocated, see `relocate`.
+        /// Keeps the provenance and id mapping of the first stats with one.
+        pub fn combine(&mut self, other: &Self) -> io::Result<()> {
+            tracing::debug!("Combining wrapper");
 
-            let tier_advise = self.tier_advise.as_mut().unwrap();
-            let other_tier_advise = other.tier_advise.as_ref().unwrap();
+            if let Some(other_tier_advise) = &other.tier_advise {
+                self.tier_advise.get_or_insert_with(Default::default).combine(other_tier_advise)?;
+            }
+            if self.provenance.is_none() {
+                self.provenance = other.provenance.clone();
+            }
+            match (&self.id_mapping, &other.id_mapping) {
+                (None, _) => self.id_mapping = other.id_mapping.clone(),
+                (Some(id_mapping), Some(other_id_mapping)) if id_mapping != other_id_mapping => {
+                    tracing::warn!("Combined oracles have different ids, keeping the ids of the first oracle");
+                },
+                _ => {},
+            }
+            Ok(())
+        }
+
+        /// Checks that the oracle was computed from the input files, compared by content.
+        /// Logs a warning per mismatch, or fails with `InvalidData` if mismatches are refused.
+        /// Oracles without provenance, e.g., of older versions, are only warned about.
+        pub fn check_provenance(&self, input_files: &[InputFile], check: ProvenanceCheck) -> io::Result<()> {
+            let mismatches = match &self.provenance {
+                Some(provenance) => provenance.mismatches(input_files),
+                None => {
+                    if check != ProvenanceCheck::Ignore {
+                        tracing::warn!("Oracle has no provenance, cannot check its input files");
+                    }
+                    return Ok(());
+                },
+            };
+
+            match check {
+                ProvenanceCheck::Refuse if !mismatches.is_empty() => {
+                    Err(io::Error::new(io::ErrorKind::InvalidData, format!("Oracle was computed from different inputs: {}", mismatches.join("; "))))
+                },
+                ProvenanceCheck::Ignore => Ok(()),
+                _ => {
+                    for mismatch in mismatches {
+                        tracing::warn!(%mismatch, "Oracle was computed from different inputs");
+                    }
+                    Ok(())
+                },
+            }
+        }
+
+        /// Runs of all replication factors
+        pub fn runs(&self) -> impl Iterator<Item = &Run> {
+            self.tier_advise.iter()
+                .flat_map(|t| t.replication_factor.values())
+                .flat_map(|s| s.runs.values())
+        }
+
+        pub fn runs_mut(&mut self) -> impl Iterator<Item = &mut Run> {
+            self.tier_advise.iter_mut()
+                .flat_map(|t| t.replication_factor.values_mut())
+                .flat_map(|s| s.runs.values_mut())
+        }
+
+        /// Optimal partitions of all runs, relative to the directory of the stats file or absolute
+        pub fn optimal_partitions(&self) -> Vec<&String> {
+            let mut seen = HashSet::new();
+            self.runs()
+                .flat_map(|r| r.optimal_partitions_by_optimizer.values())
+                .flat_map(|o| o.optimal_partitions.iter())
+                .filter(|p| seen.insert(*p))
+                .collect()
+        }
+
+        /// Candidate partitions of all runs, relative to the directory of the stats file or absolute
+        pub fn candidate_partitions(&self) -> Vec<&String> {
+            let mut seen = HashSet::new();
+            self.runs()
+                .flat_map(|r| r.candidate_partitions.iter())
+                .filter(|p| seen.insert(*p))
+                .collect()
+        }
 
-            tier_advise.combine(other_tier_advise, original_path, output_path, output_path_suffix, replace_with_candidates);
+        /// Places the partitions of all runs in the output directory, see `Run::relocate`
+        pub fn relocate(&mut self, original_path: &Path, output_path: &Path, output_path_suffix: &Path, placement: PartitionPlacement, replace_with_candidates: bool) -> io::Result<()> {
+            for setting in self.tier_advise.iter_mut().flat_map(|t| t.replication_factor.values_mut()) {
+                for run in setting.runs.values_mut() {
+                    run.relocate(original_path, output_path, output_path_suffix, placement, replace_with_candidates)?;
+                }
+            }
+            Ok(())
         }
 
         /// Saves the protobuf message to a binary file at the given path.
@@ -69,7 +157,6 @@ mod messages {
             let mut sink = ProtobufFileSink::new(file_name, capa, 0).unwrap();
             sink.start_send_unpin(self.clone()).unwrap();
             sink.flush().unwrap();
-            let _: hydroflow::futures::sink::Close<'_, ProtobufFileSink, Self> = sink.close();
         }
     }
 
@@ -84,27 +171,37 @@ mod messages {
             Self { replication_factor }
         }
 
-        pub fn combine(&mut self, other: &Self, original_path: &Path, output_path: &Path, output_path_suffix: &Path, replace_with_candidates: bool) {
-            println!("Combining tier advise");
-            let self_first_entry = self.replication_factor.iter_mut().next().unwrap();
-            for (_replication_factor, setting) in &other.replication_factor {
-                self_first_entry.1.combine(setting, original_path, output_path, output_path_suffix, replace_with_candidates);
+        pub fn combine(&mut self, other: &Self) -> io::Result<()> {
+            tracing::debug!("Combining tier advise");
+            for (replication_factor, setting) in &other.replication_factor {
+                match self.replication_factor.get_mut(replication_factor) {
+                    Some(my_setting) => my_setting.combine(setting)?,
+                    None => {
+                        self.replication_factor.insert(*replication_factor, setting.clone());
+                    }
+                }
             }
+            Ok(())
         }
     }
 
     impl Setting {
-        pub fn combine(&mut self, other: &Self, original_path: &Path, output_path: &Path, output_path_suffix: &Path, replace_with_candidates: bool) {
-            println!("Combining settings");
-            let my_first_run = self.runs.iter_mut().next().unwrap();
-            for (_run_name, run) in &other.runs {
-                
-                    my_first_run.1.combine(run, original_path, output_path, output_path_suffix, replace_with_candidates);
+        pub fn combine(&mut self, other: &Self) -> io::Result<()> {
+            tracing::debug!("Combining settings");
+            for (run_name, run) in &other.runs {
+                match self.runs.get_mut(run_name) {
+                    Some(my_run) => my_run.combine(run)?,
+                    None => {
+                        self.runs.insert(run_name.clone(), run.clone());
+                    }
+                }
             }
+            Ok(())
         }
     }
 
     impl Run {
+        #[allow(clippy::too_many_arguments)]
         pub fn new(
             max_replication_factor: u64,
             min_replication_factor: u64,
@@ -125,7 +222,7 @@ mod messages {
             let object_stores_considered = object_stores;
 
             let optimal_partitions_by_optimizer = HashMap::from_iter(vec![(
-                optimizer_name.into(),
+                optimizer_name,
                 OptimalByOptimizer::new(optimal_partitions, optimizer_type),
             )]);
 
@@ -141,29 +238,61 @@ mod messages {
                 object_stores_considered,
                 candidate_partitions,
                 optimal_partitions_by_optimizer,
+                no_pruned_write_choices: Some(0),
+                no_pruned_candidates: Some(0),
             }
         }
 
-        pub fn combine(&mut self, other: &Self, original_path: &Path, output_path: &Path, output_path_suffix: &Path, replace_with_candidates: bool) {
-            println!("Combining runs");
-
-            self.enumerator_time_ns = Some(self.enumerator_time_ns.unwrap() + other.enumerator_time_ns.unwrap());
-            self.min_replication_factor = Some(self.min_replication_factor.unwrap().min(other.min_replication_factor.unwrap()));
-            self.max_replication_factor = Some(self.max_replication_factor.unwrap().max(other.max_replication_factor.unwrap()));
-            assert_eq!(self.no_app_regions.unwrap(), other.no_app_regions.unwrap());
-            assert_eq!(self.no_dimensions.unwrap(), other.no_dimensions.unwrap());
-            self.no_facets = Some(self.no_facets.unwrap() + other.no_facets.unwrap());
-            self.partitioner_time_ns = Some(self.partitioner_time_ns.unwrap() + other.partitioner_time_ns.unwrap());
-            //self.no_object_stores = Some(self.no_object_stores.unwrap().max(other.no_object_stores.unwrap()));
-            assert_eq!(self.object_stores_considered, other.object_stores_considered);
+        pub fn combine(&mut self, other: &Self) -> io::Result<()> {
+            tracing::debug!("Combining runs");
 
-            let candidates = if replace_with_candidates {Some(&other.candidate_partitions)} else {None};
+            if self.no_app_regions != other.no_app_regions || self.no_dimensions != other.no_dimensions {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                    "Conflicting dimensions of runs: {:?} app regions and {:?} dimensions vs. {:?} app regions and {:?} dimensions",
+                    self.no_app_regions, self.no_dimensions, other.no_app_regions, other.no_dimensions)));
+            }
+            if self.object_stores_considered != other.object_stores_considered {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Conflicting object stores of runs"));
+            }
 
-            let my_first_optimizer = self.optimal_partitions_by_optimizer.iter_mut().next().unwrap();
+            self.enumerator_time_ns = Some(self.enumerator_time_ns.unwrap_or(0) + other.enumerator_time_ns.unwrap_or(0));
+            self.min_replication_factor = match (self.min_replication_factor, other.min_replication_factor) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            self.max_replication_factor = self.max_replication_factor.max(other.max_replication_factor);
+            self.no_facets = Some(self.no_facets.unwrap_or(0) + other.no_facets.unwrap_or(0));
+            self.partitioner_time_ns = Some(self.partitioner_time_ns.unwrap_or(0) + other.partitioner_time_ns.unwrap_or(0));
+            // Older runs do not track pruning
+            self.no_pruned_write_choices = Some(self.no_pruned_write_choices.unwrap_or(0) + other.no_pruned_write_choices.unwrap_or(0));
+            self.no_pruned_candidates = Some(self.no_pruned_candidates.unwrap_or(0) + other.no_pruned_candidates.unwrap_or(0));
+            self.candidate_partitions.extend(other.candidate_partitions.iter().cloned());
+
+            for (optimizer_name, optimal_by_optimizer) in &other.optimal_partitions_by_optimizer {
+                match self.optimal_partitions_by_optimizer.get_mut(optimizer_name) {
+                    Some(my_optimizer) => my_optimizer.combine(optimal_by_optimizer),
+                    None => {
+                        self.optimal_partitions_by_optimizer.insert(optimizer_name.clone(), optimal_by_optimizer.clone());
+                    }
+                }
+            }
+            Ok(())
+        }
 
-            for (_optimizer_name, optimal_by_optimizer) in &other.optimal_partitions_by_optimizer {
-                my_first_optimizer.1.combine(optimal_by_optimizer, original_path, output_path, output_path_suffix, candidates);
+        /// Places the optimal partitions of the run under the suffix of the output directory and renames them accordingly,
+        /// the candidate partitions replace the optimal partitions with `replace_with_candidates`.
+        /// Candidate partitions are not placed, so the relocated run has none.
+        pub fn relocate(&mut self, original_path: &Path, output_path: &Path, output_path_suffix: &Path, placement: PartitionPlacement, replace_with_candidates: bool) -> io::Result<()> {
+            let candidates = std::mem::take(&mut self.candidate_partitions);
+
+            for optimal_by_optimizer in self.optimal_partitions_by_optimizer.values_mut() {
+                let partitions = if replace_with_candidates { &candidates } else { &optimal_by_optimizer.optimal_partitions };
+                let placed = partitions.par_iter()
+                    .map(|partition| placement.place(partition, original_path, output_path, output_path_suffix))
+                    .collect::<io::Result<Vec<_>>>()?;
+                optimal_by_optimizer.optimal_partitions = placed;
             }
+            Ok(())
         }
     }
 
@@ -183,34 +312,177 @@ mod messages {
             }
         }
 
-        pub fn combine(&mut self, other: &Self, original_path: &Path, output_path: &Path, output_path_suffix: &Path, candidates: Option<&Vec<String>>) {
-            println!("Combining optimal partitions");
-            //assert_eq!(self.optimizer_type, other.optimizer_type);
-            self.no_facets = Some(self.no_facets.unwrap() + other.no_facets.unwrap());
-            self.partitioner_time_ns = Some(self.partitioner_time_ns.unwrap() + other.partitioner_time_ns.unwrap());
-            self.partitioner_computation_time_ns = Some(self.partitioner_computation_time_ns.unwrap() + other.partitioner_computation_time_ns.unwrap());
+        pub fn combine(&mut self, other: &Self) {
+            tracing::debug!("Combining optimal partitions");
+            self.no_facets = Some(self.no_facets.unwrap_or(0) + other.no_facets.unwrap_or(0));
+            self.partitioner_time_ns = Some(self.partitioner_time_ns.unwrap_or(0) + other.partitioner_time_ns.unwrap_or(0));
+            self.partitioner_computation_time_ns = Some(self.partitioner_computation_time_ns.unwrap_or(0) + other.partitioner_computation_time_ns.unwrap_or(0));
+            self.optimal_partitions.extend(other.optimal_partitions.iter().cloned());
+        }
+    }
 
-            let partitions = if let Some(candidates) = candidates {
-                candidates
-            } else {
-                &other.optimal_partitions
+    /// Placement of the partition files of merged oracles
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum PartitionPlacement {
+        /// Refer to the original files by their absolute path
+        Reference,
+        /// Hard-link the original files into the output directory, copy them if linking fails, e.g., across file systems
+        HardLink,
+        /// Copy the original files into the output directory
+        Copy,
+    }
+
+    impl std::str::FromStr for PartitionPlacement {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "reference" => Ok(PartitionPlacement::Reference),
+                "hard-link" => Ok(PartitionPlacement::HardLink),
+                "copy" => Ok(PartitionPlacement::Copy),
+                _ => Err(format!("Unknown placement {}, expected reference, hard-link or copy", s)),
+            }
+        }
+    }
+
+    impl PartitionPlacement {
+        /// Places the partition of the original directory under the suffix of the output directory,
+        /// returns the name of the partition relative to the output directory or its absolute path for references
+        pub fn place(&self, partition: &str, original_path: &Path, output_path: &Path, output_path_suffix: &Path) -> io::Result<String> {
+            let original_file = original_path.join(partition);
+            let name = match self {
+                PartitionPlacement::Reference => fs::canonicalize(&original_file)?,
+                PartitionPlacement::HardLink | PartitionPlacement::Copy => {
+                    let output_suffix = output_path_suffix.join(partition);
+                    let output_file = output_path.join(&output_suffix);
+                    if let Some(parent) = output_file.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+
+                    let linked = *self == PartitionPlacement::HardLink && {
+                        if output_file.exists() {
+                            fs::remove_file(&output_file)?;
+                        }
+                        match fs::hard_link(&original_file, &output_file) {
+                            Ok(()) => true,
+                            Err(e) => {
+                                tracing::warn!(from = %original_file.to_string_lossy(), %e, "Failed to hard-link file, copying it");
+                                false
+                            }
+                        }
+                    };
+                    if !linked {
+                        tracing::info!(from = %original_file.to_string_lossy(), to = %output_file.to_string_lossy(), "Copying file");
+                        fs::copy(&original_file, &output_file)?;
+                    }
+                    output_suffix
+                }
             };
+            Ok(name.to_string_lossy().to_string())
+        }
+    }
+
+    impl InputFile {
+        /// Input file with the SHA-256 of its content
+        pub fn hash(kind: &str, path: &Path) -> io::Result<Self> {
+            let mut hasher = Sha256::new();
+            io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+            let sha256 = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+            Ok(InputFile { kind: kind.to_string(), file_name: path.to_string_lossy().to_string(), sha256 })
+        }
+
+        /// Price and latency inputs of the precomputation
+        pub fn of_inputs(network_file: &Path, object_store_file: &Path, latency_file: Option<&Path>) -> io::Result<Vec<Self>> {
+            let mut input_files = vec![Self::hash("network", network_file)?, Self::hash("object_store", object_store_file)?];
+            if let Some(latency_file) = latency_file {
+                input_files.push(Self::hash("latency", latency_file)?);
+            }
+            Ok(input_files)
+        }
+    }
 
-            for partition in partitions {
-                let output_suffix = output_path_suffix.join(partition);
-                self.optimal_partitions.push(output_suffix.to_str().unwrap().to_string());
+    impl IdMapping {
+        /// Ids of the names in the dictionaries of an oracle header
+        pub fn of_header(header: &OracleHeader) -> Self {
+            Self {
+                app_regions: header.app_regions.iter().enumerate().map(|(i, name)| (name.clone(), header.app_region_id(i))).collect(),
+                object_stores: header.object_stores.iter().enumerate().map(|(i, name)| (name.clone(), header.object_store_id(i))).collect(),
             }
+        }
+    }
 
-            partitions.par_iter().for_each(|partition|{
-                let output_suffix = output_path_suffix.join(partition);
+    impl OracleHeader {
+        /// Id of the app region at the index of the dictionary
+        pub fn app_region_id(&self, index: usize) -> u32 {
+            self.app_region_ids.get(index).copied().unwrap_or(index as u32)
+        }
 
-                // Copy file from original location to output location
-                let original_file = original_path.join(partition);
-                let output_file = output_path.join(output_suffix);
-                let copy_error_msg = format!("Failed to copy file {} to {}", original_file.to_string_lossy(), output_file.to_string_lossy());
-                println!("Copying file {} to {}", original_file.to_string_lossy(), output_file.to_string_lossy());
-                fs::copy(original_file, output_file).expect(copy_error_msg.as_str());
-            });
+        /// Id of the object store at the index of the dictionary
+        pub fn object_store_id(&self, index: usize) -> u32 {
+            self.object_store_ids.get(index).copied().unwrap_or(index as u32)
+        }
+
+        /// Index of the object store with the id in the dictionary, ids can have gaps, e.g., of an id catalogue
+        pub fn object_store_index(&self, id: u32) -> Option<usize> {
+            if self.object_store_ids.is_empty() {
+                Some(id as usize).filter(|&i| i < self.object_stores.len())
+            } else {
+                self.object_store_ids.binary_search(&id).ok()
+            }
+        }
+
+        /// Fails with `InvalidData` if the ids do not match the dictionaries or are not ascending
+        pub fn validate(&self) -> io::Result<()> {
+            for (kind, names, ids) in [("app region", &self.app_regions, &self.app_region_ids), ("object store", &self.object_stores, &self.object_store_ids)] {
+                if !ids.is_empty() && ids.len() != names.len() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} {} ids for {} {}s in the dictionary", ids.len(), kind, names.len(), kind)));
+                }
+                if ids.windows(2).any(|w| w[0] >= w[1]) {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("The {} ids of the dictionary are not ascending", kind)));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Provenance {
+        /// Differences between the input files of the provenance and the given input files by kind, compared by content
+        pub fn mismatches(&self, input_files: &[InputFile]) -> Vec<String> {
+            let mut mismatches = Vec::new();
+            for input_file in input_files {
+                match self.input_files.iter().find(|f| f.kind == input_file.kind) {
+                    Some(f) if f.sha256 == input_file.sha256 => {},
+                    Some(f) => mismatches.push(format!("{} file {} differs from {} of the oracle", input_file.kind, input_file.file_name, f.file_name)),
+                    None => mismatches.push(format!("{} file {} is given, but the oracle was computed without", input_file.kind, input_file.file_name)),
+                }
+            }
+            for f in &self.input_files {
+                if !input_files.iter().any(|i| i.kind == f.kind) {
+                    mismatches.push(format!("{} file {} of the oracle is not given", f.kind, f.file_name));
+                }
+            }
+            mismatches
+        }
+    }
+
+    /// Handling of an oracle that is used with different inputs than it was computed from
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum ProvenanceCheck {
+        Ignore,
+        Warn,
+        Refuse,
+    }
+
+    impl std::str::FromStr for ProvenanceCheck {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "ignore" => Ok(ProvenanceCheck::Ignore),
+                "warn" => Ok(ProvenanceCheck::Warn),
+                "refuse" => Ok(ProvenanceCheck::Refuse),
+                _ => Err(format!("Unknown provenance check {}, expected \"ignore\", \"warn\" or \"refuse\"", s)),
+            }
         }
     }
 
@@ -230,6 +502,45 @@ mod messages {
             self.app_assignments = self.app_assignments.into_iter().map(|x| x.compact(is_first)).collect();
             self
         }
+
+        /// Fills in the names of the compact encoding from the dictionaries of the oracle header.
+        /// Schemes with names, e.g., of files written before the compact encoding, stay as they are.
+        /// Fails with `InvalidData` if an id is not in the dictionaries.
+        pub fn expand(mut self, dictionary: &OracleHeader) -> io::Result<Scheme> {
+            let name = |o: u32| dictionary.object_store_index(o).map(|i| dictionary.object_stores[i].clone())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Object store id {} not in the dictionary of {} object stores", o, dictionary.object_stores.len())));
+            if self.object_stores.is_empty() {
+                self.object_stores = self.object_store_ids.iter().map(|&o| name(o)).collect::<io::Result<_>>()?;
+            }
+            if self.app_assignments.is_empty() && !self.app_object_store_ids.is_empty() {
+                if self.app_object_store_ids.len() != dictionary.app_regions.len() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} app assignments for {} app regions in the dictionary", self.app_object_store_ids.len(), dictionary.app_regions.len())));
+                }
+                self.app_assignments = dictionary.app_regions.iter().zip(&self.app_object_store_ids)
+                    .map(|(app, &o)| Ok(Assignment { app: app.clone(), object_store: name(o)? }))
+                    .collect::<io::Result<_>>()?;
+            }
+            Ok(self)
+        }
+
+        /// Replaces the names by their ids in the dictionaries of the oracle header, the inverse of `expand`.
+        /// Fails with `InvalidData` if a name is not in the dictionaries.
+        pub fn encode(mut self, dictionary: &OracleHeader) -> io::Result<Scheme> {
+            let id = |name: &String| dictionary.object_stores.iter().position(|o| o == name).map(|i| dictionary.object_store_id(i))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Object store {} not in the dictionary", name)));
+            if !self.object_stores.is_empty() {
+                self.object_store_ids = self.object_stores.iter().map(id).collect::<io::Result<_>>()?;
+                self.object_stores.clear();
+            }
+            if !self.app_assignments.is_empty() {
+                if self.app_assignments.iter().map(|a| &a.app).ne(dictionary.app_regions.iter()) {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "App assignments do not match the app regions of the dictionary"));
+                }
+                self.app_object_store_ids = self.app_assignments.iter().map(|a| id(&a.object_store)).collect::<io::Result<_>>()?;
+                self.app_assignments.clear();
+            }
+            Ok(self)
+        }
     }
 
     impl Decision {
@@ -240,92 +551,138 @@ mod messages {
 
             self
         }
+
+        pub fn expand(mut self, dictionary: &OracleHeader) -> io::Result<Decision> {
+            if let Some(replication_scheme) = self.replication_scheme {
+                self.replication_scheme = Some(replication_scheme.expand(dictionary)?);
+            }
+
+            Ok(self)
+        }
+
+        pub fn encode(mut self, dictionary: &OracleHeader) -> io::Result<Decision> {
+            if let Some(replication_scheme) = self.replication_scheme {
+                self.replication_scheme = Some(replication_scheme.encode(dictionary)?);
+            }
+
+            Ok(self)
+        }
     }
 
-    pub fn load_decision_costs(paths: Vec<&Path>) -> Vec<Vec<f32>> {
+    pub fn load_decision_costs(paths: Vec<&Path>) -> io::Result<Vec<Vec<f32>>> {
         let mut res = Vec::new();
 
         for path in paths {
-            let message_iter = ProtobufFileReader::new(path).unwrap().into_iter_all::<Decision>();
+            let message_iter = ProtobufFileReader::new(path)?.into_iter_all::<Decision>();
 
-            res.extend(message_iter.map(|x: Decision| {
+            for x in message_iter {
+                let x: Decision = x?;
                 let len = x.cost_wl_halfplane.len();
-                x.cost_wl_halfplane.into_iter().take(len - 1).map(|x| x as f32).collect::<Vec<f32>>()
-            }));
+                res.push(x.cost_wl_halfplane.into_iter().take(len - 1).map(|x| x as f32).collect::<Vec<f32>>());
+            }
         }
 
-        return res;
+        Ok(res)
     }
 
-    pub fn load_decision_costs_parallel(paths: Vec<&Path>, threads: usize) -> Vec<Vec<f32>> {
+    pub fn load_decision_costs_parallel(paths: Vec<&Path>, threads: usize) -> io::Result<Vec<Vec<f32>>> {
 
         let chunk_size = std::cmp::max(1, paths.len() / threads);
-        let res = paths.chunks(chunk_size).into_iter().par_bridge()
+        paths.chunks(chunk_size).par_bridge()
             .map(|chunk|load_decision_costs(chunk.to_vec()))
-            .reduce(|| vec![], |mut acc: Vec<Vec<f32>>, next| {acc.extend(next); acc});
-
-        return res;
+            .try_reduce(Vec::new, |mut acc: Vec<Vec<f32>>, next| {acc.extend(next); Ok(acc)})
     }
 
-    pub fn load_decisions(paths: Vec<&Path>, compact: bool) -> Vec<Decision> {
+    pub fn load_decisions(paths: Vec<&Path>, compact: bool) -> io::Result<Vec<Decision>> {
         type M = Decision;
 
         // Treat first message compaction differently
         let mut is_first = true;
         let mut decisions = Vec::new();
         for path in paths {
-            let message_iter = ProtobufFileReader::new(path).unwrap().into_iter_all::<M>();
-
-            if compact {
-                // Replace non-essential contend with empty values
-                let compact_message_iter = message_iter.map(|x: M| {
-                    let res = x.compact(is_first);
+            let reader = ProtobufFileReader::new(path)?;
+            let dictionary = reader.header().cloned();
+
+            for x in reader.into_iter_all::<M>() {
+                let mut x: M = x?;
+                // Only the app names of the first decision survive compaction
+                if let Some(dictionary) = &dictionary {
+                    if !compact || is_first {
+                        x = x.expand(dictionary)?;
+                    }
+                }
+
+                if compact {
+                    // Replace non-essential contend with empty values
+                    decisions.push(x.compact(is_first));
                     is_first = false;
-                    res
-                });
-                decisions.extend(compact_message_iter);
-
-            } else {
-                decisions.extend(message_iter);
+                } else {
+                    decisions.push(x);
+                }
             }
         }
 
-        decisions
+        Ok(decisions)
     }
 
-    pub fn count_decisions(paths: Vec<&Path>) -> usize {
-        type M = Decision;
+    /// Counts the decisions via the index of sealed containers, without decoding them
+    pub fn count_decisions(paths: Vec<&Path>) -> io::Result<usize> {
+        paths.into_iter().map(count_messages).sum()
+    }
+
+    /// Loads the decision with the id, i.e., its position in the sealed container, with names.
+    /// The container stays open for loading further decisions.
+    pub fn load_decision(path: &Path, id: usize) -> io::Result<Decision> {
+        let container = OracleContainer::open_cached(path)?;
+        container.get::<Decision>(id)?.expand(container.header())
+    }
+
+    /// Loads the optimal decisions of all runs of the oracle with the stats file, with names
+    pub fn load_optimal_decisions(stats_file: &Path) -> io::Result<Vec<Decision>> {
+        let stats = load_wrapper(stats_file)?;
+        let dir = stats_file.parent().unwrap_or(Path::new(""));
+        let partitions = stats.optimal_partitions().into_iter().map(|p| dir.join(p)).collect::<Vec<_>>();
+        load_decisions(partitions.iter().map(|p| p.as_path()).collect(), false)
+    }
 
-        let decisions = paths.into_iter().map(|path| {
-            let message_iter = ProtobufFileReader::new(path).unwrap().into_iter_all::<M>();
-            message_iter.count()
-        }).sum();
+    /// Names of the halfplane coefficients of decisions in the order of `cost_wl_halfplane`:
+    /// intercept, storage, put, get_<region>, ingress_<region>, egress_<region>, and cost
+    pub fn dimension_names(app_regions: &[String]) -> Vec<String> {
+        let mut names = vec!["intercept".to_string(), "storage".to_string(), "put".to_string()];
+        for prefix in ["get", "ingress", "egress"] {
+            names.extend(app_regions.iter().map(|r| format!("{}_{}", prefix, r)));
+        }
+        names.push("cost".to_string());
+        names
+    }
 
-        decisions
+    /// Header with the dictionaries of the compact encoding, None for files without header
+    pub fn load_oracle_header(path: &Path) -> io::Result<Option<OracleHeader>> {
+        Ok(ProtobufFileReader::new(path)?.header().cloned())
     }
 
-    pub fn load_decisions_parallel(paths: Vec<&Path>, threads: usize, compact: bool) -> Vec<Decision> {
+    pub fn load_decisions_parallel(paths: Vec<&Path>, threads: usize, compact: bool) -> io::Result<Vec<Decision>> {
 
         let chunk_size = std::cmp::max(1, paths.len() / threads);
-        paths.chunks(chunk_size).into_iter().par_bridge()
+        paths.chunks(chunk_size).par_bridge()
             .map(|chunk|load_decisions(chunk.to_vec(), compact))
-            .reduce(|| vec![], |mut acc: Vec<Decision>, next| {acc.extend(next); acc})
+            .try_reduce(Vec::new, |mut acc: Vec<Decision>, next| {acc.extend(next); Ok(acc)})
     }
 
-    pub fn count_decisions_parallel(paths: Vec<&Path>, threads: usize) -> usize {
+    pub fn count_decisions_parallel(paths: Vec<&Path>, threads: usize) -> io::Result<usize> {
 
         let chunk_size = std::cmp::max(1, paths.len() / threads);
-        paths.chunks(chunk_size).into_iter().par_bridge()
+        paths.chunks(chunk_size).par_bridge()
             .map(|chunk|count_decisions(chunk.to_vec()))
-            .sum()
+            .try_reduce(|| 0, |acc, next| Ok(acc + next))
     }
 
-    pub fn load_wrapper(path: &Path) -> Wrapper {
+    pub fn load_wrapper(path: &Path) -> io::Result<Wrapper> {
         type M = Wrapper;
 
-        let mut reader = ProtobufFileReader::new(path).unwrap();
+        let mut reader = ProtobufFileReader::new(path)?;
 
-        reader.read_next::<M>().unwrap()
+        reader.read_next::<M>()
     }
 }
 
@@ -334,18 +691,21 @@ mod python {
     use crate::messages::{
         load_decision_costs, load_decision_costs_parallel,
         load_decisions, load_decisions_parallel, load_wrapper,
-        count_decisions, count_decisions_parallel,
+        count_decisions, count_decisions_parallel, load_decision, load_oracle_header,
         Assignment, Decision, OptimalByOptimizer, Run, Scheme,
-        Setting, TierAdvise, Wrapper,
+        Setting, TierAdvise, Wrapper, OracleHeader, PartitionPlacement,
+        InputFile, Provenance, IdMapping,
     };
     use pyo3::prelude::*;
+    use pyo3::exceptions::{PyUserWarning, PyValueError};
     use pyo3::pymethods;
     use pyo3::wrap_pyfunction;
     use numpy::{PyArray, PyArrayDyn};
     use std::path::Path;
+    use std::io;
     use std::mem::size_of_val;
     use crate::ProtobufFileReader;
-    use rayon::iter::{ParallelBridge,IntoParallelIterator,ParallelIterator};
+    use rayon::iter::{IntoParallelIterator,ParallelIterator};
 
     #[pymethods]
     impl Assignment {
@@ -360,6 +720,10 @@ mod python {
 
     #[pymethods]
     impl Decision {
+        fn expand_py(&self, dictionary: &OracleHeader) -> PyResult<Decision> {
+            Ok(self.clone().expand(dictionary)?)
+        }
+
         fn __repr__(&self) -> String {
             format!("{:?}", self)
         }
@@ -422,8 +786,10 @@ mod python {
 
     #[pymethods]
     impl Wrapper {
-        fn combine_py(&mut self, other: &Self, original_path: &str, output_path: &str, output_path_suffix: &str, replace_with_candidates: bool) {
-            self.combine(other, Path::new(original_path), Path::new(output_path), Path::new(output_path_suffix), replace_with_candidates);
+        fn combine_py(&mut self, other: &Self, original_path: &str, output_path: &str, output_path_suffix: &str, replace_with_candidates: bool) -> PyResult<()> {
+            let mut other = other.clone();
+            other.relocate(Path::new(original_path), Path::new(output_path), Path::new(output_path_suffix), PartitionPlacement::Copy, replace_with_candidates)?;
+            Ok(self.combine(&other)?)
         }
 
         fn save_py(&self, path: &str) {
@@ -443,35 +809,35 @@ mod python {
     #[pyo3(text_signature = "(paths: List[String], compact: bool = False, /)")]
     fn load_decisions_py(paths: Vec<&str>, compact: bool) -> PyResult<Vec<Decision>> {
         let paths = paths.into_iter().map(|p| Path::new(p)).collect();
-        Ok(load_decisions(paths, compact))
+        Ok(load_decisions(paths, compact)?)
     }
 
     #[pyfunction(name = "load_decisions_parallel")]
     #[pyo3(text_signature = "(paths: List[String], threads: int, compact: bool = False, /)")]
     fn load_decisions_parallel_py(paths: Vec<&str>, threads: usize, compact: bool) -> PyResult<Vec<Decision>> {
         let paths = paths.into_iter().map(|p| Path::new(p)).collect();
-        Ok(load_decisions_parallel(paths, threads, compact))
+        Ok(load_decisions_parallel(paths, threads, compact)?)
     }
 
     #[pyfunction(name = "load_decision_costs")]
     #[pyo3(text_signature = "(paths: List[String], /)")]
     fn load_decision_costs_py(paths: Vec<&str>) -> PyResult<Vec<Vec<f32>>> {
         let paths = paths.into_iter().map(|p| Path::new(p)).collect();
-        Ok(load_decision_costs(paths))
+        Ok(load_decision_costs(paths)?)
     }
 
     #[pyfunction(name = "load_decision_costs_parallel")]
     #[pyo3(text_signature = "(paths: List[String], threads: int, /)")]
     fn load_decision_costs_parallel_py(paths: Vec<&str>, threads: usize) -> PyResult<Vec<Vec<f32>>> {
         let paths = paths.into_iter().map(|p| Path::new(p)).collect();
-        Ok(load_decision_costs_parallel(paths, threads))
+        Ok(load_decision_costs_parallel(paths, threads)?)
     }
 
     #[pyfunction(name = "load_decision_costs_parallel_with_size")]
     #[pyo3(text_signature = "(paths: List[String], threads: int, /)")]
     fn load_decision_costs_parallel_with_size_py(paths: Vec<&str>, threads: usize) -> PyResult<(Vec<Vec<f32>>, usize)> {
         let paths = paths.into_iter().map(|p| Path::new(p)).collect();
-        let res  = load_decision_costs_parallel(paths, threads);
+        let res  = load_decision_costs_parallel(paths, threads)?;
         let size = res.len() * res[0].len() * size_of_val(&res[0][0]);
         Ok((res, size))
     }
@@ -480,42 +846,91 @@ mod python {
     #[pyo3(text_signature = "(paths: List[String], /)")]
     fn count_decisions_py(paths: Vec<&str>) -> PyResult<usize> {
         let paths = paths.into_iter().map(|p| Path::new(p)).collect();
-        Ok(count_decisions(paths))
+        Ok(count_decisions(paths)?)
     }
 
     #[pyfunction(name = "count_decisions_parallel")]
     #[pyo3(text_signature = "(paths: List[String], threads: int, /)")]
     fn count_decisions_parallel_py(paths: Vec<&str>, threads: usize) -> PyResult<usize> {
         let paths = paths.into_iter().map(|p| Path::new(p)).collect();
-        Ok(count_decisions_parallel(paths, threads))
+        Ok(count_decisions_parallel(paths, threads)?)
+    }
+
+    #[pyfunction(name = "load_decision")]
+    #[pyo3(text_signature = "(path: String, id: int, /)")]
+    fn load_decision_py(path: &str, id: usize) -> PyResult<Decision> {
+        let path = Path::new(path);
+        Ok(load_decision(path, id)?)
+    }
+
+    #[pyfunction(name = "load_oracle_header")]
+    #[pyo3(text_signature = "(path: String, /)")]
+    fn load_oracle_header_py(path: &str) -> PyResult<Option<OracleHeader>> {
+        let path = Path::new(path);
+        Ok(load_oracle_header(path)?)
+    }
+
+    #[cfg(feature = "parquet")]
+    #[pyfunction(name = "export_parquet")]
+    #[pyo3(text_signature = "(paths: List[String], output: String, /)")]
+    fn export_parquet_py(paths: Vec<&str>, output: &str) -> PyResult<usize> {
+        let paths = paths.into_iter().map(Path::new).collect::<Vec<_>>();
+        Ok(crate::parquet_export::export_parquet(&paths, Path::new(output))?)
+    }
+
+    /// Checks that the oracle of the stats file was computed from the price and latency files, compared by content.
+    /// Emits a warning per mismatch, or raises a ValueError if `refuse` is set. Returns the mismatches.
+    #[pyfunction(name = "check_provenance")]
+    #[pyo3(signature = (stats_file, network_file, object_store_file, latency_file = None, refuse = false))]
+    fn check_provenance_py(py: Python, stats_file: &str, network_file: &str, object_store_file: &str, latency_file: Option<&str>, refuse: bool) -> PyResult<Vec<String>> {
+        let wrapper = load_wrapper(Path::new(stats_file))?;
+        let input_files = InputFile::of_inputs(Path::new(network_file), Path::new(object_store_file), latency_file.map(Path::new))?;
+        let mismatches = match &wrapper.provenance {
+            Some(provenance) => provenance.mismatches(&input_files),
+            None => vec![format!("Oracle {} has no provenance", stats_file)],
+        };
+
+        if refuse && !mismatches.is_empty() {
+            return Err(PyValueError::new_err(format!("Oracle was computed from different inputs: {}", mismatches.join("; "))));
+        }
+        for mismatch in &mismatches {
+            PyErr::warn(py, py.get_type::<PyUserWarning>(), mismatch, 1)?;
+        }
+        Ok(mismatches)
     }
 
     #[pyfunction(name = "load_wrapper")]
     #[pyo3(text_signature = "(path: String, /)")]
     fn load_wrapper_py(path: &str) -> PyResult<Wrapper> {
         let path = Path::new(path);
-        Ok(load_wrapper(path))
+        Ok(load_wrapper(path)?)
+    }
+
+    /// Number of cost columns of the first decision, without the cost dimension
+    fn first_decision_cost_columns(path: &Path) -> io::Result<usize> {
+        let first = ProtobufFileReader::new(path)?.into_iter_all::<Decision>().next()
+            .unwrap_or_else(|| Err(io::Error::new(io::ErrorKind::UnexpectedEof, "No decisions")))?;
+        Ok(first.cost_wl_halfplane.len() - 1)
+    }
+
+    fn load_decision_costs_flat(path: &Path, cols: usize) -> io::Result<Vec<f32>> {
+        let mut costs = Vec::new();
+        for x in ProtobufFileReader::new(path)?.into_iter_all::<Decision>() {
+            costs.extend(x?.cost_wl_halfplane.into_iter().take(cols).map(|x| x as f32));
+        }
+        Ok(costs)
     }
 
     #[pyfunction]
-    pub fn load_decision_costs_numpy<'py>(py: Python<'py>, paths: Vec<String>) -> &'py PyArrayDyn<f32> {
+    pub fn load_decision_costs_numpy<'py>(py: Python<'py>, paths: Vec<String>) -> PyResult<&'py PyArrayDyn<f32>> {
         
             let path = Path::new(paths.first().unwrap());
-            let cols = ProtobufFileReader::new(&path).unwrap().into_iter_all::<Decision>().take(1).map(|x: Decision| {
-                x.cost_wl_halfplane.len()
-            }).reduce(|a, b| a.max(b)).unwrap();
-            let cols = cols - 1; // Skip cost dimension
-
-            let iter = paths.into_iter()
-            .flat_map(|path|{
-                let path = Path::new(&path);
-                let message_iter = ProtobufFileReader::new(path).unwrap().into_iter_all::<Decision>();
-                let c = message_iter.flat_map(|x: Decision| {
-                    x.cost_wl_halfplane.into_iter().take(cols).map(|x| x as f32)
-                });
-                c
-            }); //.collect::<Vec<f32>>();
-            let pyarray_1dim = PyArray::from_iter(py, iter);
+            let cols = first_decision_cost_columns(path)?;
+
+            let costs = paths.into_iter()
+            .map(|path| load_decision_costs_flat(Path::new(&path), cols))
+            .collect::<io::Result<Vec<Vec<f32>>>>()?;
+            let pyarray_1dim = PyArray::from_iter(py, costs.into_iter().flatten());
             
             // Reshape to 2 dimensions
             // Check that the number of elements is multiple of cols
@@ -526,28 +941,19 @@ mod python {
             let dims = vec![rows, cols];
             let pyarray_2dim: &PyArrayDyn<f32> = pyarray_1dim.reshape(dims).unwrap();
 
-            pyarray_2dim
+            Ok(pyarray_2dim)
     }
 
     #[pyfunction]
-    pub fn load_decision_costs_numpy_parallel<'py>(py: Python<'py>, paths: Vec<String>) -> &'py PyArrayDyn<f32> {
+    pub fn load_decision_costs_numpy_parallel<'py>(py: Python<'py>, paths: Vec<String>) -> PyResult<&'py PyArrayDyn<f32>> {
         
             let path = Path::new(paths.first().unwrap());
-            let cols = ProtobufFileReader::new(&path).unwrap().into_iter_all::<Decision>().take(1).map(|x: Decision| {
-                x.cost_wl_halfplane.len()
-            }).reduce(|a, b| a.max(b)).unwrap();
-            let cols = cols - 1; // Skip cost dimension
-
-            let iter = paths.into_par_iter() //.into_iter()
-            .flat_map(|path|{
-                let path = Path::new(&path);
-                let message_iter = ProtobufFileReader::new(path).unwrap().into_iter_all::<Decision>();
-                let c = message_iter.flat_map(|x: Decision| {
-                    x.cost_wl_halfplane.into_iter().take(cols).map(|x| x as f32)
-                }).par_bridge();
-                c
-            }).collect::<Vec<f32>>();
-            let pyarray_1dim = PyArray::from_iter(py, iter);
+            let cols = first_decision_cost_columns(path)?;
+
+            let costs = paths.into_par_iter()
+            .map(|path| load_decision_costs_flat(Path::new(&path), cols))
+            .collect::<io::Result<Vec<Vec<f32>>>>()?;
+            let pyarray_1dim = PyArray::from_iter(py, costs.into_iter().flatten());
             
             // Reshape to 2 dimensions
             // Check that the number of elements is multiple of cols
@@ -558,7 +964,7 @@ mod python {
             let dims = vec![rows, cols];
             let pyarray_2dim: &PyArrayDyn<f32> = pyarray_1dim.reshape(dims).unwrap();
 
-            pyarray_2dim
+            Ok(pyarray_2dim)
     }
 
     #[pymodule]
@@ -573,6 +979,11 @@ mod python {
         m.add_function(wrap_pyfunction!(count_decisions_py, m)?)?;
         m.add_function(wrap_pyfunction!(count_decisions_parallel_py, m)?)?;
         m.add_function(wrap_pyfunction!(load_wrapper_py, m)?)?;
+        m.add_function(wrap_pyfunction!(load_decision_py, m)?)?;
+        m.add_function(wrap_pyfunction!(load_oracle_header_py, m)?)?;
+        m.add_function(wrap_pyfunction!(check_provenance_py, m)?)?;
+        #[cfg(feature = "parquet")]
+        m.add_function(wrap_pyfunction!(export_parquet_py, m)?)?;
         m.add_class::<Assignment>()?;
         m.add_class::<Scheme>()?;
         m.add_class::<Decision>()?;
@@ -581,11 +992,113 @@ mod python {
         m.add_class::<TierAdvise>()?;
         m.add_class::<Setting>()?;
         m.add_class::<Wrapper>()?;
+        m.add_class::<OracleHeader>()?;
+        m.add_class::<InputFile>()?;
+        m.add_class::<Provenance>()?;
+        m.add_class::<IdMapping>()?;
         Ok(())
     }
 }
 
 pub use messages::{
-    load_decision_costs, load_decision_costs_parallel, load_decisions, load_decisions_parallel, load_wrapper, count_decisions, count_decisions_parallel, Assignment, Decision, OptimalByOptimizer, Run, Scheme, Setting,
-    TierAdvise, Wrapper,
+    load_decision_costs, load_decision_costs_parallel, load_decisions, load_decisions_parallel, load_wrapper, count_decisions, count_decisions_parallel, load_decision, load_oracle_header, load_optimal_decisions, dimension_names, Assignment, Decision, OptimalByOptimizer, Run, Scheme, Setting,
+    TierAdvise, Wrapper, OracleHeader, OracleIndex, PartitionPlacement, InputFile, Provenance, ProvenanceCheck, IdMapping,
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracle_container::SCHEMA_VERSION;
+    use std::{fs, io, path::Path};
+
+    #[test]
+    fn test_check_provenance() {
+        let dir = std::env::temp_dir().join(format!("provenance_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+        let network_file = write("network.csv", "src,dst,cost\n");
+        let object_store_file = write("storage.csv", "vendor,region,price\n");
+        let updated_object_store_file = write("storage_v2.csv", "vendor,region,price\nAWS,eu-west-1,0.1\n");
+
+        let input_files = InputFile::of_inputs(&network_file, &object_store_file, None).unwrap();
+        let wrapper = Wrapper { provenance: Some(Provenance { input_files: input_files.clone(), ..Default::default() }), ..Default::default() };
+        wrapper.check_provenance(&input_files, ProvenanceCheck::Refuse).unwrap();
+
+        // Same content under another name matches
+        let copied = write("storage_copy.csv", "vendor,region,price\n");
+        wrapper.check_provenance(&InputFile::of_inputs(&network_file, &copied, None).unwrap(), ProvenanceCheck::Refuse).unwrap();
+
+        let updated = InputFile::of_inputs(&network_file, &updated_object_store_file, Some(Path::new(&network_file))).unwrap();
+        assert_eq!(wrapper.provenance.as_ref().unwrap().mismatches(&updated).len(), 2);
+        wrapper.check_provenance(&updated, ProvenanceCheck::Warn).unwrap();
+        let err = wrapper.check_provenance(&updated, ProvenanceCheck::Refuse).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_provenance_invalid() {
+        let dir = std::env::temp_dir().join(format!("provenance_invalid_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let network_file = dir.join("network.csv");
+        fs::write(&network_file, "src,dst,cost\n").unwrap();
+        let object_store_file = dir.join("storage.csv");
+        fs::write(&object_store_file, "vendor,region,price\n").unwrap();
+
+        let err = InputFile::of_inputs(&network_file, &object_store_file, Some(&dir.join("missing.csv"))).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!("strict".parse::<ProvenanceCheck>().is_err());
+
+        // Oracles without provenance and ignored mismatches pass even when refusing
+        let input_files = InputFile::of_inputs(&network_file, &object_store_file, None).unwrap();
+        Wrapper::default().check_provenance(&input_files, ProvenanceCheck::Refuse).unwrap();
+        let wrapper = Wrapper { provenance: Some(Provenance::default()), ..Default::default() };
+        wrapper.check_provenance(&input_files, ProvenanceCheck::Ignore).unwrap();
+        // Input files the oracle was computed without
+        assert_eq!(wrapper.provenance.as_ref().unwrap().mismatches(&input_files).len(), 2);
+        let err = wrapper.check_provenance(&input_files, ProvenanceCheck::Refuse).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // A latency file of the oracle that is not given
+        let with_latency = Provenance { input_files: [input_files.clone(), vec![InputFile { kind: "latency".to_string(), ..Default::default() }]].concat(), ..Default::default() };
+        assert_eq!(with_latency.mismatches(&input_files), ["latency file  of the oracle is not given"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expand_encode_invalid() {
+        let header = OracleHeader {
+            schema_version: SCHEMA_VERSION,
+            no_dimensions: 3,
+            app_regions: vec!["aws-eu-west-1".to_string()],
+            object_stores: vec!["aws-eu-west-1-General Purpose".to_string()],
+            ..Default::default()
+        };
+        let scheme = Scheme { object_store_ids: vec![0], app_object_store_ids: vec![0], ..Default::default() };
+        let expanded = scheme.clone().expand(&header).unwrap();
+        assert_eq!(expanded.object_stores, header.object_stores);
+        assert_eq!(expanded.clone().encode(&header).unwrap(), scheme);
+
+        // Ids outside of the dictionary
+        let out_of_range = Scheme { object_store_ids: vec![1], ..scheme.clone() };
+        assert_eq!(out_of_range.expand(&header).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let decision = Decision { replication_scheme: Some(Scheme { app_object_store_ids: vec![7], ..scheme.clone() }), ..Default::default() };
+        assert_eq!(decision.expand(&header).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        // More app assignments than app regions
+        let too_many_apps = Scheme { app_object_store_ids: vec![0, 0], ..scheme.clone() };
+        assert_eq!(too_many_apps.expand(&header).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Names missing from the dictionary
+        let mut unknown = expanded.clone();
+        unknown.object_stores = vec!["gcp-europe-west1-STANDARD".to_string()];
+        assert_eq!(unknown.encode(&header).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let mut unknown_app = expanded;
+        unknown_app.app_assignments[0].app = "gcp-europe-west1".to_string();
+        assert_eq!(unknown_app.encode(&header).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
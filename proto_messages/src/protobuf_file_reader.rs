@@ -1,46 +1,140 @@
-use std::{fs::File, path::Path, io::{self, Read}};
+use std::{fs::File, path::{Path, PathBuf}, io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom}};
 
-use bytes::Buf;
-use prost::encoding::{self, WireType, DecodeContext};
+use memmap2::Mmap;
 
+use crate::{oracle_container::{read_layout, CONTAINER_MAGIC}, protobuf_file_sink::{is_zstd_path, ZSTD_MAGIC}, OracleHeader};
+
+/// Size of the buffer for streaming decompression
+const STREAM_BUFFER_SIZE: usize = 1 << 20;
+
+enum Source {
+    // Uncompressed files are memory-mapped, so the OS pages them in and out as needed
+    Mapped(Cursor<Mmap>),
+    // Compressed files are decompressed while streaming through a bounded buffer
+    Compressed(BufReader<zstd::stream::read::Decoder<'static, BufReader<File>>>),
+    // Nothing to read from the offset on, e.g., an empty file, which is not a valid zstd stream
+    Empty(io::Empty),
+}
+
+impl Source {
+    fn open(file_name: &Path, compressed: bool, offset: u64) -> io::Result<Self> {
+        let mut file = File::open(file_name)?;
+        if file.metadata()?.len() <= offset {
+            return Ok(Source::Empty(io::empty()));
+        }
+        if compressed {
+            file.seek(SeekFrom::Start(offset))?;
+            let decoder = zstd::stream::read::Decoder::new(file)?;
+            Ok(Source::Compressed(BufReader::with_capacity(STREAM_BUFFER_SIZE, decoder)))
+        } else {
+            // Safety: Oracle files are not modified while being read
+            let mmap = unsafe { Mmap::map(&file)? };
+            let mut cursor = Cursor::new(mmap);
+            cursor.set_position(offset);
+            Ok(Source::Mapped(cursor))
+        }
+    }
+
+    fn reader(&mut self) -> &mut dyn BufRead {
+        match self {
+            Source::Mapped(cursor) => cursor,
+            Source::Compressed(reader) => reader,
+            Source::Empty(empty) => empty,
+        }
+    }
+}
+
+/// Reads length-delimited messages from a file without loading the whole file into memory
 pub struct ProtobufFileReader {
-    buf: Vec<u8>,
-    pos: usize,
+    file_name: PathBuf,
+    compressed: bool,
+    source: Source,
+    // Header and end of the messages of containers, see `oracle_container`
+    header: Option<OracleHeader>,
+    data_end: Option<u64>,
+    // Messages spanning the end of the stream buffer are copied here for decoding
+    message_buf: Vec<u8>,
 }
 
 impl ProtobufFileReader {
+    /// Opens a file written by the `ProtobufFileSink`, which is zstd compressed if it starts with the zstd magic bytes or has the extension ".zst".
+    /// Containers start reading after the header and stop before the index.
     pub fn new(file_name: &Path) -> io::Result<Self> {
-        let mut buf: Vec<u8> = Vec::new();
-        File::open(file_name).unwrap().read_to_end(&mut buf)?;
+        let mut magic = Vec::with_capacity(ZSTD_MAGIC.len());
+        File::open(file_name)?.take(ZSTD_MAGIC.len() as u64).read_to_end(&mut magic)?;
+        let compressed = magic == ZSTD_MAGIC || is_zstd_path(file_name);
+
+        let mut source = Source::open(file_name, compressed, 0)?;
+        let (header, data_end) = match &mut source {
+            Source::Mapped(cursor) => match read_layout(cursor.get_ref())? {
+                Some(layout) => {
+                    cursor.set_position(layout.data_start);
+                    (Some(layout.header), Some(layout.data_end))
+                }
+                None => (None, None),
+            },
+            Source::Compressed(reader) => {
+                // Compressed containers have a header but no index
+                if reader.fill_buf()?.starts_with(&CONTAINER_MAGIC) {
+                    reader.consume(CONTAINER_MAGIC.len());
+                    let header = read_message::<OracleHeader>(reader, &mut Vec::new())?;
+                    header.validate()?;
+                    (Some(header), None)
+                } else {
+                    (None, None)
+                }
+            }
+            Source::Empty(_) => (None, None),
+        };
 
         Ok(Self {
-            buf,
-            pos: 0,
+            file_name: file_name.to_path_buf(),
+            compressed,
+            source,
+            header,
+            data_end,
+            message_buf: Vec::new(),
         })
     }
 
+    /// Header of a container, None for bare files
+    pub fn header(&self) -> Option<&OracleHeader> {
+        self.header.as_ref()
+    }
+
+    /// Continues reading at the offset of a message in the file, e.g., as returned by `ProtobufFileSink::offset`.
+    /// For compressed files, the offset must be at the start of a zstd frame, which holds for the offsets of the sink.
+    pub fn seek(&mut self, offset: u64) -> io::Result<()> {
+        match &mut self.source {
+            Source::Mapped(cursor) => cursor.set_position(offset),
+            Source::Compressed(_) | Source::Empty(_) => self.source = Source::open(&self.file_name, self.compressed, offset)?,
+        }
+        Ok(())
+    }
+
     pub fn read_next<M>(&mut self) -> io::Result<M>
     where
         M: prost::Message + Default,
     {
-        let mut msg = M::default();
-
-        let r = encoding::message::merge(
-            WireType::LengthDelimited,
-            &mut msg,
-            self,
-            DecodeContext::default(),
-        );
+        read_message(self.source.reader(), &mut self.message_buf)
+    }
 
-        if let Err(e) = r {
-            return Err(io::Error::new(io::ErrorKind::Other, e));
+    /// Skips the next message without decoding it
+    pub fn skip_next(&mut self) -> io::Result<()> {
+        let reader = self.source.reader();
+        let len = read_length_delimiter(reader)? as u64;
+        if io::copy(&mut Read::take(reader, len), &mut io::sink())? < len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated message"));
         }
-
-        Ok(msg)
+        Ok(())
     }
 
-    pub fn has_data(&self) -> bool {
-        (self.buf.len() - self.pos) > 0
+    pub fn has_data(&mut self) -> bool {
+        if let (Source::Mapped(cursor), Some(data_end)) = (&self.source, self.data_end) {
+            return cursor.position() < data_end;
+        }
+        // Report data on errors, so that read_next returns the error
+        self.source.reader().fill_buf().map_or(true, |buf| !buf.is_empty())
     }
 
     pub fn into_iter_all<M>(self) -> ProtobufFileReaderIterator<M>
@@ -51,47 +145,148 @@ impl ProtobufFileReader {
     }
 }
 
-// Buf wrapper on reader for prost decoding
-impl<'a> Buf for ProtobufFileReader {
-    fn remaining(&self) -> usize {
-        self.buf.len() - self.pos
-    }
+/// Decodes the next length-delimited message, messages spanning the end of the stream buffer are copied to `message_buf`
+fn read_message<M>(reader: &mut dyn BufRead, message_buf: &mut Vec<u8>) -> io::Result<M>
+where
+    M: prost::Message + Default,
+{
+    let len = read_length_delimiter(reader)?;
 
-    fn chunk(&self) -> &[u8] {
-        &self.buf[self.pos..]
-    }
+    let available = reader.fill_buf()?;
+    let msg = if available.len() >= len {
+        let msg = M::decode(&available[..len]);
+        reader.consume(len);
+        msg
+    } else {
+        message_buf.resize(len, 0);
+        reader.read_exact(message_buf)?;
+        M::decode(&message_buf[..])
+    };
 
-    fn advance(&mut self, cnt: usize) {
-        self.pos += cnt;
+    msg.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Decodes the varint length prefix of a message
+fn read_length_delimiter(reader: &mut dyn BufRead) -> io::Result<usize> {
+    let mut len = 0u64;
+    for i in 0..10 {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        len |= ((byte[0] & 0x7F) as u64) << (7 * i);
+        if byte[0] < 0x80 {
+            return Ok(len as usize);
+        }
     }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid length delimiter"))
 }
 
-// Iterator to load all messages of a given type from a file
+// Iterator to load all messages of a given type from a file, stops after the first error
 pub struct ProtobufFileReaderIterator<M> {
     reader: ProtobufFileReader,
+    failed: bool,
     _marker: std::marker::PhantomData<M>,
 }
 
-impl<'a, M> ProtobufFileReaderIterator<M> {
+impl<M> ProtobufFileReaderIterator<M> {
     pub fn new(reader: ProtobufFileReader) -> Self {
         Self {
             reader,
+            failed: false,
             _marker: std::marker::PhantomData,
         }
     }
 }
 
-impl<'a, M> Iterator for ProtobufFileReaderIterator<M>
+impl<M> Iterator for ProtobufFileReaderIterator<M>
 where
     M: prost::Message + Default,
 {
-    type Item = M;
+    type Item = io::Result<M>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.reader.has_data() {
-            Some(self.reader.read_next().unwrap())
-        } else {
-            None
+        if self.failed || !self.reader.has_data() {
+            return None;
+        }
+
+        let msg = self.reader.read_next();
+        self.failed = msg.is_err();
+        Some(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use crate::{ProtobufFileReader, ProtobufFileSink};
+
+    #[test]
+    fn test_zstd_roundtrip_and_resume() {
+        let file_name = std::env::temp_dir().join(format!("protobuf_file_test_{}.proto.bin.zst", std::process::id()));
+        let messages = (0..100).map(|i| format!("message {}", i)).collect::<Vec<_>>();
+
+        let mut sink = ProtobufFileSink::new(&file_name, 1024, 64).unwrap();
+        for m in &messages[..50] {
+            sink.write(m.clone()).unwrap();
+        }
+        let offset = sink.offset().unwrap();
+        // Written after the checkpoint, discarded when resuming
+        sink.write("lost".to_string()).unwrap();
+        sink.flush().unwrap();
+
+        let mut sink = ProtobufFileSink::open_at(&file_name, offset, 1024, 64).unwrap();
+        for m in &messages[50..] {
+            sink.write(m.clone()).unwrap();
+        }
+        sink.flush().unwrap();
+
+        let raw = std::fs::read(&file_name).unwrap();
+        assert_eq!(&raw[..4], &crate::protobuf_file_sink::ZSTD_MAGIC);
+
+        let read = ProtobufFileReader::new(&file_name).unwrap().into_iter_all::<String>().collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(read, messages);
+
+        std::fs::remove_file(file_name).unwrap();
+    }
+
+    #[test]
+    fn test_seek_and_decode_errors() {
+        let file_name = std::env::temp_dir().join(format!("protobuf_file_test_{}.proto.bin", std::process::id()));
+
+        let mut sink = ProtobufFileSink::new(&file_name, 1024, 64).unwrap();
+        sink.write("first".to_string()).unwrap();
+        let offset = sink.offset().unwrap();
+        sink.write("second".to_string()).unwrap();
+        sink.flush().unwrap();
+        // Truncated message: length 10 followed by a single byte
+        std::fs::OpenOptions::new().append(true).open(&file_name).unwrap().write_all(&[10, 0]).unwrap();
+
+        let mut reader = ProtobufFileReader::new(&file_name).unwrap();
+        reader.seek(offset).unwrap();
+        assert_eq!(reader.read_next::<String>().unwrap(), "second");
+
+        let read = ProtobufFileReader::new(&file_name).unwrap().into_iter_all::<String>().collect::<Vec<_>>();
+        assert_eq!(read.len(), 3);
+        assert_eq!(read[1].as_ref().unwrap(), "second");
+        assert_eq!(read[2].as_ref().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        std::fs::remove_file(file_name).unwrap();
+    }
+
+    #[test]
+    fn test_empty_files() {
+        for extension in ["proto.bin", "proto.bin.zst"] {
+            let file_name = std::env::temp_dir().join(format!("protobuf_file_empty_test_{}.{}", std::process::id(), extension));
+            std::fs::File::create(&file_name).unwrap();
+
+            let mut reader = ProtobufFileReader::new(&file_name).unwrap();
+            assert!(reader.header().is_none());
+            assert!(!reader.has_data());
+            reader.seek(0).unwrap();
+            assert_eq!(ProtobufFileReader::new(&file_name).unwrap().into_iter_all::<String>().count(), 0);
+            assert_eq!(crate::oracle_container::count_messages(&file_name).unwrap(), 0);
+
+            std::fs::remove_file(file_name).unwrap();
         }
     }
-}
\ No newline at end of file
+}
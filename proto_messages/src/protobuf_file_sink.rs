@@ -1,14 +1,25 @@
 use bytes::{buf::UninitSlice, BufMut};
 use futures::sink::Sink;
 use hydroflow::futures;
-use std::fs::File;
-use std::io::{self, Write};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
 use std::path::Path;
 
+/// Magic bytes at the start of every zstd frame
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+pub const ZSTD_DEFAULT_LEVEL: i32 = 3;
+
+/// Files with the extension ".zst" are zstd compressed, e.g., "candidates_0.proto.bin.zst"
+pub fn is_zstd_path(file_name: &Path) -> bool {
+    file_name.extension().is_some_and(|e| e == "zst")
+}
+
 pub struct ProtobufFileSink {
     write_buffer: Vec<u8>,
     file: File,
     spare_capacity: usize,
+    // Compress each flushed buffer as a separate zstd frame
+    compression_level: Option<i32>,
 }
 
 impl ProtobufFileSink {
@@ -19,7 +30,9 @@ impl ProtobufFileSink {
     /// * `file_name` - The path to the file to create.
     /// * `capacity` - The capacity of the write buffer.
     /// * `spare_capacity` - The amount of extra capacity to maintain.
-    /// This should be at least as big as the largest field being written, since fragmentation is not supported!
+    ///   This should be at least as big as the largest field being written, since fragmentation is not supported!
+    ///
+    /// The file is zstd compressed if its extension is ".zst".
     ///
     /// # Returns
     ///
@@ -35,10 +48,57 @@ impl ProtobufFileSink {
             write_buffer,
             file,
             spare_capacity,
+            compression_level: Self::compression_level(file_name),
         })
     }
 
+    fn compression_level(file_name: &Path) -> Option<i32> {
+        if is_zstd_path(file_name) { Some(ZSTD_DEFAULT_LEVEL) } else { None }
+    }
+
+    /// Overrides the zstd compression level, None disables compression
+    pub fn with_compression_level(mut self, level: Option<i32>) -> Self {
+        self.compression_level = level;
+        self
+    }
+
+    /// Opens an existing file for appending after truncating it to `offset`, e.g., to resume from a checkpoint.
+    /// Data after `offset` is discarded, so that messages written after the checkpoint are not duplicated.
+    pub fn open_at(
+        file_name: &Path,
+        offset: u64,
+        capacity: usize,
+        spare_capacity: usize,
+    ) -> io::Result<ProtobufFileSink> {
+        let mut file = OpenOptions::new().write(true).open(file_name)?;
+        // Devices such as /dev/null cannot be truncated
+        if file.metadata()?.is_file() {
+            file.set_len(offset)?;
+            file.seek(SeekFrom::End(0))?;
+        }
+        let write_buffer = Vec::with_capacity(capacity);
+        Ok(ProtobufFileSink {
+            write_buffer,
+            file,
+            spare_capacity,
+            compression_level: Self::compression_level(file_name),
+        })
+    }
+
+    /// Writes a length-delimited protobuf message, like `start_send` but without going through a stream.
+    pub fn write<Item: prost::Message>(&mut self, item: Item) -> io::Result<()> {
+        item.encode_length_delimited(self).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+
+    /// Flushes the write buffer and returns the offset in the file, i.e., the number of bytes written.
+    /// With compression, the offset is at the end of a complete zstd frame, so the file can be truncated to it.
+    pub fn offset(&mut self) -> io::Result<u64> {
+        self.flush()?;
+        self.file.stream_position()
+    }
+
     /// Flushes the write buffer to the file and clears it.
+    /// With compression, the buffer is written as a complete zstd frame.
     ///
     /// # Errors
     ///
@@ -55,8 +115,13 @@ impl ProtobufFileSink {
     /// sink.flush().unwrap();
     /// ```
     pub fn flush(&mut self) -> io::Result<()> {
-        self.file
-            .write_all(&self.write_buffer[0..self.write_buffer.len()])?;
+        match self.compression_level {
+            Some(level) if !self.write_buffer.is_empty() => {
+                let frame = zstd::bulk::compress(&self.write_buffer, level)?;
+                self.file.write_all(&frame)?;
+            }
+            _ => self.file.write_all(&self.write_buffer[0..self.write_buffer.len()])?,
+        }
 
         self.write_buffer.clear();
         self.file.flush()
@@ -100,12 +165,10 @@ where
         if res.is_ok() {
             return Ok(());
         }
-        {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Failed to encode protobuf message",
-            ));
-        }
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Failed to encode protobuf message",
+        ))
     }
 }
 
@@ -1,9 +1,17 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
 use clap::Parser;
 use hydroflow::util::cli::{ConnectedDirect, ConnectedSource, ConnectedSink};
 
 use skypie_lib::{candidate_policies_reduce_hydroflow, Args, Loader, ApplicationRegion};
+use skypie_lib::input_format::InputFormat;
+use skypie_lib::id_catalogue::IdCatalogue;
+use skypie_lib::logging::init_logging;
+use tracing::{info, info_span, warn, Instrument};
+use skypie_lib::checkpoint::{Checkpoint, Checkpointer};
+use skypie_lib::combination_ranking::CombinationRanking;
+use skypie_lib::object_store::ObjectStore;
+use skypie_lib::write_choice_constraints::WriteChoiceConstraints;
 
 #[hydroflow::main]
 async fn main() {
@@ -29,6 +37,12 @@ async fn main() {
         .await
         .into_sink();
 
+    let request_sink = ports
+        .port("request_output")
+        .connect::<ConnectedDirect>() 
+        .await
+        .into_sink();
+
     let done_sink = ports
         .port("done_output")
         .connect::<ConnectedDirect>() 
@@ -37,22 +51,57 @@ async fn main() {
 
     // Load the input
     let args = Args::parse();
+    init_logging(&args.log_level, args.log_json).unwrap();
 
-    let loader = Loader::new(&args.network_file, &args.object_store_file, &args.region_selector, &args.object_store_selector, &args.latency_file, &args.latency_slo, None);
+    let span = info_span!("worker", worker_id = args.worker_id, experiment = %args.experiment_name);
+    let guard = span.enter();
+
+    let input_format = InputFormat::from_optional_file(&args.input_format).unwrap();
+    let id_catalogue = IdCatalogue::from_optional_file(&args.id_catalogue).unwrap();
+    let loader = Loader::with_id_catalogue(&args.network_file, &args.object_store_file, &args.region_selector, &args.object_store_selector, &args.latency_file, &args.latency_slo, &args.write_latency_slo, &input_format, id_catalogue.as_ref(), None).unwrap();
+    let write_compatibility_checker = loader.load_write_compatibility_checker(&args.write_latency_slo, &args.write_quorum);
+    let prune_unread_replicas = args.unread_replica_pruning().unwrap();
+    let constraints = Arc::new(WriteChoiceConstraints::from_args(&args).unwrap());
 
     // Static life time hack for hydroflow lifetime mess
     let data = Box::new(loader.app_regions);
     let regions: &'static Vec<ApplicationRegion> = Box::leak(data);
 
+    // The worker enumerates the write choices of the chunks it receives
+    let object_store_ids = loader.object_stores.iter().map(|x| x.id).collect::<Vec<_>>();
+    let ranking = Arc::new(CombinationRanking::new(object_store_ids, args.replication_factor, args.replication_factor_max.unwrap_or(args.replication_factor)));
+    let object_stores: Arc<[ObjectStore]> = loader.object_stores.into();
+
     let output_candidates_file_name: String = args.output_candidates_file_name.unwrap_or(PathBuf::from("/dev/null")).to_str().unwrap().into();
     let output_file_name = args.output_file_name.unwrap_or(PathBuf::from("/dev/null")).to_str().unwrap().into();
 
-    let object_store_id_map = loader.object_stores.iter().map(|x| (x.id.clone(), x.clone())).collect::<std::collections::HashMap<_,_>>();
+    let object_store_id_map = object_stores.iter().map(|x| (x.id.clone(), x.clone())).collect::<std::collections::HashMap<_,_>>();
 
     let compatibility_checker_slos = loader.compatibility_checker_slos;
 
-    let flow = candidate_policies_reduce_hydroflow(regions, input_recv, args.batch_size, args.experiment_name, output_candidates_file_name, output_file_name, object_store_id_map, time_sink, done_sink, args.worker_id, args.optimizer, args.use_clarkson, compatibility_checker_slos, count_sink);
+    // Checkpoints track the processed write choices by their rank in the chunks of this worker
+    let checkpoint_file_name = Checkpoint::file_name(&args.experiment_name, args.worker_id);
+    let resume = if args.resume {
+        let checkpoint = Checkpoint::load(&checkpoint_file_name).unwrap();
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.check_chunk_size(args.chunk_size).unwrap();
+        } else {
+            warn!(?checkpoint_file_name, "No checkpoint found, starting from scratch");
+        }
+        checkpoint
+    } else {
+        None
+    };
+    let checkpointer = if args.checkpoint_interval.is_some() || resume.is_some() {
+        let interval = args.checkpoint_interval.map(std::time::Duration::from_secs);
+        Some(Checkpointer::new(checkpoint_file_name, interval, args.chunk_size, resume.as_ref()))
+    } else {
+        None
+    };
+
+    let flow = candidate_policies_reduce_hydroflow(regions, input_recv, args.batch_size, args.experiment_name, output_candidates_file_name, output_file_name, object_store_id_map, time_sink, done_sink, args.worker_id, args.optimizer, args.use_clarkson, compatibility_checker_slos, write_compatibility_checker, count_sink, args.replication_factor, prune_unread_replicas, checkpointer, resume, request_sink, ranking, constraints, object_stores).unwrap();
 
-    println!("Launching candidate and reduce");
-    hydroflow::util::cli::launch_flow(flow).await;
+    info!("Launching candidate and reduce");
+    drop(guard);
+    hydroflow::util::cli::launch_flow(flow).instrument(span).await;
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use clap::Parser;
@@ -7,28 +7,23 @@ use hydroflow::bytes::Bytes;
 use hydroflow::util::cli::{ConnectedDirect, ConnectedSource, ConnectedSink};
 use hydroflow::util::deserialize_from_bytes;
 use hydroflow::hydroflow_syntax;
-use itertools::Itertools;
-use skypie_lib::read_choice::ReadChoice;
-use skypie_lib::{Args, Loader, Decision};
+use skypie_lib::{Args, Loader};
+use skypie_lib::stats::{new_stats, stats_file_name, update_stats};
 use skypie_lib::log_entry::SkyPieLogEntryType;
+use skypie_lib::combination_ranking::CombinationRanking;
+use skypie_lib::checkpoint::Checkpoint;
+use skypie_lib::chunks::resumed_write_choices;
+use skypie_lib::progress::{Progress, ProgressReport};
+use skypie_lib::metrics::{serve_metrics, Metrics};
+use skypie_lib::input_format::InputFormat;
+use skypie_lib::id_catalogue::IdCatalogue;
+use skypie_lib::logging::init_logging;
+use tracing::{info, info_span, Instrument};
 
 #[hydroflow::main]
 async fn main() {
     let mut ports = hydroflow::util::cli::init().await;
 
-    // Load the input
-    let args = Args::parse();
-
-    let loader = Loader::new(
-        &args.network_file,
-        &args.object_store_file,
-        &args.region_selector,
-        &args.object_store_selector,
-        &args.latency_file,
-        &args.latency_slo,
-        None,
-    );
-
     let time_input_recv = ports
         .port("time_input")
         // connect to the port with a single recipient
@@ -56,67 +51,54 @@ async fn main() {
         .await
         .into_sink();
 
+    // Load the input
+    let args = Args::parse();
+    init_logging(&args.log_level, args.log_json).unwrap();
+
+    let span = info_span!("logger", worker_id = args.worker_id, experiment = %args.experiment_name);
+    let guard = span.enter();
+
+    let loader = Loader::with_id_catalogue(
+        &args.network_file,
+        &args.object_store_file,
+        &args.region_selector,
+        &args.object_store_selector,
+        &args.latency_file,
+        &args.latency_slo,
+        &args.write_latency_slo,
+        &InputFormat::from_optional_file(&args.input_format).unwrap(),
+        IdCatalogue::from_optional_file(&args.id_catalogue).unwrap().as_ref(),
+        None,
+    ).unwrap();
+
     type Input = (SkyPieLogEntryType, std::time::Duration);
     type CountInput = (SkyPieLogEntryType, usize);
 
     // Write basic stats to file
-    let no_app_regions = loader.app_regions.len();
-    let read_choice: ReadChoice = ReadChoice::new(no_app_regions);
-    let no_app_regions = no_app_regions as i64;
-    // Number of dimensions is the number of workload parameters/cost coefficients + 1 for the intercept
-    let no_dimensions = Decision {
-        write_choice: Default::default(),
-        read_choice,
-    }.plane_iter().len() as i64;
-
-    let optimal_partitions: Vec<String> = if let Some(output_file_name) = args.output_file_name {
-        let file_name_with_ext2 = output_file_name.file_stem().unwrap().to_str().unwrap();
-        let pbuf = PathBuf::from(file_name_with_ext2);
-        
-        let file_extension_outer = output_file_name.extension().unwrap().to_str().unwrap();
-        let file_extension_inner = pbuf.extension().unwrap_or_default().to_str().unwrap();
-        let file_name = pbuf.file_stem().unwrap().to_str().unwrap();
-        
-        (0..args.redundancy_elimination_workers)
-            .map(|i| format!("{}_{}.{}.{}", file_name, i, file_extension_inner, file_extension_outer))
-            .collect_vec()
-    } else {
-        vec![]
-    };
-    let candidate_partitions: Vec<String> = if let Some(output_file_name) = args.output_candidates_file_name {
-        let file_name_with_ext2 = output_file_name.file_stem().unwrap().to_str().unwrap();
-        let pbuf = PathBuf::from(file_name_with_ext2);
-        
-        let file_extension_outer = output_file_name.extension().unwrap().to_str().unwrap();
-        let file_extension_inner = pbuf.extension().unwrap_or_default().to_str().unwrap();
-        let file_name = pbuf.file_stem().unwrap().to_str().unwrap();
-        
-        (0..args.redundancy_elimination_workers)
-            .map(|i| format!("{}_{}.{}.{}", file_name, i, file_extension_inner, file_extension_outer))
-            .collect_vec()
+    let (mut stats, optimizer_name) = new_stats(&loader, &args).unwrap();
+    let replication_factor = args.replication_factor as u64;
+    let stats_file_name = stats_file_name(&args.experiment_name);
+
+    // Progress against the total number of write choices over the replication range
+    let object_store_ids = loader.object_stores.iter().map(|x| x.id).collect::<Vec<_>>();
+    let total = CombinationRanking::new(object_store_ids, args.replication_factor, args.replication_factor_max.unwrap_or(args.replication_factor)).total();
+    // Baseline of the write choices processed before resuming, the coordinator reports them as done at start-up
+    let initial_done = if args.resume {
+        let watermarks = Checkpoint::load_watermarks(&args.experiment_name, args.redundancy_elimination_workers as usize, args.chunk_size).unwrap();
+        resumed_write_choices(total, args.chunk_size, &watermarks)
     } else {
-        vec![]
+        0
     };
+    let mut progress = Progress::new(total, initial_done, Duration::from_secs(args.progress_interval));
+    let progress_file_name = ProgressReport::file_name(&args.experiment_name);
 
-    let (optimizer_name, optimizer_type) = skypie_lib::optimizer_stats::get_optimizer_json(args.batch_size, args.optimizer, args.use_clarkson);
-
-    let replication_factor = args.replication_factor as u64;
-
-    let stats_file_name = format!("{}/stats", args.experiment_name);
-    let mut stats = skypie_proto_messages::Wrapper::new(
-        loader
-            .object_stores
-            .iter()
-            .map(|o| format!("{}-{}", o.region.name, o.name))
-            .collect_vec(),
-        candidate_partitions,
-        optimal_partitions,
-        replication_factor,
-        no_app_regions,
-        no_dimensions,
-        optimizer_name.clone(),
-        optimizer_type
-    );
+    let metrics = Arc::new(Mutex::new(Metrics::new(vec![("experiment".to_string(), args.experiment_name.clone())])));
+    if let Some(port) = args.metrics_port {
+        serve_metrics(&args.metrics_address, port, metrics.clone()).unwrap();
+    }
+    let metrics_file_name = Metrics::file_name(&args.experiment_name);
+    let (metrics_log, metrics_done) = (metrics.clone(), metrics.clone());
+    let metrics_done_file_name = metrics_file_name.clone();
 
     let flow = hydroflow_syntax! {
 
@@ -128,11 +110,14 @@ async fn main() {
             // Collect all finished workers
             -> fold(Vec::new(), |acc: &mut Vec<_>, x| {acc.push(x);})
             -> inspect(|x| {
-                println!("Workers done: {} ({:?})", x.len(), x);
+                info!(no_workers_done = x.len(), workers_done = ?x, "Workers done");
             })
             -> filter(|x| x.len() >= (args.num_workers-1))
             -> map(|_| -> Bytes {vec![42 as u8].into()})
-            -> inspect(|_| {println!("All workers done!");})
+            -> inspect(|_| {
+                info!("All workers done!");
+                metrics_done.lock().unwrap().save(&metrics_done_file_name);
+            })
             -> dest_sink(done_sink);
 
         counts = source_stream(count_input_recv)
@@ -153,49 +138,23 @@ async fn main() {
         times -> [1]log;
         log = cross_join::<'tick>()
             -> for_each(|(counts, x): (HashMap::<SkyPieLogEntryType, usize>, HashMap::<SkyPieLogEntryType, Duration>)|{
-                let zero_duration = Duration::from_secs(0);
-                let total_time = x.get(&SkyPieLogEntryType::Total).unwrap_or(&zero_duration);
-                let redundancy_elimination_time = x.get(&SkyPieLogEntryType::RedundancyElimination).unwrap_or(&zero_duration);
-                let write_chioce_time = x.get(&SkyPieLogEntryType::WriteChoiceGeneration).unwrap_or(&zero_duration);
-                let total_time = *total_time + *write_chioce_time;
-
-                let optimal_placements = counts.get(&SkyPieLogEntryType::OptimalCount).unwrap_or(&0);
-
-                if *redundancy_elimination_time == zero_duration {
-                    return;
+                if update_stats(&mut stats, replication_factor, &optimizer_name, &x, &counts) {
+                    stats.save(&stats_file_name);
+                }
+                let mut metrics = metrics_log.lock().unwrap();
+                metrics.update_from_log(&x, &counts);
+                if let Some(report) = progress.report_if_due(&counts) {
+                    report.print();
+                    report.save(&progress_file_name);
+                    metrics.update_from_progress(&report);
                 }
-
-                let enumerator_time = if total_time < *redundancy_elimination_time {
-                    println!("Temporary inconsistency, total time is less than redundancy elimination time!");
-                    Duration::default()
-                } else {
-                    total_time - (*redundancy_elimination_time)
-                };
-                println!("{}: Total time: {:?}, Enumerator time: {:?}, Redundancy elimination time: {:?}, Write Choice time: {:?}, Optimal placements: {}", context.current_tick(), total_time, enumerator_time, redundancy_elimination_time, write_chioce_time, optimal_placements);
-
-                let partitioner_time_ns = redundancy_elimination_time.as_secs() as i64 * 1_000_000_000 + redundancy_elimination_time.subsec_nanos() as i64;
-
-                let run = stats.tier_advise.as_mut().unwrap()
-                    .replication_factor.entry(replication_factor).or_default()
-                    .runs.entry("place_holder".to_string()).or_default();
-                run.enumerator_time_ns = Some(enumerator_time.as_secs() as i64 * 1_000_000_000 + enumerator_time.subsec_nanos() as i64);
-                run.partitioner_time_ns = Some(partitioner_time_ns);
-                run.no_facets = Some(*optimal_placements as i64);
-
-                let optimizer = stats.tier_advise.as_mut().unwrap()
-                    .replication_factor.entry(replication_factor).or_default()
-                    .runs.entry("place_holder".to_string()).or_default()
-                    .optimal_partitions_by_optimizer.entry(optimizer_name.clone()).or_default();
-    
-                optimizer.partitioner_computation_time_ns = Some(partitioner_time_ns);
-                optimizer.partitioner_time_ns = Some(partitioner_time_ns);
-                optimizer.no_facets = Some(*optimal_placements as i64);
-                
-                stats.save(&stats_file_name);
             });
     };
 
-    println!("Launching");
-    hydroflow::util::cli::launch_flow(flow).await;
-    println!("Stopping");
+    info!("Launching");
+    drop(guard);
+    hydroflow::util::cli::launch_flow(flow).instrument(span.clone()).await;
+    let _guard = span.enter();
+    info!("Stopping");
+    metrics.lock().unwrap().save(&metrics_file_name);
 }
\ No newline at end of file
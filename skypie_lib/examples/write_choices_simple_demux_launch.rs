@@ -1,32 +1,24 @@
 use clap::Parser;
-use itertools::Itertools;
 
 use hydroflow::hydroflow_syntax;
-use hydroflow::util::cli::{ConnectedDemux, ConnectedDirect, ConnectedSink};
-use hydroflow::util::serialize_to_bytes;
+use hydroflow::util::cli::{ConnectedDemux, ConnectedDirect, ConnectedSink, ConnectedSource};
+use hydroflow::util::{deserialize_from_bytes, serialize_to_bytes};
 use skypie_lib::args::Args;
 use skypie_lib::log_entry::SkyPieLogEntryType;
 use skypie_lib::monitor::MonitorMovingAverage;
-use skypie_lib::iter_stream_batches::iter_stream_batches;
+use skypie_lib::checkpoint::Checkpoint;
+use skypie_lib::chunks::{split_chunks, Chunk, ChunkRequest, ChunkScheduler};
+use skypie_lib::combination_ranking::CombinationRanking;
 use skypie_lib::Loader;
+use skypie_lib::input_format::InputFormat;
+use skypie_lib::id_catalogue::IdCatalogue;
+use skypie_lib::logging::init_logging;
+use tracing::{info, info_span, Instrument};
 
 #[hydroflow::main]
 async fn main() {
     let mut ports = hydroflow::util::cli::init().await;
 
-    // Load the input
-    let args = Args::parse();
-
-    let loader = Loader::new(
-        &args.network_file,
-        &args.object_store_file,
-        &args.region_selector,
-        &args.object_store_selector,
-        &args.latency_file,
-        &args.latency_slo,
-        None,
-    );
-
     // Get ports
     let output_send = ports
         .port("output")
@@ -34,83 +26,108 @@ async fn main() {
         .await
         .into_sink();
 
+    let request_recv = ports
+        .port("request_input")
+        .connect::<ConnectedDirect>()
+        .await
+        .into_source();
+
     let time_sink = ports
         .port("time_output")
         .connect::<ConnectedDirect>() 
         .await
         .into_sink();
 
+    let count_sink = ports
+        .port("count_output")
+        .connect::<ConnectedDirect>()
+        .await
+        .into_sink();
+
+    // Load the input
+    let args = Args::parse();
+    init_logging(&args.log_level, args.log_json).unwrap();
+
+    let span = info_span!("write_choices", worker_id = args.worker_id, experiment = %args.experiment_name);
+    let guard = span.enter();
+
+    let loader = Loader::with_id_catalogue(
+        &args.network_file,
+        &args.object_store_file,
+        &args.region_selector,
+        &args.object_store_selector,
+        &args.latency_file,
+        &args.latency_slo,
+        &args.write_latency_slo,
+        &InputFormat::from_optional_file(&args.input_format).unwrap(),
+        IdCatalogue::from_optional_file(&args.id_catalogue).unwrap().as_ref(),
+        None,
+    ).unwrap();
+
     let object_stores = loader.object_stores;
     let replication_factor = args.replication_factor;
     let replication_factor_max = args.replication_factor_max.unwrap_or(replication_factor);
     let redundancy_elimination_workers: u32 = args.redundancy_elimination_workers;
 
     let mut output_monitor = MonitorMovingAverage::new(1000);
-    let output_log_frequency = 10000;
+    let output_log_frequency = 100;
 
+    // Workers pull chunks of write choices and enumerate them locally, so that busy workers do not fall behind
     let object_store_ids = object_stores.iter().map(|x| x.id).collect::<Vec<_>>();
+    let ranking = CombinationRanking::new(object_store_ids, replication_factor, replication_factor_max);
 
-    //let iter_batch_size = 5000; //args.batch_size*20;
-    //let iter = IterWrapper::new(object_store_ids, replication_factor);
-    let tombstone = vec![];
-    let tombstone_vec = vec![tombstone];
-    let tombstone_iter = tombstone_vec.into_iter();
-    let iter = 
-    (replication_factor..=replication_factor_max)
-    .map(move |n| object_store_ids.clone().into_iter()
-    .combinations(n))
-    .flatten()
-    .chain(tombstone_iter);
-    
-    let iter_batch_size = if args.replication_factor <= 2 {10} else {5000};
-    let combo_batches_stream = iter_stream_batches(iter, iter_batch_size);
+    // Continue after the last write choice processed per chunk by any worker
+    let watermarks = if args.resume {
+        Checkpoint::load_watermarks(&args.experiment_name, redundancy_elimination_workers as usize, args.chunk_size).unwrap()
+    } else {
+        Default::default()
+    };
+    let chunks = split_chunks(ranking.total(), args.chunk_size, &watermarks);
+    info!(write_choices = ranking.total(), chunks = chunks.len(), "Split write choices into chunks");
+    let mut scheduler = ChunkScheduler::new(chunks, redundancy_elimination_workers);
+    // Progress of acknowledged chunks, starting with the write choices processed before resuming
+    let resumed_done = (ranking.total() - scheduler.no_remaining()) as usize;
 
     let flow = hydroflow_syntax! {
-        write_choices = source_stream(combo_batches_stream);
-        // Distribute the write choices among instances of next stage
-        branched_payload_and_tombstone = write_choices
-            -> inspect(|_|{
-                output_monitor.add_arrival_time_now();
-                output_monitor.print("Write choices:", Some(output_log_frequency));
-            })
-            -> demux(|v: Vec<u16>, var_args!(payload, tombstone)| {
-                if v.len() > 0 {
-                    payload.give(v)
-                }
-                else {
-                    tombstone.give(v)
+        requests = source_stream(request_recv) -> map(|x| -> ChunkRequest {deserialize_from_bytes(x.unwrap()).unwrap()});
+
+        // Assign the next chunk to the requesting worker, broadcast the tombstone once all chunks are acknowledged
+        scheduled = requests -> map(|request: ChunkRequest| {
+            let start = std::time::Instant::now();
+            let done = scheduler.no_done();
+            let responses = scheduler.on_request(request).unwrap();
+            (responses, start.elapsed(), (scheduler.no_done() - done) as usize)
+        }) -> demux(|(responses, duration, done): (Vec<(u32, Option<Chunk>)>, std::time::Duration, usize), var_args!(payload, time, count)| {
+            payload.give(responses);
+            time.give(duration);
+            count.give(done);
+        });
+
+        serialized = scheduled[payload] -> flatten()
+            -> inspect(|(id, chunk): &(u32, Option<Chunk>)|{
+                match chunk {
+                    Some(_) => {
+                        output_monitor.add_arrival_time_now();
+                        output_monitor.print("Chunks:", Some(output_log_frequency));
+                    },
+                    None => info!(worker_id = id, "Tombstone sent"),
                 }
-            });
-
-        // Round robin send payload to one worker of the next stage
-        branched_payload_and_tombstone[payload]
-            -> map(|x| -> (u32, _) {(context.current_tick() as u32 % redundancy_elimination_workers, x)})
-            -> serialized;
-            //-> map(|x| (rng.gen_range(0..redundancy_elimination_workers), x));
-
-        // Broadcast tombstone to all workers of the next stage
-        source_iter(0..redundancy_elimination_workers) -> persist() -> [0]broadcast_tombstone;
-        branched_payload_and_tombstone[tombstone] -> [1]broadcast_tombstone;
-        broadcast_tombstone = cross_join_multiset() 
-            -> inspect(|(id, _): &(u32, _)| println!("Tombstone sent to {}", id))
-            -> serialized;
-
-        serialized = union() -> map(|(id, payload): (u32, Vec<u16>)| (id, serialize_to_bytes(payload))) -> tee();
+            })
+            -> map(|(id, chunk): (u32, Option<Chunk>)| (id, serialize_to_bytes(chunk)));
         serialized -> dest_sink(output_send);
 
-        // Measure the total cycle time here
-        tick_duration =
-            serialized -> reduce(|_,_| ()) // Current tick
-            -> map(|_| context.current_tick_start()) // Time of current tick
-            -> defer_tick() // Wait for next tick
-            // Duration between start of current tick and start of next tick
-            -> map(|prev_tick| context.current_tick_start() - prev_tick)
-            -> map(|d|(SkyPieLogEntryType::WriteChoiceGeneration, d));
-
-        tick_duration -> map(|d|{serialize_to_bytes(d)}) -> dest_sink(time_sink);
+        // Time spent on scheduling, write choices are enumerated by the workers
+        scheduled[time] -> reduce(|acc: &mut std::time::Duration, d|{*acc = *acc + d})
+            -> map(|d|(SkyPieLogEntryType::ChunkScheduling, d))
+            -> map(|d|{serialize_to_bytes(d)}) -> dest_sink(time_sink);
 
+        // Write choices of acknowledged chunks for progress reporting
+        count_sink = union() -> map(|c: usize|{serialize_to_bytes((SkyPieLogEntryType::WriteChoiceCount, c))}) -> dest_sink(count_sink);
+        source_iter([resumed_done]) -> filter(|c: &usize| *c > 0) -> count_sink;
+        scheduled[count] -> reduce(|acc: &mut usize, c|{*acc = *acc + c}) -> filter(|c: &usize| *c > 0) -> count_sink;
     };
 
-    hydroflow::util::cli::launch_flow(flow).await;
-    println!("DONE: write choices sent");
+    drop(guard);
+    hydroflow::util::cli::launch_flow(flow).instrument(span.clone()).await;
+    span.in_scope(|| info!("DONE: write choices sent"));
 }
@@ -1,5 +1,6 @@
 use clap::Parser;
-use std::path::PathBuf;
+use skypie_proto_messages::ProvenanceCheck;
+use std::{io, path::PathBuf};
 
 #[derive(Debug, Parser)]
 pub struct Args {
@@ -41,10 +42,26 @@ pub struct Args {
     #[clap(long)]
     pub latency_slo: Option<f64>,
 
+    /// Maximum latency of writes from an application region to the replicas of a write choice
+    #[clap(long)]
+    pub write_latency_slo: Option<f64>,
+
+    /// Number of replicas that must meet the write latency SLO, defaults to all replicas
+    #[clap(long)]
+    pub write_quorum: Option<usize>,
+
     /// Object store file
     #[clap(short = 's', long)]
     pub object_store_file: PathBuf,
 
+    /// JSON file with the column names, naming rules and region aliases of the input files, see `InputFormat`
+    #[clap(long)]
+    pub input_format: Option<PathBuf>,
+
+    /// CSV file with the catalogue ids to use as ids of regions and object stores, see `IdCatalogue`
+    #[clap(long)]
+    pub id_catalogue: Option<PathBuf>,
+
     /// Output the candidates
     #[clap(long)]
     pub output_candidates: bool,
@@ -58,18 +75,104 @@ pub struct Args {
     #[clap(long)]
     pub executor_name: Option<String>,
 
+    /// Log level or filter directives, e.g., "debug" or "info,skypie_lib=trace", overridden by RUST_LOG
+    #[clap(long, default_value = "info")]
+    pub log_level: String,
+
+    /// Log as JSON lines, e.g., for log aggregation
     #[clap(long)]
-    pub influx_host: Option<String>,
+    pub log_json: bool,
 
+    /// Serve metrics in the OpenMetrics format at this port of the logger
     #[clap(long)]
+    pub metrics_port: Option<u16>,
+
+    /// Address of the metrics endpoint, e.g., 0.0.0.0 to expose it beyond this host
+    #[clap(long, default_value = "127.0.0.1")]
+    pub metrics_address: String,
+
+    #[clap(long, default_value_t = 0)]
     pub worker_id: usize,
 
-    #[clap(long)]
+    #[clap(long, default_value_t = 1)]
     pub num_workers: usize,
 
     #[clap(long)]
     pub optimizer: Option<String>,
 
     #[clap(long)]
-    pub use_clarkson: bool
+    pub use_clarkson: bool,
+
+    /// Skip write choices and candidates with replicas that are never read, as long as the smaller write choice is enumerated, i.e., exceeds the replication factor.
+    /// Decisions are only dominated by the smaller write choice if it is not excluded by durability constraints or write latency SLOs,
+    /// so it cannot be combined with `min_vendors`, `required_region` or `write_latency_slo`, see `unread_replica_pruning`.
+    #[clap(long)]
+    pub prune_unread_replicas: bool,
+
+    /// Number of write choices per chunk that a worker requests at a time, resuming with another chunk size fails
+    #[clap(long, default_value_t = 1000)]
+    pub chunk_size: u64,
+
+    /// Seconds between progress reports of the logger
+    #[clap(long, default_value_t = 10)]
+    pub progress_interval: u64,
+
+    /// Seconds between checkpoints of a worker, no checkpoints if not set
+    #[clap(long)]
+    pub checkpoint_interval: Option<u64>,
+
+    /// Resume from the last checkpoints in the experiment directory
+    #[clap(long)]
+    pub resume: bool,
+
+    /// Only enumerate write choices with replicas at at least this many vendors
+    #[clap(long)]
+    pub min_vendors: Option<usize>,
+
+    /// Only enumerate write choices with all replicas in distinct regions
+    #[clap(long)]
+    pub distinct_regions: bool,
+
+    /// Only enumerate write choices with at least one replica in a region matching this regex, can be repeated
+    #[clap(long)]
+    pub required_region: Vec<String>,
+
+    /// Stats file of the oracle precomputed with the previous price inputs, only re-enumerates write choices with changed prices if set
+    #[clap(long, requires_all = ["previous_network_file", "previous_object_store_file"])]
+    pub previous_oracle: Option<PathBuf>,
+
+    /// Network file of the previous oracle
+    #[clap(long)]
+    pub previous_network_file: Option<PathBuf>,
+
+    /// Object store file of the previous oracle
+    #[clap(long)]
+    pub previous_object_store_file: Option<PathBuf>,
+
+    /// Handling of a previous oracle that was computed from other files than the previous price inputs: "ignore", "warn" or "refuse"
+    #[clap(long, default_value = "refuse")]
+    pub provenance_check: ProvenanceCheck,
+}
+
+impl Args {
+    /// Whether write choices with unread replicas are pruned, see `prune_unread_replicas`.
+    /// Fails if pruning is combined with constraints that can exclude the smaller write choice that dominates the pruned decisions.
+    /// Distinct regions hold for any smaller write choice, so they do not conflict.
+    pub fn unread_replica_pruning(&self) -> io::Result<bool> {
+        if !self.prune_unread_replicas {
+            return Ok(false);
+        }
+
+        let conflicts = [
+            ("--min-vendors", self.min_vendors.is_some()),
+            ("--required-region", !self.required_region.is_empty()),
+            ("--write-latency-slo", self.write_latency_slo.is_some()),
+        ].into_iter().filter(|(_, set)| *set).map(|(flag, _)| flag).collect::<Vec<_>>();
+
+        if conflicts.is_empty() {
+            Ok(true)
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidInput, format!("--prune-unread-replicas cannot be combined with {}, they can exclude the smaller write choice that dominates the pruned decisions", conflicts.join(", "))))
+        }
+    }
 }
@@ -1,9 +1,9 @@
-use std::{collections::HashMap, path::Path};
+use std::{cell::RefCell, collections::HashMap, io, path::{Path, PathBuf}, rc::Rc, sync::Arc};
 
 use hydroflow::{hydroflow_syntax, tokio_stream::Stream, bytes::{BytesMut, Bytes}, futures::Sink, util::{deserialize_from_bytes, serialize_to_bytes}};
 use itertools::Itertools;
-use pyo3::{Python, PyAny, Py, types::{PyModule, PyDict}};
-use skypie_proto_messages::ProtobufFileSink;
+use skypie_proto_messages::{oracle_container, ProtobufFileSink};
+use tracing::{debug, debug_span, info, warn};
 
 use crate::{
     write_choice::WriteChoice,
@@ -13,7 +13,9 @@ use crate::{
     identifier::Identifier,
     monitor::{MonitorNOOP, MonitorMovingAverage},
     ApplicationRegion,
-    compatibility_checker::CompatibilityChecker, log_entry::SkyPieLogEntryType, opt_assignments::opt_assignments, merge_policies::MergeIterator, Tombstone
+    compatibility_checker::{CompatibilityChecker, WriteCompatibilityChecker}, log_entry::SkyPieLogEntryType, merge_policies::MergeIterator, Tombstone,
+    checkpoint::{Checkpoint, Checkpointer, DecisionIds, InFlight}, redundancy_elimination::RedundancyElimination, metrics::Metrics, write_choice_candidates::write_choice_candidates, stats::oracle_header,
+    chunks::{chunk_work_items, Chunk, ChunkRequest, WorkItem}, combination_ranking::CombinationRanking, write_choice_constraints::WriteChoiceConstraints
 };
 
 pub type InputType = WriteChoice;
@@ -21,17 +23,113 @@ pub type OutputType = Decision;
 pub type InputConnection = std::pin::Pin<Box<dyn Stream<Item = Result<BytesMut, std::io::Error>> + Send + Sync>>;
 pub type OutputConnection = std::pin::Pin<Box<dyn Sink<Bytes, Error = std::io::Error> + Send + Sync>>;
 
-pub fn candidate_policies_reduce_hydroflow(regions: &'static Vec<ApplicationRegion>, input: InputConnection, batch_size: usize, _experiment_name: String, output_candidates_file_name: String, output_file_name: String, object_store_id_map: HashMap<u16, ObjectStore>, time_sink: OutputConnection, done_sink: OutputConnection, worker_id: usize, optimizer: Option<String>, use_clarkson: bool, compatibility_checker_slos: Box<dyn CompatibilityChecker>, count_sink: OutputConnection) -> hydroflow::scheduled::graph::Hydroflow
+// Stages of the pipeline tracked for consistent checkpoints
+const WRITE_CHOICES: &str = "write choices";
+const CANDIDATE_SINK: &str = "candidate sink";
+const BATCHER: &str = "batcher";
+const BATCHES: &str = "batches";
+const OPTIMAL_SINK: &str = "optimal sink";
+
+/// State of a worker that is shared among operators and captured by checkpoints
+#[derive(Clone)]
+struct WorkerState {
+    candidate_sink: Rc<RefCell<ProtobufFileSink>>,
+    optimal_sink: Rc<RefCell<ProtobufFileSink>>,
+    // Files of the sinks, sealed once the worker is done
+    output_file_names: Rc<[PathBuf; 2]>,
+    batcher: Rc<RefCell<BatcherMap<Decision>>>,
+    counts: Rc<RefCell<HashMap<SkyPieLogEntryType, usize>>>,
+    in_flight: Rc<RefCell<InFlight>>,
+    checkpointer: Option<Rc<RefCell<Checkpointer>>>,
+    optimal_monitor: Rc<RefCell<MonitorMovingAverage>>,
+    // Metrics of the monitors written at shutdown, with the experiment and worker id as labels
+    metrics: Rc<RefCell<Metrics>>,
+    metrics_file_name: Rc<PathBuf>,
+}
+
+impl WorkerState {
+    fn add_count(&self, entry_type: SkyPieLogEntryType, count: usize) {
+        *self.counts.borrow_mut().entry(entry_type).or_default() += count;
+    }
+
+    fn send(&self, stage: &'static str, count: usize) {
+        self.in_flight.borrow_mut().send(stage, count);
+    }
+
+    fn receive(&self, stage: &'static str, count: usize) {
+        self.in_flight.borrow_mut().receive(stage, count);
+    }
+
+    /// Start processing a write choice, false if it was already processed before resuming
+    fn start(&self, chunk: u64, rank: u64) -> bool {
+        match &self.checkpointer {
+            Some(checkpointer) => checkpointer.borrow_mut().start(chunk, rank),
+            None => true,
+        }
+    }
+
+    fn finish_chunk(&self, chunk: &Chunk) {
+        if let Some(checkpointer) = &self.checkpointer {
+            checkpointer.borrow_mut().finish_chunk(chunk);
+        }
+    }
+
+    /// Checkpoint if due or forced, must be called between write choices.
+    /// Skipped while decisions of previous write choices are in flight, a due checkpoint is retried at the next write choice.
+    fn checkpoint(&self, force: bool) -> io::Result<()> {
+        if let Some(checkpointer) = &self.checkpointer {
+            let mut checkpointer = checkpointer.borrow_mut();
+            if force || checkpointer.is_due() {
+                if !self.in_flight.borrow().is_drained() {
+                    if force {
+                        warn!(in_flight = ?self.in_flight.borrow(), "Decisions in flight, skipping final checkpoint");
+                    } else {
+                        debug!(in_flight = ?self.in_flight.borrow(), "Decisions in flight, postponing checkpoint");
+                    }
+                    return Ok(());
+                }
+
+                let checkpoint = Checkpoint {
+                    chunks: HashMap::new(),
+                    batch: self.batcher.borrow().get_batch().iter().map(DecisionIds::from).collect(),
+                    candidates_offset: self.candidate_sink.borrow_mut().offset()?,
+                    optimal_offset: self.optimal_sink.borrow_mut().offset()?,
+                    counts: self.counts.borrow().iter().map(|(t, c)| (t.clone(), *c)).collect(),
+                    ..Default::default()
+                };
+                checkpointer.save(checkpoint)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&self) {
+        self.candidate_sink.borrow_mut().flush().unwrap();
+        self.optimal_sink.borrow_mut().flush().unwrap();
+    }
+
+    fn save_metrics(&self) {
+        let mut metrics = self.metrics.borrow_mut();
+        metrics.update_from_monitor("optimal", &self.optimal_monitor.borrow());
+        metrics.save(&self.metrics_file_name);
+    }
+
+    /// Append the index to the output containers, after the final checkpoint so that resuming drops the index
+    fn seal(&self) {
+        for file_name in self.output_file_names.iter() {
+            oracle_container::seal(file_name).unwrap();
+        }
+    }
+}
+
+pub fn candidate_policies_reduce_hydroflow(regions: &'static Vec<ApplicationRegion>, input: InputConnection, batch_size: usize, experiment_name: String, output_candidates_file_name: String, output_file_name: String, object_store_id_map: HashMap<u16, ObjectStore>, time_sink: OutputConnection, done_sink: OutputConnection, worker_id: usize, optimizer: Option<String>, use_clarkson: bool, compatibility_checker_slos: Box<dyn CompatibilityChecker>, write_compatibility_checker: Box<dyn WriteCompatibilityChecker>, count_sink: OutputConnection, replication_factor: usize, prune_unread_replicas: bool, checkpointer: Option<Checkpointer>, resume: Option<Checkpoint>, request_sink: OutputConnection, ranking: Arc<CombinationRanking>, constraints: Arc<WriteChoiceConstraints>, object_stores: Arc<[ObjectStore]>) -> io::Result<hydroflow::scheduled::graph::Hydroflow>
 {
     {
         // Validate application regions
-        let min = regions.iter().map(|r|r.get_id()).min().unwrap();
-        let max = regions.iter().map(|r|r.get_id()).max().unwrap();
+        // Ids can have gaps, e.g., with an id catalogue
         let unique = regions.iter().map(|r|r.get_id()).unique().collect_vec().len();
 
         debug_assert_eq!(regions.len(), unique);
-        debug_assert_eq!(min, 0);
-        debug_assert_eq!(max as usize, regions.len() - 1);
 
         for r in regions {
             debug_assert_ne!(r.get_id(), u16::MAX);
@@ -40,62 +138,77 @@ pub fn candidate_policies_reduce_hydroflow(regions: &'static Vec<ApplicationRegi
     
     }
 
-    let no_dimensions = if use_clarkson {Decision::get_no_dimensions(regions.len())} else {0};
-
     let mut input_monitor = MonitorNOOP::new(1000); //MonitorNOOP::new(0); //MonitorMovingAverage::new(1000);
     let input_log_interval = 1;
     //let mut output_monitor = MonitorMovingAverage::new(1000); //MonitorNOOP::new(0);
 
-    let module = "";
-    let fun_name = "redundancy_elimination";
-    let code = include_str!("python_redundancy_bridge.py");
-    let fun = Python::with_gil(|py| {
-
-        // Load python code as module
-        let module = PyModule::from_code(py, code, "", module).unwrap();
-        // Load arguments via python function "load_args"
-        let kwargs = PyDict::new(py);
-        kwargs.set_item("dsize", batch_size).unwrap();
-        kwargs.set_item("use_clarkson", use_clarkson).unwrap();
-        if let Some(optimizer) = optimizer {
-            kwargs.set_item("optimizer", optimizer).unwrap();
-        }
-        let res = module.call_method("load_args", (), Some(kwargs));
-        if let Err(e) = res {
-            println!("Error in load_args: {}", e);
-        }
-
-        // Get reference to python function for redundancy elimination
-        let fun: Py<PyAny> =
-            //PyModule::import(py, module)
-            module.getattr(fun_name)
-            .unwrap()
-            .into();
-        fun
-    });
-    let fun: &Py<PyAny> = &*Box::leak(Box::new(fun));
+    let redundancy_elimination: &'static RedundancyElimination = Box::leak(Box::new(RedundancyElimination::new(batch_size, optimizer, use_clarkson, regions.len())));
 
     type Input = Decision;
     let mut batcher = BatcherMap::<Input>::new(batch_size);
+    if let Some(resume) = &resume {
+        // Restore candidates that were not reduced yet, fewer than a batch
+        for d in resume.restore_batch(regions, &object_store_id_map)? {
+            let res = batcher.add(d);
+            debug_assert!(res.is_none());
+        }
+    }
 
     let mut reduce_input_monitor = MonitorNOOP::new(1000); //MonitorMovingAverage::new(1000);
     let mut reduce_batch_monitor = MonitorNOOP::new(1000); //MonitorMovingAverage::new(1000);
     let batch_logging_frequency = Some(1);
-    let mut reduce_output_monitor =  MonitorMovingAverage::new(1000); // MonitorNOOP::new(0);
     let optimal_log_interval = Some(1000);
 
-    let candidate_proto_sink = ProtobufFileSink::new(Path::new(&output_candidates_file_name), 1*1024*1024, 1024).unwrap();
-    let optimal_proto_sink = ProtobufFileSink::new(Path::new(&output_file_name), 1*1024*1024, 1024).unwrap();
+    // Continue the output files at the checkpoint, dropping decisions written after it
+    let (candidate_proto_sink, optimal_proto_sink) = if let Some(resume) = &resume {
+        (
+            ProtobufFileSink::open_at(Path::new(&output_candidates_file_name), resume.candidates_offset, 1*1024*1024, 1024)?,
+            ProtobufFileSink::open_at(Path::new(&output_file_name), resume.optimal_offset, 1*1024*1024, 1024)?
+        )
+    } else {
+        let header = oracle_header(regions, &object_stores);
+        (
+            oracle_container::create(Path::new(&output_candidates_file_name), &header, 1*1024*1024, 1024)?,
+            oracle_container::create(Path::new(&output_file_name), &header, 1*1024*1024, 1024)?
+        )
+    };
+
+    // Counts before resuming are reported again, since the logger starts over
+    let resumed_counts = resume.map(|r| r.counts).unwrap_or_default();
+
+    let state = WorkerState {
+        candidate_sink: Rc::new(RefCell::new(candidate_proto_sink)),
+        optimal_sink: Rc::new(RefCell::new(optimal_proto_sink)),
+        output_file_names: Rc::new([PathBuf::from(&output_candidates_file_name), PathBuf::from(&output_file_name)]),
+        batcher: Rc::new(RefCell::new(batcher)),
+        counts: Rc::new(RefCell::new(resumed_counts.iter().cloned().collect())),
+        in_flight: Rc::new(RefCell::new(InFlight::default())),
+        optimal_monitor: Rc::new(RefCell::new(MonitorMovingAverage::new(1000))),
+        metrics: Rc::new(RefCell::new(Metrics::new(vec![("experiment".to_string(), experiment_name.clone()), ("worker".to_string(), worker_id.to_string())]))),
+        metrics_file_name: Rc::new(Metrics::worker_file_name(&experiment_name, worker_id)),
+        checkpointer: checkpointer.map(|c| Rc::new(RefCell::new(c))),
+    };
+    // Each subgraph captures its own handle on the shared state
+    let (state_done, state_candidates, state_chunk_done, state_flatten, state_pruned_candidates, state_candidate_sink, state_batcher, state_reduce, state_optimal_sink) =
+        (state.clone(), state.clone(), state.clone(), state.clone(), state.clone(), state.clone(), state.clone(), state.clone(), state.clone());
+
+    let worker_id_u32 = worker_id as u32;
+
+    // Decisions with unread replicas are dominated by the smaller write choice, if it is enumerated
+    let prunable = move |no_replicas: usize| prune_unread_replicas && no_replicas > replication_factor;
+    let shared_object_store_id_map = Arc::new(object_store_id_map.clone());
 
     let flow = hydroflow_syntax! {
-        source_in = source_stream(input) -> map(|x| -> Vec<u16> {deserialize_from_bytes(x.unwrap()).unwrap()})
+        // Chunks of write choices, None is the tombstone
+        source_in = source_stream(input) -> map(|x| -> Option<Chunk> {deserialize_from_bytes(x.unwrap()).unwrap()})
         -> inspect(|_|{
             input_monitor.add_arrival_time_now();
             input_monitor.print("Input:", Some(input_log_interval));
         }) -> tee();
 
         time_sink = union() -> dest_sink(time_sink);
-        count_sink = dest_sink(count_sink);
+        count_sink = union() -> dest_sink(count_sink);
+        source_iter(resumed_counts) -> map(|c: (SkyPieLogEntryType, usize)|{serialize_to_bytes(c)}) -> count_sink;
         
         // Measure the total cycle time here
         tick_duration =
@@ -155,71 +268,106 @@ pub fn candidate_policies_reduce_hydroflow(regions: &'static Vec<ApplicationRegi
         })
         -> tee(); */
 
+        // Request the first chunk, and the next chunk after completing a chunk
+        request_sink = union() -> map(|r: ChunkRequest|{serialize_to_bytes(r)}) -> dest_sink(request_sink);
+        source_iter([ChunkRequest{worker_id: worker_id_u32, done: None}]) -> request_sink;
+
         // Non-hydro version
-        tombstone_signal = source_in -> demux(|v: Vec<u16>, var_args!(payload, tombstone)| {
-            if v.len() == 0 {
-                tombstone.give(v.clone());
+        tombstone_signal = source_in
+        // Enumerate the write choices of the chunk locally
+        -> flat_map(|chunk: Option<Chunk>| chunk_work_items(chunk, &ranking, &constraints, &shared_object_store_id_map))
+        -> demux(|item: WorkItem, var_args!(payload, chunk_done, tombstone)| {
+            match item {
+                WorkItem::WriteChoice{chunk, rank, ids} => payload.give((chunk, rank, ids)),
+                WorkItem::ChunkDone(chunk) => chunk_done.give(chunk),
+                WorkItem::Tombstone => {
+                    tombstone.give(());
+                    payload.give((0, 0, vec![]));
+                }
             }
-            payload.give(v);
         });
 
+        // All decisions of the chunk are pushed through the pipeline before its completion
+        tombstone_signal[chunk_done]
+            -> inspect(|chunk: &Chunk|{
+                info!(chunk = chunk.id, start = chunk.start, end = chunk.end, "Chunk done");
+                state_chunk_done.finish_chunk(chunk);
+            })
+            -> map(|chunk: Chunk| ChunkRequest{worker_id: worker_id_u32, done: Some(chunk.id)})
+            -> request_sink;
+
         // Signal termination of this worker after draining all work
-        tombstone_signal[tombstone] -> defer_tick() -> map(|_|{serialize_to_bytes(worker_id)}) -> dest_sink(done_sink);
-
-        candidates = tombstone_signal[payload] -> map(|write_choice_ids: Vec<u16>| {
-            if write_choice_ids.len() > 0 {
-
-                //object_store_id_map
-                let object_stores = write_choice_ids.iter().map(|id|{
-                    object_store_id_map.get(id).unwrap().clone()
-                }).collect_vec();
-                let write_choice = WriteChoice{object_stores};
-
-                let write_choice = Box::<WriteChoice>::new(write_choice);
-                //let mut i = 0; // for debugging
-                let assignments = regions.iter()
-                    .map(|r|{
-                        // Get optimal assignments of region r
-                        let assignments = opt_assignments(write_choice.clone(), r, &compatibility_checker_slos);
-                        // Convert to (upper bound, object store) pairs 
-                        let assignments = assignments.map(|(o, range)|(range.max, o));
-
-                        // Collect into vector
-                        return (r.clone(), assignments.collect::<Vec<(f64, ObjectStore)>>());
-                    })
-                    .filter(|(_, v)|{v.len() > 0}); // Filter out empty assignments
-
-                // Merge assignments per region to candidate policies
-                // XXX: Materializing here is not necessary
-                // In HyrdoFlow, use stream of (region, upper bound, object store), then search min per region and then merge?
-                let assignments = HashMap::from_iter(assignments);
-
-                #[cfg(dev)]
-                println!("Assignments: {}", assignments.len());
-
-                // Each app region must have at least one assignment otherwise there is no feasible candidate placement
-                if assignments.len() == regions.len() {
-                    return MergeIterator::new(write_choice, assignments);
-                } else {
-                    // Print number of assignments per application region
-                    
-                    #[cfg(dev)]
-                    for (r, v) in assignments.iter() {
-                        println!("Region {}: {}", r.get_id(), v.len());
-                    }
+        tombstone_signal[tombstone] -> defer_tick()
+            -> inspect(|_|{
+                state_done.flush();
+                state_done.checkpoint(true).unwrap();
+                state_done.seal();
+                state_done.save_metrics();
+            })
+            -> map(|_|{serialize_to_bytes(worker_id)}) -> dest_sink(done_sink);
+
+        candidates_zip = tombstone_signal[payload] -> map(|(chunk, rank, write_choice_ids): (u64, u64, Vec<u16>)| -> (MergeIterator, usize) {
+            let res = if write_choice_ids.len() > 0 {
+                let _span = debug_span!("write_choice", chunk, rank).entered();
+                // The state is consistent between write choices once no decision of the previous write choices is in flight
+                state_candidates.checkpoint(false).unwrap();
 
-                    return MergeIterator::empty();
+                // Skip write choices processed before resuming
+                if !state_candidates.start(chunk, rank) {
+                    (MergeIterator::empty(), 0)
+                } else {
+                    write_choice_candidates(&write_choice_ids, regions, &object_store_id_map, compatibility_checker_slos.as_ref(), write_compatibility_checker.as_ref(), prunable(write_choice_ids.len()))
                 }
             } else {
-                return MergeIterator::tombstone();
+                (MergeIterator::tombstone(), 0)
+            };
+
+            // Counts are taken where the decisions are consumed, so that checkpoints capture them consistently
+            state_candidates.add_count(SkyPieLogEntryType::PrunedWriteChoiceCount, res.1);
+            state_candidates.send(WRITE_CHOICES, 1);
+            res
+        }) -> unzip();
+
+        candidates_zip[1] -> reduce(|acc: &mut usize, c|{*acc = *acc + c}) -> map(|c|(SkyPieLogEntryType::PrunedWriteChoiceCount, c)) -> map(|c|{serialize_to_bytes(c)}) -> count_sink;
+
+        // Drop candidates that do not read every replica
+        candidates_pruned = candidates_zip[0]
+        -> inspect(|_|{state_flatten.receive(WRITE_CHOICES, 1)})
+        -> flatten()
+        -> inspect(|d: &Decision|{
+            if !d.is_tombstone() {
+                state_flatten.send(CANDIDATE_SINK, 1);
+                state_flatten.send(BATCHER, 1);
             }
-        }) -> flatten() -> tee();
+        })
+        -> demux(|d: Decision, var_args!(payload, pruned)| {
+            if !d.is_tombstone() && prunable(d.write_choice.object_stores.len()) && !d.reads_all_replicas() {
+                pruned.give(d);
+            } else {
+                payload.give(d);
+            }
+        });
+
+        candidates_pruned[pruned] -> map(|_|1) -> inspect(|c|{
+            state_pruned_candidates.add_count(SkyPieLogEntryType::PrunedCandidateCount, *c);
+            state_pruned_candidates.receive(CANDIDATE_SINK, 1);
+            state_pruned_candidates.receive(BATCHER, 1);
+        }) -> reduce(|acc: &mut usize, c|{*acc = *acc + c}) -> map(|c|(SkyPieLogEntryType::PrunedCandidateCount, c)) -> map(|c|{serialize_to_bytes(c)}) -> count_sink;
+
+        candidates = candidates_pruned[payload] -> tee();
+
+        // Count candidates for progress reporting
+        candidates -> filter(|d: &Decision|{!d.is_tombstone()}) -> map(|_|1) -> reduce(|acc: &mut usize, c|{*acc = *acc + c}) -> map(|c|(SkyPieLogEntryType::CandidateCount, c)) -> map(|c|{serialize_to_bytes(c)}) -> count_sink;
 
         // Output candidates
         candidates
         -> filter(|d|{!d.is_tombstone()}) // Filter out tombstones for file output
-        -> map(|d: Decision| -> skypie_proto_messages::Decision {d.into()})
-        -> dest_sink(candidate_proto_sink);
+        -> map(|d: Decision| -> skypie_proto_messages::Decision {d.into_compact_proto()})
+        -> for_each(|d|{
+            state_candidate_sink.candidate_sink.borrow_mut().write(d).unwrap();
+            state_candidate_sink.add_count(SkyPieLogEntryType::CandidateCount, 1);
+            state_candidate_sink.receive(CANDIDATE_SINK, 1);
+        });
 
         // XXX: Materializing decisions here, since putting lifetime into the Batcher is difficult
         reduce_input = candidates -> map(|x: Decision| x);
@@ -232,12 +380,18 @@ pub fn candidate_policies_reduce_hydroflow(regions: &'static Vec<ApplicationRegi
         // Filter_map drops None values
         // XXX: Use hydro's batch operator. But it has to have sufficient items, about batch size!
         -> filter_map(|x: Input|{
-            if x.is_tombstone() {
-                println!("Flushing batcher: {}", batcher.get_batch().len());
+            let batch = if x.is_tombstone() {
+                let mut batcher = state_batcher.batcher.borrow_mut();
+                info!(no_decisions = batcher.get_batch().len(), "Flushing batcher");
                 batcher.flush()
             } else {
-                batcher.add(x)
+                state_batcher.receive(BATCHER, 1);
+                state_batcher.batcher.borrow_mut().add(x)
+            };
+            if batch.is_some() {
+                state_batcher.send(BATCHES, 1);
             }
+            batch
         })
         -> inspect(|_|{
             reduce_batch_monitor.add_arrival_time_now();
@@ -246,51 +400,9 @@ pub fn candidate_policies_reduce_hydroflow(regions: &'static Vec<ApplicationRegi
 
         // Redundancy elimination via python
         optimal_zip = batches -> map(|decisions: Vec<Decision>| {
-            let no_candidates = decisions.len();
-
-            // Start time of computing optimal decisions
-            let start = std::time::Instant::now();
-
-            // Clarkson's algorithm cannot handle the case of fewer decisions than dimensions
-            let optimal = if decisions.len() < no_dimensions {
-                decisions
-            }
-            else {
-
-                // Convert batch of decisions to numpy array
-                let py_array = Decision::to_inequalities_numpy(&decisions);
-    
-                let optimal = Python::with_gil(|py| {
-                    type T = Vec<usize>;
-        
-                    // Computing optimal decisions by row IDs in vector
-                    let py_res = fun.call(py, (py_array,), None).unwrap();
-                    let res: T = py_res.extract(py).unwrap();
-    
-                    //println!("Optimal decisions: ({}) {:?}", res.len(), res);
-                    
-                    // Extract optimal decisions by ids in res
-                    let mut optimal = Vec::with_capacity(res.len());
-                    for id in res {
-                        optimal.push(decisions[id].clone());
-                    }
-    
-                    optimal
-                });
-
-                optimal
-            };
-
-            // End time of computing optimal decisions
-            let end = std::time::Instant::now();
-            let duration = end - start;
-
-            let no_optimal = optimal.len();
-            println!("Optimal: {}/{} (-{})", no_optimal, no_candidates,  no_candidates - no_optimal);
-            /* if diff <= 5 {
-                println!("Optimal: {}/{} (-{})", no_optimal, no_candidates,  no_candidates - no_optimal);
-            } */
-
+            let (duration, optimal) = redundancy_elimination.reduce(decisions);
+            state_reduce.receive(BATCHES, 1);
+            state_reduce.send(OPTIMAL_SINK, optimal.len());
             (duration, optimal)
         })
         -> unzip();
@@ -308,11 +420,16 @@ pub fn candidate_policies_reduce_hydroflow(regions: &'static Vec<ApplicationRegi
         // Output optimal
         optimal[payload] -> flatten()
             -> inspect(|_|{
-                reduce_output_monitor.add_arrival_time_now();
-                reduce_output_monitor.print("Optimal:", optimal_log_interval);
+                let mut optimal_monitor = state_optimal_sink.optimal_monitor.borrow_mut();
+                optimal_monitor.add_arrival_time_now();
+                optimal_monitor.print("Optimal:", optimal_log_interval);
             })
-            -> map(|d: Decision| -> skypie_proto_messages::Decision {d.into()})
-            -> dest_sink(optimal_proto_sink);
+            -> map(|d: Decision| -> skypie_proto_messages::Decision {d.into_compact_proto()})
+            -> for_each(|d|{
+                state_optimal_sink.optimal_sink.borrow_mut().write(d).unwrap();
+                state_optimal_sink.add_count(SkyPieLogEntryType::OptimalCount, 1);
+                state_optimal_sink.receive(OPTIMAL_SINK, 1);
+            });
 
         // Time of optimal
         optimal_duration
@@ -323,6 +440,6 @@ pub fn candidate_policies_reduce_hydroflow(regions: &'static Vec<ApplicationRegi
 
     };
 
-    return flow;
+    Ok(flow)
 
 }
@@ -0,0 +1,332 @@
+use std::{collections::HashMap, fs::File, io, path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use hydroflow::serde_json;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunks::Chunk, identifier::Identifier, log_entry::SkyPieLogEntryType,
+    object_store::ObjectStore, read_choice::ReadChoice, ApplicationRegion, Decision, WriteChoice
+};
+
+/// Decision by ids of its object stores and application regions, for compact checkpoints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecisionIds {
+    pub write_choice: Vec<u16>,
+    // (application region id, object store id)
+    pub read_choice: Vec<(u16, u16)>,
+}
+
+impl From<&Decision> for DecisionIds {
+    fn from(decision: &Decision) -> Self {
+        DecisionIds {
+            write_choice: decision.write_choice.object_stores.iter().map(|o| o.id).collect(),
+            read_choice: decision.read_choice.iter().map(|(r, o)| (r.get_id(), o.id)).collect(),
+        }
+    }
+}
+
+impl DecisionIds {
+    /// Restore the decision, fails if the ids do not match the loaded regions and object stores
+    pub fn to_decision(&self, regions: &[ApplicationRegion], object_store_id_map: &HashMap<u16, ObjectStore>) -> io::Result<Decision> {
+        let object_store = |id: &u16| object_store_id_map.get(id).cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Unknown object store id in checkpoint: {}", id)));
+
+        let object_stores = self.write_choice.iter().map(object_store).collect::<io::Result<_>>()?;
+
+        let mut read_choice = ReadChoice::new(regions.len());
+        for (r, o) in &self.read_choice {
+            let region = regions.iter().find(|region| region.get_id() == *r)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Unknown application region id in checkpoint: {}", r)))?;
+            read_choice.insert(region.clone(), object_store(o)?);
+        }
+
+        Ok(Decision { write_choice: WriteChoice { object_stores }, read_choice })
+    }
+}
+
+/// State of a worker after completely processing the write choices of its chunks up to a rank
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Rank of the last processed write choice per chunk processed by the worker
+    pub chunks: HashMap<u64, u64>,
+    /// Candidates in the batcher that are not reduced yet
+    pub batch: Vec<DecisionIds>,
+    /// Flushed length of the candidate and optimal files
+    pub candidates_offset: u64,
+    pub optimal_offset: u64,
+    /// Counts already sent to the logger
+    pub counts: Vec<(SkyPieLogEntryType, usize)>,
+    /// Number of write choices per chunk, the chunks and ranks are only valid for the same chunk size
+    pub chunk_size: u64,
+}
+
+impl Checkpoint {
+    pub fn file_name(experiment_name: &str, worker_id: usize) -> PathBuf {
+        PathBuf::from(format!("{}/checkpoint_{}.json", experiment_name, worker_id))
+    }
+
+    /// Load a checkpoint, None if there is none
+    pub fn load(file_name: &Path) -> io::Result<Option<Checkpoint>> {
+        if !file_name.exists() {
+            return Ok(None);
+        }
+
+        let file = File::open(file_name)?;
+        serde_json::from_reader(file)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid checkpoint {:?}: {}", file_name, e)))
+    }
+
+    /// Fails with InvalidInput if the checkpoint was taken with another chunk size than the resumed precomputation
+    pub fn check_chunk_size(&self, chunk_size: u64) -> io::Result<()> {
+        if self.chunk_size == chunk_size {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Cannot resume with chunk size {}, the checkpoint was taken with chunk size {}", chunk_size, self.chunk_size)))
+        }
+    }
+
+    /// Candidates in the batcher that are not reduced yet
+    pub fn restore_batch(&self, regions: &[ApplicationRegion], object_store_id_map: &HashMap<u16, ObjectStore>) -> io::Result<Vec<Decision>> {
+        self.batch.iter().map(|d| d.to_decision(regions, object_store_id_map)).collect()
+    }
+
+    /// Watermarks of the chunks over the checkpoints of all workers, fails if a checkpoint was taken with another chunk size
+    pub fn load_watermarks(experiment_name: &str, no_workers: usize, chunk_size: u64) -> io::Result<HashMap<u64, u64>> {
+        let mut watermarks = HashMap::new();
+        for worker_id in 0..no_workers {
+            if let Some(checkpoint) = Checkpoint::load(&Checkpoint::file_name(experiment_name, worker_id))? {
+                checkpoint.check_chunk_size(chunk_size)?;
+                // A partially processed chunk may be continued by another worker after resuming
+                for (chunk, watermark) in checkpoint.chunks {
+                    let w = watermarks.entry(chunk).or_insert(watermark);
+                    *w = (*w).max(watermark);
+                }
+            }
+        }
+        Ok(watermarks)
+    }
+
+    /// Save the checkpoint atomically, i.e., a crash while saving keeps the previous checkpoint
+    pub fn save(&self, file_name: &Path) -> io::Result<()> {
+        let tmp_file_name = file_name.with_extension("json.tmp");
+        let file = File::create(&tmp_file_name)?;
+        serde_json::to_writer(&file, &self)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_file_name, file_name)
+    }
+}
+
+/// Items sent to and received by the stages of a worker's pipeline.
+/// A checkpoint is only consistent if no item is in flight, regardless of the order in which Hydroflow runs the stages.
+#[derive(Debug, Default)]
+pub struct InFlight {
+    // (sent, received) per stage
+    stages: HashMap<&'static str, (usize, usize)>,
+}
+
+impl InFlight {
+    pub fn send(&mut self, stage: &'static str, count: usize) {
+        self.stages.entry(stage).or_default().0 += count;
+    }
+
+    pub fn receive(&mut self, stage: &'static str, count: usize) {
+        self.stages.entry(stage).or_default().1 += count;
+    }
+
+    pub fn is_drained(&self) -> bool {
+        self.stages.values().all(|(sent, received)| sent == received)
+    }
+}
+
+/// Tracks the processed write choices of a worker and decides when to checkpoint
+pub struct Checkpointer {
+    file_name: PathBuf,
+    interval: Option<Duration>,
+    last_checkpoint: Instant,
+    chunk_size: u64,
+    // Rank of the last completely processed write choice per chunk
+    watermarks: HashMap<u64, u64>,
+    // Chunk and rank of the write choice currently being processed
+    current: Option<(u64, u64)>,
+}
+
+impl Checkpointer {
+    pub fn new(file_name: PathBuf, interval: Option<Duration>, chunk_size: u64, resume: Option<&Checkpoint>) -> Self {
+        let watermarks = resume.map(|c| c.chunks.clone()).unwrap_or_default();
+
+        Checkpointer {
+            file_name,
+            interval,
+            last_checkpoint: Instant::now(),
+            chunk_size,
+            watermarks,
+            current: None,
+        }
+    }
+
+    /// Mark the previous write choice as completely processed
+    fn complete(&mut self) {
+        if let Some((chunk, rank)) = self.current.take() {
+            self.watermarks.insert(chunk, rank);
+        }
+    }
+
+    /// Start processing the next write choice, the previous one is completely processed by now.
+    /// Returns false if the write choice was already processed before the checkpoint.
+    pub fn start(&mut self, chunk: u64, rank: u64) -> bool {
+        self.complete();
+
+        if self.watermarks.get(&chunk).map_or(false, |w| rank <= *w) {
+            return false;
+        }
+
+        self.current = Some((chunk, rank));
+        true
+    }
+
+    /// Mark the chunk as completely processed, including trailing write choices skipped by constraints
+    pub fn finish_chunk(&mut self, chunk: &Chunk) {
+        self.complete();
+        if chunk.start < chunk.end {
+            self.watermarks.insert(chunk.id, chunk.end - 1);
+        }
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.interval.map_or(false, |interval| self.last_checkpoint.elapsed() >= interval)
+    }
+
+    /// Save the state after the previous write choice, call before `start` of the next write choice
+    pub fn save(&mut self, mut checkpoint: Checkpoint) -> io::Result<()> {
+        self.complete();
+
+        checkpoint.chunks = self.watermarks.clone();
+        checkpoint.chunk_size = self.chunk_size;
+        checkpoint.save(&self.file_name)?;
+        self.last_checkpoint = Instant::now();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io, path::Path, time::Duration};
+
+    use skypie_proto_messages::{ProtobufFileReader, ProtobufFileSink};
+
+    use crate::chunks::Chunk;
+
+    use super::{Checkpoint, Checkpointer, DecisionIds, InFlight};
+
+    /// Process the write choices of chunk 0 with a checkpoint before each write choice, writing one message per write choice.
+    /// Crashes while processing the write choice `crash_at`, after writing its message but before the next checkpoint.
+    fn run(checkpoint_file_name: &Path, output_file_name: &Path, no_write_choices: u64, crash_at: Option<u64>, resume: Option<Checkpoint>) {
+        let mut sink = match &resume {
+            Some(resume) => ProtobufFileSink::open_at(output_file_name, resume.candidates_offset, 1024, 1024).unwrap(),
+            None => ProtobufFileSink::new(output_file_name, 1024, 1024).unwrap(),
+        };
+        let mut checkpointer = Checkpointer::new(checkpoint_file_name.to_path_buf(), Some(Duration::ZERO), 10, resume.as_ref());
+
+        for rank in 0..no_write_choices {
+            assert!(checkpointer.is_due());
+            checkpointer.save(Checkpoint { candidates_offset: sink.offset().unwrap(), ..Default::default() }).unwrap();
+
+            if !checkpointer.start(0, rank) {
+                continue;
+            }
+            sink.write(skypie_proto_messages::Decision { timestamp: Some(rank), ..Default::default() }).unwrap();
+
+            if crash_at == Some(rank) {
+                // Flush the message written after the checkpoint, as if the buffer was full
+                sink.flush().unwrap();
+                return;
+            }
+        }
+        checkpointer.finish_chunk(&Chunk { id: 0, start: 0, end: no_write_choices });
+        checkpointer.save(Checkpoint { candidates_offset: sink.offset().unwrap(), ..Default::default() }).unwrap();
+    }
+
+    #[test]
+    fn test_interrupt_and_resume() {
+        let dir = std::env::temp_dir().join(format!("checkpoint_resume_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let checkpoint_file_name = dir.join("checkpoint_0.json");
+        let output_file_name = dir.join("candidates.proto.bin");
+
+        run(&checkpoint_file_name, &output_file_name, 5, Some(2), None);
+        let checkpoint = Checkpoint::load(&checkpoint_file_name).unwrap().unwrap();
+        assert_eq!(checkpoint.chunks.get(&0), Some(&1));
+
+        run(&checkpoint_file_name, &output_file_name, 5, None, Some(checkpoint));
+
+        // Each write choice is output exactly once, the message written after the checkpoint is dropped
+        let ranks = ProtobufFileReader::new(&output_file_name).unwrap()
+            .into_iter_all::<skypie_proto_messages::Decision>()
+            .map(|d| d.unwrap().timestamp.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ranks, vec![0, 1, 2, 3, 4]);
+        assert_eq!(Checkpoint::load(&checkpoint_file_name).unwrap().unwrap().chunks.get(&0), Some(&4));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_checkpoint() {
+        let file_name = std::env::temp_dir().join(format!("checkpoint_invalid_test_{}.json", std::process::id()));
+        assert!(Checkpoint::load(&file_name).unwrap().is_none());
+
+        std::fs::write(&file_name, "{\"chunks\": ").unwrap();
+        assert_eq!(Checkpoint::load(&file_name).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(file_name).unwrap();
+
+        // Ids that do not match the loaded object stores and regions
+        let checkpoint = Checkpoint { batch: vec![DecisionIds { write_choice: vec![3], read_choice: vec![] }], ..Default::default() };
+        assert_eq!(checkpoint.restore_batch(&[], &HashMap::new()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let decision = DecisionIds { write_choice: vec![], read_choice: vec![(0, 3)] };
+        assert_eq!(decision.to_decision(&[], &HashMap::new()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_in_flight() {
+        let mut in_flight = InFlight::default();
+        assert!(in_flight.is_drained());
+        in_flight.send("batches", 2);
+        in_flight.receive("batches", 1);
+        assert!(!in_flight.is_drained());
+        in_flight.receive("batches", 1);
+        assert!(in_flight.is_drained());
+    }
+
+    #[test]
+    fn test_checkpointer_resume() {
+        let file_name = std::env::temp_dir().join(format!("checkpoint_test_{}.json", std::process::id()));
+
+        let mut checkpointer = Checkpointer::new(file_name.clone(), None, 3, None);
+        assert!(checkpointer.start(0, 0));
+        assert!(checkpointer.start(0, 1));
+        checkpointer.finish_chunk(&Chunk { id: 0, start: 0, end: 3 });
+        assert!(checkpointer.start(1, 3));
+        assert!(checkpointer.start(1, 4));
+        // Write choice 4 is completely processed before the checkpoint
+        checkpointer.save(Checkpoint { candidates_offset: 42, ..Default::default() }).unwrap();
+
+        let checkpoint = Checkpoint::load(&file_name).unwrap().unwrap();
+        assert_eq!(checkpoint.chunks.get(&0), Some(&2));
+        assert_eq!(checkpoint.chunks.get(&1), Some(&4));
+        assert_eq!(checkpoint.candidates_offset, 42);
+        assert_eq!(checkpoint.chunk_size, 3);
+
+        // Chunk ids and ranks refer to other write choices with another chunk size
+        checkpoint.check_chunk_size(3).unwrap();
+        assert_eq!(checkpoint.check_chunk_size(4).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        let mut resumed = Checkpointer::new(file_name.clone(), None, 3, Some(&checkpoint));
+        assert!(!resumed.start(1, 3));
+        assert!(!resumed.start(1, 4));
+        assert!(resumed.start(1, 5));
+        assert!(resumed.start(2, 6));
+
+        std::fs::remove_file(file_name).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+use std::{collections::{HashMap, VecDeque}, io, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{combination_ranking::CombinationRanking, object_store::ObjectStore, write_choice_constraints::WriteChoiceConstraints};
+
+/// Contiguous rank range of write choices that a worker enumerates and processes as a unit of work.
+/// The id identifies the chunk across reruns with the same chunk size, also when resuming a partially processed chunk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub id: u64,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Request of a worker for the next chunk, acknowledging the previous chunk if any
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkRequest {
+    pub worker_id: u32,
+    pub done: Option<u64>,
+}
+
+/// Split the write choice space into chunks, skipping what is already processed according to the watermarks of the chunks,
+/// i.e., the rank of the last processed write choice per chunk
+pub fn split_chunks(total: u64, chunk_size: u64, watermarks: &HashMap<u64, u64>) -> Vec<Chunk> {
+    assert!(chunk_size > 0, "Chunk size must be positive");
+
+    (0..(total + chunk_size - 1) / chunk_size)
+        .filter_map(|id| {
+            let end = total.min((id + 1) * chunk_size);
+            let start = match watermarks.get(&id) {
+                Some(watermark) => end.min(watermark + 1),
+                None => id * chunk_size,
+            };
+            if start < end { Some(Chunk { id, start, end }) } else { None }
+        })
+        .collect()
+}
+
+/// Number of write choices processed before resuming according to the watermarks, see `split_chunks`
+pub fn resumed_write_choices(total: u64, chunk_size: u64, watermarks: &HashMap<u64, u64>) -> u64 {
+    total - split_chunks(total, chunk_size, watermarks).iter().map(|c| c.end - c.start).sum::<u64>()
+}
+
+/// Hands out chunks to workers on request and tracks the outstanding chunks.
+/// Once all chunks are acknowledged, every worker receives the tombstone, i.e., None, exactly once.
+pub struct ChunkScheduler {
+    pending: VecDeque<Chunk>,
+    // Chunk id -> worker id and chunk
+    outstanding: HashMap<u64, (u32, Chunk)>,
+    no_workers: u32,
+    finished: bool,
+    // Write choices of acknowledged chunks
+    done: u64,
+}
+
+impl ChunkScheduler {
+    pub fn new(chunks: Vec<Chunk>, no_workers: u32) -> Self {
+        ChunkScheduler { pending: chunks.into(), outstanding: HashMap::new(), no_workers, finished: false, done: 0 }
+    }
+
+    /// Messages to send in response to the request as (worker id, chunk or tombstone).
+    /// Fails if the acknowledged chunk is not outstanding at the requesting worker, the chunk stays outstanding then.
+    pub fn on_request(&mut self, request: ChunkRequest) -> io::Result<Vec<(u32, Option<Chunk>)>> {
+        if let Some(done) = request.done {
+            match self.outstanding.get(&done) {
+                Some((worker_id, _)) if *worker_id == request.worker_id => {
+                    let (_, chunk) = self.outstanding.remove(&done).unwrap();
+                    self.done += chunk.end - chunk.start;
+                }
+                Some((worker_id, _)) => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("Chunk {} acknowledged by worker {}, but assigned to worker {}", done, request.worker_id, worker_id))),
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("Chunk {} acknowledged by worker {}, but not outstanding", done, request.worker_id))),
+            }
+        }
+
+        if let Some(chunk) = self.pending.pop_front() {
+            self.outstanding.insert(chunk.id, (request.worker_id, chunk.clone()));
+            return Ok(vec![(request.worker_id, Some(chunk))]);
+        }
+
+        // Idle workers wait for the tombstone until all outstanding chunks are acknowledged
+        if self.outstanding.is_empty() && !self.finished {
+            self.finished = true;
+            return Ok((0..self.no_workers).map(|worker_id| (worker_id, None)).collect());
+        }
+
+        Ok(vec![])
+    }
+
+    pub fn no_outstanding(&self) -> usize {
+        self.outstanding.len()
+    }
+
+    /// Number of write choices in acknowledged chunks
+    pub fn no_done(&self) -> u64 {
+        self.done
+    }
+
+    /// Number of write choices in chunks not acknowledged yet
+    pub fn no_remaining(&self) -> u64 {
+        self.pending.iter().chain(self.outstanding.values().map(|(_, c)| c)).map(|c| c.end - c.start).sum()
+    }
+}
+
+/// Unit of work of a worker derived from the received chunks
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkItem {
+    WriteChoice { chunk: u64, rank: u64, ids: Vec<u16> },
+    ChunkDone(Chunk),
+    Tombstone,
+}
+
+/// Enumerate the write choices of a chunk that satisfy the constraints, followed by the completion of the chunk.
+/// Object stores are looked up by their id.
+pub fn chunk_work_items(chunk: Option<Chunk>, ranking: &Arc<CombinationRanking>, constraints: &Arc<WriteChoiceConstraints>, object_store_id_map: &Arc<HashMap<u16, ObjectStore>>) -> Box<dyn Iterator<Item = WorkItem>> {
+    match chunk {
+        Some(chunk) => {
+            let id = chunk.id;
+            let start = chunk.start;
+            let (constraints, object_store_id_map) = (constraints.clone(), object_store_id_map.clone());
+            let write_choices = ranking.iter_range_shared(chunk.start..chunk.end)
+                .enumerate()
+                .filter(move |(_, ids)| constraints.is_satisfied_by_ids(&object_store_id_map, ids))
+                .map(move |(i, ids)| WorkItem::WriteChoice { chunk: id, rank: start + i as u64, ids });
+            Box::new(write_choices.chain(std::iter::once(WorkItem::ChunkDone(chunk))))
+        }
+        None => Box::new(std::iter::once(WorkItem::Tombstone)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io};
+
+    use super::{resumed_write_choices, split_chunks, Chunk, ChunkRequest, ChunkScheduler};
+
+    #[test]
+    fn test_chunk_scheduler() {
+        let chunks = split_chunks(25, 10, &HashMap::from([(1, 19), (2, 21)]));
+        assert_eq!(resumed_write_choices(25, 10, &HashMap::from([(1, 19), (2, 21)])), 12);
+        // Chunk 1 is completely processed, chunk 2 continues after its watermark
+        assert_eq!(chunks, vec![Chunk { id: 0, start: 0, end: 10 }, Chunk { id: 2, start: 22, end: 25 }]);
+
+        let mut scheduler = ChunkScheduler::new(chunks, 2);
+        assert_eq!(scheduler.on_request(ChunkRequest { worker_id: 0, done: None }).unwrap(), vec![(0, Some(Chunk { id: 0, start: 0, end: 10 }))]);
+        assert_eq!(scheduler.on_request(ChunkRequest { worker_id: 1, done: None }).unwrap(), vec![(1, Some(Chunk { id: 2, start: 22, end: 25 }))]);
+        // Worker 1 waits for the outstanding chunk of worker 0
+        assert_eq!(scheduler.on_request(ChunkRequest { worker_id: 1, done: Some(2) }).unwrap(), vec![]);
+        assert_eq!(scheduler.no_outstanding(), 1);
+        assert_eq!((scheduler.no_done(), scheduler.no_remaining()), (3, 10));
+        assert_eq!(scheduler.on_request(ChunkRequest { worker_id: 0, done: Some(0) }).unwrap(), vec![(0, None), (1, None)]);
+        assert_eq!(scheduler.no_done(), 13);
+    }
+
+    #[test]
+    fn test_wrong_acknowledgement() {
+        let mut scheduler = ChunkScheduler::new(split_chunks(20, 10, &HashMap::new()), 2);
+        scheduler.on_request(ChunkRequest { worker_id: 0, done: None }).unwrap();
+        scheduler.on_request(ChunkRequest { worker_id: 1, done: None }).unwrap();
+
+        // Chunk 0 is assigned to worker 0, chunk 2 does not exist
+        for request in [ChunkRequest { worker_id: 1, done: Some(0) }, ChunkRequest { worker_id: 1, done: Some(2) }] {
+            assert_eq!(scheduler.on_request(request).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+        assert_eq!((scheduler.no_outstanding(), scheduler.no_done()), (2, 0));
+
+        // A chunk is acknowledged only once
+        scheduler.on_request(ChunkRequest { worker_id: 0, done: Some(0) }).unwrap();
+        assert!(scheduler.on_request(ChunkRequest { worker_id: 0, done: Some(0) }).is_err());
+        assert_eq!(scheduler.no_done(), 10);
+    }
+}
@@ -0,0 +1,217 @@
+use std::{ops::{Deref, Range}, sync::Arc};
+
+/// Ranking and unranking of the write choice space, i.e., the combinations of `k_min..=k_max` elements of a pool.
+/// Ranks follow the order of `itertools::combinations` for increasing `k`, so that a rank range identifies exactly the same
+/// write choices across reruns, e.g., the shard of a worker.
+#[derive(Debug, Clone)]
+pub struct CombinationRanking {
+    pool: Vec<u16>,
+    k_min: usize,
+    k_max: usize,
+    // binomials[n][k] = n choose k
+    binomials: Vec<Vec<u64>>,
+}
+
+impl CombinationRanking {
+    pub fn new(pool: Vec<u16>, k_min: usize, k_max: usize) -> Self {
+        assert!(k_min > 0 && k_min <= k_max, "Invalid replication factor range {}..={}", k_min, k_max);
+
+        let n = pool.len();
+        let mut binomials = vec![vec![0u64; k_max + 1]; n + 1];
+        for i in 0..=n {
+            binomials[i][0] = 1;
+            for j in 1..=k_max.min(i) {
+                binomials[i][j] = binomials[i - 1][j - 1].checked_add(if j < i { binomials[i - 1][j] } else { 0 }).expect("Number of write choices overflows u64");
+            }
+        }
+
+        Self { pool, k_min, k_max, binomials }
+    }
+
+    fn binomial(&self, n: usize, k: usize) -> u64 {
+        if k > n { 0 } else { self.binomials[n][k] }
+    }
+
+    /// Number of combinations of size k
+    fn total_k(&self, k: usize) -> u64 {
+        self.binomial(self.pool.len(), k)
+    }
+
+    /// Total number of write choices
+    pub fn total(&self) -> u64 {
+        (self.k_min..=self.k_max).map(|k| self.total_k(k)).sum()
+    }
+
+    /// Rank of a combination given as positions in the pool in increasing order
+    fn rank_positions(&self, positions: &[usize]) -> u64 {
+        let n = self.pool.len();
+        let k = positions.len();
+        assert!(k >= self.k_min && k <= self.k_max, "Combination of size {} outside of range {}..={}", k, self.k_min, self.k_max);
+
+        // Offset of combinations with fewer elements
+        let mut rank: u64 = (self.k_min..k).map(|k| self.total_k(k)).sum();
+
+        let mut next = 0;
+        for (i, &p) in positions.iter().enumerate() {
+            // Skip all combinations with a smaller element at position i
+            for v in next..p {
+                rank += self.binomial(n - 1 - v, k - 1 - i);
+            }
+            next = p + 1;
+        }
+
+        rank
+    }
+
+    /// Rank of a write choice given as object store ids of the pool, in the order of the pool
+    pub fn rank(&self, combination: &[u16]) -> u64 {
+        let positions = combination.iter()
+            .map(|id| self.pool.iter().position(|x| x == id).expect(&format!("Object store {} not in pool", id)))
+            .collect::<Vec<_>>();
+        debug_assert!(positions.windows(2).all(|w| w[0] < w[1]), "Combination not in order of pool: {:?}", combination);
+
+        self.rank_positions(&positions)
+    }
+
+    fn unrank_positions(&self, rank: u64) -> Vec<usize> {
+        assert!(rank < self.total(), "Rank {} out of range {}", rank, self.total());
+        let n = self.pool.len();
+
+        // Find size of combination
+        let mut rank = rank;
+        let mut k = self.k_min;
+        while rank >= self.total_k(k) {
+            rank -= self.total_k(k);
+            k += 1;
+        }
+
+        let mut positions = Vec::with_capacity(k);
+        let mut v = 0;
+        for i in 0..k {
+            loop {
+                let count = self.binomial(n - 1 - v, k - 1 - i);
+                if rank < count {
+                    break;
+                }
+                rank -= count;
+                v += 1;
+            }
+            positions.push(v);
+            v += 1;
+        }
+
+        positions
+    }
+
+    /// Write choice of the given rank
+    pub fn unrank(&self, rank: u64) -> Vec<u16> {
+        self.unrank_positions(rank).into_iter().map(|p| self.pool[p]).collect()
+    }
+
+    /// Contiguous rank range of a shard, shards are balanced up to one write choice
+    pub fn shard_range(&self, shard: usize, num_shards: usize) -> Range<u64> {
+        assert!(shard < num_shards, "Shard {} out of range {}", shard, num_shards);
+        let total = self.total() as u128;
+        let start = total * shard as u128 / num_shards as u128;
+        let end = total * (shard as u128 + 1) / num_shards as u128;
+        (start as u64)..(end as u64)
+    }
+
+    /// Iterate over the write choices of a rank range
+    pub fn iter_range(&self, range: Range<u64>) -> CombinationRangeIter<&Self> {
+        CombinationRangeIter::new(self, range)
+    }
+
+    /// Iterate over the write choices of a rank range of a shared ranking, e.g., for iterators that outlive the caller
+    pub fn iter_range_shared(self: &Arc<Self>, range: Range<u64>) -> CombinationRangeIter<Arc<Self>> {
+        CombinationRangeIter::new(self.clone(), range)
+    }
+}
+
+/// Iterator over the write choices of a rank range, holding the ranking by reference or shared
+pub struct CombinationRangeIter<R: Deref<Target = CombinationRanking>> {
+    ranking: R,
+    positions: Vec<usize>,
+    remaining: u64,
+}
+
+impl<R: Deref<Target = CombinationRanking>> CombinationRangeIter<R> {
+    fn new(ranking: R, range: Range<u64>) -> Self {
+        let positions = if range.start < range.end { ranking.unrank_positions(range.start) } else { vec![] };
+        Self { ranking, positions, remaining: range.end.saturating_sub(range.start) }
+    }
+
+    /// Advance positions to the next combination, continuing with the first combination of the next size
+    fn advance(&mut self) {
+        let n = self.ranking.pool.len();
+        let k = self.positions.len();
+
+        let mut i = k;
+        while i > 0 {
+            i -= 1;
+            if self.positions[i] < n - k + i {
+                self.positions[i] += 1;
+                for j in i + 1..k {
+                    self.positions[j] = self.positions[j - 1] + 1;
+                }
+                return;
+            }
+        }
+
+        self.positions = (0..k + 1).collect();
+    }
+}
+
+impl<R: Deref<Target = CombinationRanking>> Iterator for CombinationRangeIter<R> {
+    type Item = Vec<u16>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let item = self.positions.iter().map(|&p| self.ranking.pool[p]).collect();
+        self.remaining -= 1;
+        if self.remaining > 0 {
+            self.advance();
+        }
+
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::CombinationRanking;
+
+    #[test]
+    fn test_combination_ranking() {
+        let pool = vec![3, 5, 7, 8, 10, 11];
+        let (k_min, k_max) = (2, 4);
+        let ranking = CombinationRanking::new(pool.clone(), k_min, k_max);
+
+        let expected = (k_min..=k_max).flat_map(|k| pool.clone().into_iter().combinations(k)).collect_vec();
+        assert_eq!(ranking.total() as usize, expected.len());
+
+        for (rank, combination) in expected.iter().enumerate() {
+            assert_eq!(ranking.rank(combination), rank as u64);
+            assert_eq!(&ranking.unrank(rank as u64), combination);
+        }
+
+        assert_eq!(ranking.iter_range(0..ranking.total()).collect_vec(), expected);
+        let shared = std::sync::Arc::new(ranking.clone());
+        assert_eq!(shared.iter_range_shared(2..5).collect_vec(), expected[2..5]);
+
+        // Shards partition the space in order
+        for num_shards in [1, 4, 7, 100] {
+            let shards = (0..num_shards).flat_map(|s| ranking.iter_range(ranking.shard_range(s, num_shards))).collect_vec();
+            assert_eq!(shards, expected);
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use crate::{object_store::ObjectStore, ApplicationRegion};
-pub trait CompatibilityChecker {
+use crate::{object_store::ObjectStore, ApplicationRegion, WriteChoice};
+pub trait CompatibilityChecker: Send + Sync {
     fn is_compatible(&self, object_store: &ObjectStore, app: &ApplicationRegion) -> bool;
 }
 
@@ -10,4 +10,18 @@ impl CompatibilityChecker for DefaultCompatibilityChecker {
     fn is_compatible(&self, _object_store: &ObjectStore, _app: &ApplicationRegion) -> bool {
         true
     }
-}
\ No newline at end of file
+}
+
+/// Checks whether an application region can write to the replicas of a write choice
+pub trait WriteCompatibilityChecker: Send + Sync {
+    fn is_write_compatible(&self, write_choice: &WriteChoice, app: &ApplicationRegion) -> bool;
+}
+
+pub struct DefaultWriteCompatibilityChecker {
+}
+
+impl WriteCompatibilityChecker for DefaultWriteCompatibilityChecker {
+    fn is_write_compatible(&self, _write_choice: &WriteChoice, _app: &ApplicationRegion) -> bool {
+        true
+    }
+}
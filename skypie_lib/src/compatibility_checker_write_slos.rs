@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use crate::{Region, ApplicationRegion, WriteChoice, compatibility_checker::WriteCompatibilityChecker};
+
+/// Write latency SLO: A write from an application region completes once a quorum of replicas acknowledged it,
+/// i.e., the write latency is the latency to the quorum-th closest replica.
+pub struct CompatibilityCheckerWriteSLOs {
+    network_latency: HashMap<Region, HashMap<Region, f64>>,
+    write_slo: f64,
+    // Number of replicas that have to acknowledge a write, None for all replicas
+    quorum: Option<usize>,
+}
+
+impl CompatibilityCheckerWriteSLOs {
+    pub(crate) fn new(network_latency: HashMap<Region, HashMap<Region, f64>>, write_slo: f64, quorum: Option<usize>) -> Self {
+        assert!(quorum != Some(0), "Write quorum must be at least 1");
+        Self {
+            network_latency,
+            write_slo,
+            quorum
+        }
+    }
+
+    /// Write latency of the app region, infinite if the latency data misses a pair of the app region and a replica, so that the write choice is incompatible
+    pub fn write_latency(&self, write_choice: &WriteChoice, app: &ApplicationRegion) -> f64 {
+        let latency = self.network_latency.get(&app.region);
+
+        let latencies = write_choice.object_stores.iter()
+            .map(|o| latency.and_then(|l| l.get(&o.region)).copied().unwrap_or(f64::INFINITY))
+            .collect::<Vec<f64>>();
+
+        // A quorum larger than the write choice requires all replicas
+        let quorum = self.quorum.unwrap_or(latencies.len()).min(latencies.len());
+        if quorum == 0 {
+            return 0.0;
+        }
+
+        let mut latencies = latencies;
+        latencies.sort_by(f64::total_cmp);
+        latencies[quorum - 1]
+    }
+}
+
+impl WriteCompatibilityChecker for CompatibilityCheckerWriteSLOs {
+    fn is_write_compatible(&self, write_choice: &WriteChoice, app: &ApplicationRegion) -> bool {
+        self.write_latency(write_choice, app) <= self.write_slo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{object_store::ObjectStoreStruct, ApplicationRegion, Region, WriteChoice, compatibility_checker::WriteCompatibilityChecker};
+
+    use super::CompatibilityCheckerWriteSLOs;
+
+    #[test]
+    fn test_write_quorum() {
+        let regions = (0..3).map(|i| Region{id: i, name: format!("aws-region-{}", i)}).collect::<Vec<_>>();
+        let latency = [[1.0, 20.0, 50.0], [20.0, 1.0, 30.0], [50.0, 30.0, 1.0]];
+        let network_latency = regions.iter().map(|src| {
+            let map = regions.iter().map(|dest| (dest.clone(), latency[src.id as usize][dest.id as usize])).collect::<HashMap<_, _>>();
+            (src.clone(), map)
+        }).collect::<HashMap<_, _>>();
+
+        let object_stores = regions.iter().map(|r| {
+            let mut o = ObjectStoreStruct::new(r.id);
+            o.region = r.clone();
+            o
+        }).collect::<Vec<_>>();
+        let write_choice = WriteChoice{object_stores};
+        let app = ApplicationRegion{region: regions[0].clone(), ..Default::default()};
+
+        let all = CompatibilityCheckerWriteSLOs::new(network_latency.clone(), 25.0, None);
+        assert_eq!(all.write_latency(&write_choice, &app), 50.0);
+        assert!(!all.is_write_compatible(&write_choice, &app));
+
+        let quorum = CompatibilityCheckerWriteSLOs::new(network_latency.clone(), 25.0, Some(2));
+        assert_eq!(quorum.write_latency(&write_choice, &app), 20.0);
+        assert!(quorum.is_write_compatible(&write_choice, &app));
+
+        let oversized_quorum = CompatibilityCheckerWriteSLOs::new(network_latency.clone(), 50.0, Some(5));
+        assert!(oversized_quorum.is_write_compatible(&write_choice, &app));
+
+        // Pairs without latency data are incompatible instead of panicking, unless the quorum is reached without them
+        let mut partial_latency = network_latency;
+        partial_latency.get_mut(&regions[0]).unwrap().remove(&regions[2]);
+        let partial = CompatibilityCheckerWriteSLOs::new(partial_latency.clone(), 1000.0, None);
+        assert_eq!(partial.write_latency(&write_choice, &app), f64::INFINITY);
+        assert!(!partial.is_write_compatible(&write_choice, &app));
+        assert!(CompatibilityCheckerWriteSLOs::new(partial_latency, 25.0, Some(2)).is_write_compatible(&write_choice, &app));
+        let unknown_app = ApplicationRegion{region: Region{id: 3, name: "aws-region-3".to_string()}, ..Default::default()};
+        assert!(!partial.is_write_compatible(&write_choice, &unknown_app));
+    }
+}
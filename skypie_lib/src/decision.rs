@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use itertools::Itertools;
@@ -7,6 +8,7 @@ use pyo3::{Py, Python};
 
 use crate::read_choice::ReadChoice;
 use crate::write_choice::WriteChoice;
+use crate::identifier::Identifier;
 use crate::{ApplicationRegion, Tombstone};
 
 use super::object_store::ObjectStore;
@@ -42,6 +44,13 @@ impl Decision {
 
         return decision.cost_iter().len();
     }
+
+    /// Whether every replica of the write choice is read by at least one application region.
+    /// Otherwise, the decision pays storage and put costs for a replica that is never read.
+    pub fn reads_all_replicas(&self) -> bool {
+        let read = self.read_choice.iter().map(|(_, o)| o.id).collect::<HashSet<u16>>();
+        self.write_choice.object_stores.iter().all(|o| read.contains(&o.id))
+    }
 }
 
 impl Tombstone for Decision {
@@ -231,17 +240,32 @@ impl From<Decision> for OutputDecision {
 }
 
 impl From<Decision> for skypie_proto_messages::Decision {
+    /// Decision with the names and the ids of object stores, readable without the header of an oracle container
     fn from(decision: Decision) -> Self {
-        // Get timestamp of current time of day
-        
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-        let now_secs = now.as_secs();
-        let now_subsec_nanos = now.subsec_nanos() as u64;
+        proto_decision(decision, true)
+    }
+}
+
+impl Decision {
+    /// Compact encoding by ids only, for containers whose header holds the dictionaries of the names
+    pub fn into_compact_proto(self) -> skypie_proto_messages::Decision {
+        proto_decision(self, false)
+    }
+}
+
+fn proto_decision(decision: Decision, with_names: bool) -> skypie_proto_messages::Decision {
+    // Get timestamp of current time of day
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let now_secs = now.as_secs();
+    let now_subsec_nanos = now.subsec_nanos() as u64;
 
-        let cost_wl_halfplane: Vec::<f64> = decision.get_halfplane_ineq();
-        let replication_scheme = Some(decision.into());
-        skypie_proto_messages::Decision{ replication_scheme, cost_wl_halfplane, timestamp: Some(now_secs), timestamp_subsec_nanos: Some(now_subsec_nanos)}
+    let cost_wl_halfplane: Vec::<f64> = decision.get_halfplane_ineq();
+    let mut replication_scheme = skypie_proto_messages::Scheme::from(decision);
+    if !with_names {
+        replication_scheme.object_stores.clear();
+        replication_scheme.app_assignments.clear();
     }
+    skypie_proto_messages::Decision{ replication_scheme: Some(replication_scheme), cost_wl_halfplane, timestamp: Some(now_secs), timestamp_subsec_nanos: Some(now_subsec_nanos)}
 }
 
 impl From<Decision> for OutputScheme {
@@ -253,13 +277,17 @@ impl From<Decision> for OutputScheme {
 }
 
 impl From<Decision> for skypie_proto_messages::Scheme {
-    
+    /// Names of the object stores and app assignments, together with the ids of the object stores,
+    /// which are the indices into the dictionaries of the oracle header
     fn from(decision: Decision) -> Self {
-        use skypie_proto_messages::Assignment;
-
-        let object_stores = decision.write_choice.object_stores.into_iter().map(|o| format!("{}-{}", o.region.name, o.name)).collect_vec();
-        let app_assignments = decision.read_choice.iter().map(|(region, object_store)| Assignment{app: region.region.name.clone(), object_store: format!("{}-{}", object_store.region.name, object_store.name)}).collect_vec();
-        Self { object_stores, app_assignments}
+        let object_store_ids = decision.write_choice.object_stores.iter().map(|o| o.get_id() as u32).collect_vec();
+        // Read choices are ordered by the id of the app region
+        let app_object_store_ids = decision.read_choice.iter().map(|(_region, object_store)| object_store.get_id() as u32).collect_vec();
+        let OutputScheme { object_stores, app_assignments } = decision.into();
+        let app_assignments = app_assignments.into_iter()
+            .map(|a| skypie_proto_messages::Assignment { app: a.app, object_store: a.object_store })
+            .collect_vec();
+        Self { object_stores, app_assignments, object_store_ids, app_object_store_ids }
     }
 }
 
@@ -507,6 +535,42 @@ mod tests {
     //use crate::{Region, ApplicationRegion, Decision, WriteChoice};
     //use crate::network_record::NetworkCostMap;
 
+    #[test]
+    fn test_proto_decision() {
+        use crate::object_store::{Cost, ObjectStore, ObjectStoreStruct};
+        use crate::{ApplicationRegion, Decision, Region, WriteChoice};
+        use crate::network_record::NetworkCostMap;
+        use crate::read_choice::ReadChoice;
+
+        let regions = [Region { id: 0, name: "aws-eu-west-1".to_string() }, Region { id: 1, name: "gcp-europe-west1".to_string() }];
+        let network = || NetworkCostMap::from_iter(regions.iter().map(|r| (r.clone(), 0.0)));
+        let object_stores = regions.iter().enumerate()
+            .map(|(i, r)| {
+                let mut cost = Cost::new(1.0, "storage");
+                cost.add_egress_costs(network());
+                cost.add_ingress_costs(network());
+                ObjectStore::new(ObjectStoreStruct { id: i as u16, cost, region: r.clone(), name: "Standard".to_string() })
+            })
+            .collect::<Vec<_>>();
+        let app_region = ApplicationRegion { region: regions[0].clone(), egress_cost: network(), ingress_cost: network() };
+        let decision = Decision {
+            write_choice: WriteChoice { object_stores: object_stores.clone() },
+            read_choice: ReadChoice::from_iter([(app_region, object_stores[1].clone())]),
+        };
+
+        // Readable without the dictionaries of an oracle header
+        let named: skypie_proto_messages::Decision = decision.clone().into();
+        let scheme = named.replication_scheme.unwrap();
+        assert_eq!(scheme.object_stores, ["aws-eu-west-1-Standard", "gcp-europe-west1-Standard"]);
+        assert_eq!(scheme.app_assignments, [skypie_proto_messages::Assignment { app: "aws-eu-west-1".to_string(), object_store: "gcp-europe-west1-Standard".to_string() }]);
+        assert_eq!(scheme.object_store_ids, [0, 1]);
+        assert_eq!(scheme.app_object_store_ids, [1]);
+
+        let compact = decision.into_compact_proto().replication_scheme.unwrap();
+        assert!(compact.object_stores.is_empty() && compact.app_assignments.is_empty());
+        assert_eq!((compact.object_store_ids, compact.app_object_store_ids), (scheme.object_store_ids, scheme.app_object_store_ids));
+    }
+
     #[test]
     fn test_decision_cost() {
         assert!(false);
@@ -766,4 +830,27 @@ def fn(ineqs):
             bench(1);
         });
     }
+
+    #[test]
+    fn test_reads_all_replicas() {
+        use crate::object_store::ObjectStoreStruct;
+        use crate::read_choice::ReadChoice;
+        use crate::{ApplicationRegion, Decision, Region, WriteChoice};
+
+        let object_stores = (0..2).map(|i| ObjectStoreStruct::new(i)).collect::<Vec<_>>();
+        let write_choice = WriteChoice{object_stores: object_stores.clone()};
+        let apps = (0..2).map(|i| ApplicationRegion{region: Region{id: i, name: i.to_string()}, ..Default::default()}).collect::<Vec<_>>();
+
+        let both = Decision{
+            write_choice: write_choice.clone(),
+            read_choice: ReadChoice::from_iter(vec![(apps[0].clone(), object_stores[0].clone()), (apps[1].clone(), object_stores[1].clone())]),
+        };
+        assert!(both.reads_all_replicas());
+
+        let one = Decision{
+            write_choice,
+            read_choice: ReadChoice::from_iter(vec![(apps[0].clone(), object_stores[0].clone()), (apps[1].clone(), object_stores[0].clone())]),
+        };
+        assert!(!one.reads_all_replicas());
+    }
 }
@@ -0,0 +1,160 @@
+//! Pinned ids of regions and object stores, so that the same region has the same id in all oracles.
+//! The catalogue is a CSV file with the columns `kind`, `name` and `id`, where `kind` is "region" or "object_store",
+//! region names are resolved by the region catalogue and object store names are their names in oracles, e.g., "aws-eu-west-1-s3-General Purpose".
+//! The loader uses the catalogue ids as the ids of the regions and object stores, so the ids of an oracle can have gaps,
+//! e.g., if the selectors or the latency data exclude regions of the catalogue.
+
+use std::{collections::{HashMap, HashSet}, fs::File, io, path::{Path, PathBuf}};
+
+use serde::Deserialize;
+
+use crate::region_catalogue::REGION_CATALOGUE;
+
+#[derive(Deserialize)]
+struct CatalogueIdRaw {
+    kind: String,
+    name: String,
+    id: u32,
+}
+
+/// Ids from `u16::MAX - 1` on are reserved for unset regions and tombstones
+const MAX_ID: u32 = u16::MAX as u32 - 2;
+
+/// Pinned ids of regions and object stores by name
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IdCatalogue {
+    regions: HashMap<String, u16>,
+    object_stores: HashMap<String, u16>,
+}
+
+fn invalid_data<E: std::fmt::Display>(path: &Path, e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.to_string_lossy(), e))
+}
+
+impl IdCatalogue {
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let mut catalogue = IdCatalogue::default();
+        let mut ids: HashMap<&str, HashSet<u32>> = HashMap::new();
+        for record in csv::Reader::from_reader(File::open(path)?).deserialize::<CatalogueIdRaw>() {
+            let record = record.map_err(|e| invalid_data(path, e))?;
+            let (kind, ids_of_kind) = match record.kind.as_str() {
+                "region" => ("region", &mut catalogue.regions),
+                "object_store" => ("object_store", &mut catalogue.object_stores),
+                _ => return Err(invalid_data(path, format!("Unknown kind {}, expected \"region\" or \"object_store\"", record.kind))),
+            };
+            let name = if kind == "region" { REGION_CATALOGUE.canonical_name(&record.name) } else { record.name };
+            if record.id > MAX_ID {
+                return Err(invalid_data(path, format!("Id {} of {} {} is larger than {}", record.id, kind, name, MAX_ID)));
+            }
+            if !ids.entry(kind).or_default().insert(record.id) {
+                return Err(invalid_data(path, format!("Duplicate {} id {}", kind, record.id)));
+            }
+            if ids_of_kind.insert(name.clone(), record.id as u16).is_some() {
+                return Err(invalid_data(path, format!("Duplicate {} {}", kind, name)));
+            }
+        }
+        Ok(catalogue)
+    }
+
+    /// Catalogue of the file, if any
+    pub fn from_optional_file(path: &Option<PathBuf>) -> io::Result<Option<Self>> {
+        path.as_deref().map(IdCatalogue::from_file).transpose()
+    }
+
+    /// Pinned id of the region with the canonical name
+    pub fn region_id(&self, name: &str) -> Option<u16> {
+        self.regions.get(name).copied()
+    }
+
+    /// Pinned id of the object store with the name in oracles
+    pub fn object_store_id(&self, name: &str) -> Option<u16> {
+        self.object_stores.get(name).copied()
+    }
+}
+
+/// Catalogue ids of the names, fails with InvalidData listing the names that are not in the catalogue
+fn catalogue_ids<'a>(kind: &str, ids: &HashMap<String, u16>, names: impl IntoIterator<Item = &'a str>) -> io::Result<HashMap<String, u16>> {
+    let mut missing = Vec::new();
+    let mut catalogue_ids = HashMap::new();
+    for name in names {
+        match ids.get(name) {
+            Some(id) => { catalogue_ids.insert(name.to_string(), *id); },
+            None => missing.push(name),
+        }
+    }
+    if !missing.is_empty() {
+        missing.sort();
+        missing.dedup();
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}s not in the id catalogue: {}", kind, missing.join(", "))));
+    }
+    Ok(catalogue_ids)
+}
+
+/// Pinned ids of the regions, None without catalogue.
+/// Fails with InvalidData before any id is assigned if regions are not in the catalogue.
+pub(crate) fn pinned_region_ids<'a>(id_catalogue: Option<&IdCatalogue>, names: impl IntoIterator<Item = &'a str>) -> io::Result<Option<HashMap<String, u16>>> {
+    id_catalogue.map(|c| catalogue_ids("Region", &c.regions, names)).transpose()
+}
+
+/// Pinned ids of the object stores by their names in oracles, None without catalogue.
+/// Fails with InvalidData before any id is assigned if object stores are not in the catalogue.
+pub(crate) fn pinned_object_store_ids<'a>(id_catalogue: Option<&IdCatalogue>, names: impl IntoIterator<Item = &'a str>) -> io::Result<Option<HashMap<String, u16>>> {
+    id_catalogue.map(|c| catalogue_ids("Object store", &c.object_stores, names)).transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_file() {
+        let path = std::env::temp_dir().join(format!("skypie_id_catalogue_{}.csv", std::process::id()));
+        std::fs::write(&path, "kind,name,id\nregion,aws-us-east-1,1\nregion,gcp-us-east1,0\nobject_store,aws-us-east-1-General Purpose,0\n").unwrap();
+        let catalogue = IdCatalogue::from_file(&path).unwrap();
+
+        assert_eq!(catalogue.region_id("aws-us-east-1"), Some(1));
+        // Region names are resolved by the region catalogue
+        assert_eq!(catalogue.region_id("gcp-us-east1-b"), Some(0));
+        assert_eq!(catalogue.object_store_id("aws-us-east-1-General Purpose"), Some(0));
+        assert_eq!(catalogue.region_id("aws-eu-west-1"), None);
+        assert_eq!(pinned_region_ids(None, ["aws-eu-west-1"]).unwrap(), None);
+        assert_eq!(pinned_region_ids(Some(&catalogue), ["gcp-us-east1-b", "aws-us-east-1"]).unwrap(), Some(HashMap::from([("gcp-us-east1-b".to_string(), 0), ("aws-us-east-1".to_string(), 1)])));
+
+        std::fs::write(&path, "kind,name,id\nregion,aws-us-east-1,0\nregion,aws-eu-west-1,0\n").unwrap();
+        let duplicate = IdCatalogue::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(duplicate.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_invalid_id_catalogue() {
+        let path = std::env::temp_dir().join(format!("skypie_id_catalogue_invalid_{}.csv", std::process::id()));
+        let load = |content: &str| {
+            std::fs::write(&path, content).unwrap();
+            IdCatalogue::from_file(&path)
+        };
+
+        // Unknown kind, non-numeric id, reserved id, missing column and duplicate names
+        for content in [
+            "kind,name,id\nbucket,aws-us-east-1,0\n",
+            "kind,name,id\nregion,aws-us-east-1,first\n",
+            "kind,name,id\nregion,aws-us-east-1,65534\n",
+            "kind,name\nregion,aws-us-east-1\n",
+            "kind,name,id\nobject_store,aws-us-east-1-General Purpose,0\nobject_store,aws-us-east-1-General Purpose,1\n",
+        ] {
+            assert_eq!(load(content).unwrap_err().kind(), io::ErrorKind::InvalidData, "{}", content);
+        }
+
+        let catalogue = load("kind,name,id\nregion,aws-us-east-1,0\nobject_store,aws-us-east-1-General Purpose,0\n").unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(IdCatalogue::from_file(&path).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        // All missing names are reported at once before assigning ids
+        let missing = pinned_region_ids(Some(&catalogue), ["aws-us-east-1", "aws-eu-west-1", "aws-ap-south-1"]).unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::InvalidData);
+        assert!(missing.to_string().contains("aws-ap-south-1, aws-eu-west-1"), "{}", missing);
+        let missing = pinned_object_store_ids(Some(&catalogue), ["aws-us-east-1-General Purpose", "aws-us-east-1-Glacier"]).unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::InvalidData);
+        assert!(missing.to_string().contains("aws-us-east-1-Glacier"), "{}", missing);
+    }
+}
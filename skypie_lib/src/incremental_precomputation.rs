@@ -0,0 +1,243 @@
+//! Incremental precomputation after a price update: Only write choices with an object store whose prices changed are re-enumerated.
+//! The decisions of the other write choices have the same costs as before, so they are taken from the previous oracle.
+
+use std::{collections::{BTreeMap, HashSet}, io, path::{Path, PathBuf}};
+
+use itertools::Itertools;
+use skypie_proto_messages::{load_decisions, load_wrapper, Decision, IdMapping};
+use tracing::{info, warn};
+
+use crate::{
+    network_record::NetworkCostMap, object_store::ObjectStore, oracle_reduction::{app_regions_of, write_partition, OracleReduction},
+    stats::object_store_name, ApplicationRegion,
+};
+
+/// Object stores of the new price inputs whose decisions have the same costs as with the previous price inputs
+#[derive(Debug, Default)]
+pub struct PriceChanges {
+    /// Names of the object stores with unchanged prices, see `object_store_name`
+    pub unchanged: HashSet<String>,
+    /// Some price of a changed object store or region increased, or disappeared
+    pub increased: bool,
+}
+
+/// Accumulates whether prices changed or increased
+#[derive(Default)]
+struct Delta {
+    changed: bool,
+    increased: bool,
+}
+
+impl Delta {
+    fn price(&mut self, previous: f64, current: f64) {
+        self.changed |= previous != current;
+        self.increased |= current > previous;
+    }
+
+    /// Compares network costs by region name, since region ids depend on the selected regions
+    fn network_costs(&mut self, previous: &NetworkCostMap, current: &NetworkCostMap) {
+        let by_name = |costs: &NetworkCostMap| costs.iter().map(|(r, c)| (r.name.clone(), *c)).collect::<BTreeMap<_, _>>();
+        let (previous, current) = (by_name(previous), by_name(current));
+        for (region, cost) in &current {
+            match previous.get(region) {
+                Some(previous_cost) => self.price(*previous_cost, *cost),
+                None => self.changed = true,
+            }
+        }
+        if previous.keys().any(|r| !current.contains_key(r)) {
+            self.changed = true;
+            self.increased = true;
+        }
+    }
+}
+
+impl PriceChanges {
+    /// Compares the app regions and object stores of the previous and the new price inputs.
+    /// An object store is changed if any of its prices changed, or the network costs between its region and an app region changed.
+    /// Fails if the app regions differ, since the decisions would have different dimensions.
+    pub fn new(previous_app_regions: &[ApplicationRegion], previous_object_stores: &[ObjectStore], app_regions: &[ApplicationRegion], object_stores: &[ObjectStore]) -> io::Result<Self> {
+        let names = |app_regions: &[ApplicationRegion]| app_regions.iter().map(|r| r.region.name.clone()).collect_vec();
+        if names(previous_app_regions) != names(app_regions) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                "App regions differ between the previous ({:?}) and the new price inputs ({:?})", names(previous_app_regions), names(app_regions))));
+        }
+
+        // Regions whose network costs to some app region changed
+        let mut increased = false;
+        let mut changed_regions = HashSet::new();
+        for (previous, current) in previous_app_regions.iter().zip(app_regions) {
+            for (previous_costs, current_costs) in [(&previous.egress_cost, &current.egress_cost), (&previous.ingress_cost, &current.ingress_cost)] {
+                for (region, cost) in current_costs {
+                    let previous_cost = previous_costs.iter().find(|(r, _)| r.name == region.name).map(|(_, c)| *c);
+                    if previous_cost != Some(*cost) {
+                        changed_regions.insert(region.name.clone());
+                        increased |= previous_cost.map_or(true, |c| *cost > c);
+                    }
+                }
+            }
+        }
+
+        let previous_object_stores = previous_object_stores.iter().map(|o| (object_store_name(o), o)).collect::<BTreeMap<_, _>>();
+        let mut unchanged = HashSet::new();
+        for object_store in object_stores {
+            let name = object_store_name(object_store);
+            let Some(previous) = previous_object_stores.get(&name) else {
+                continue;
+            };
+
+            let mut delta = Delta::default();
+            let (p, c) = (&previous.cost, &object_store.cost);
+            for (previous_price, price) in [(p.size_cost, c.size_cost), (p.put_cost, c.put_cost), (p.put_transfer, c.put_transfer), (p.get_cost, c.get_cost), (p.get_transfer, c.get_transfer)] {
+                delta.price(previous_price, price);
+            }
+            delta.network_costs(&p.egress_cost, &c.egress_cost);
+            delta.network_costs(&p.ingress_cost, &c.ingress_cost);
+
+            if delta.changed || changed_regions.contains(&object_store.region.name) {
+                increased |= delta.increased || changed_regions.contains(&object_store.region.name);
+            } else {
+                unchanged.insert(name);
+            }
+        }
+
+        Ok(PriceChanges { unchanged, increased })
+    }
+
+    /// Ids of the object stores with changed prices, including new object stores
+    pub fn changed_ids(&self, object_stores: &[ObjectStore]) -> HashSet<u16> {
+        object_stores.iter().filter(|o| !self.unchanged.contains(&object_store_name(o))).map(|o| o.id).collect()
+    }
+
+    /// Whether all object stores of the decision with names have unchanged prices
+    pub fn is_unchanged(&self, decision: &Decision) -> bool {
+        decision.replication_scheme.iter().flat_map(|s| s.object_stores.iter()).all(|o| self.unchanged.contains(o))
+    }
+}
+
+/// Decisions of the previous oracle whose write choices only have object stores with unchanged prices.
+/// Decisions of unchanged write choices may have been redundant only because of decisions with changed prices.
+/// When prices increased, they are taken from the candidates of the previous oracle if it has any, otherwise from its optimal decisions.
+pub fn unchanged_decisions(previous_stats_file: &Path, changes: &PriceChanges) -> io::Result<Vec<Decision>> {
+    let previous = load_wrapper(previous_stats_file)?;
+    let candidate_partitions = previous.candidate_partitions();
+    let partitions = if changes.increased && !candidate_partitions.is_empty() {
+        candidate_partitions
+    } else {
+        if changes.increased {
+            warn!("Prices increased but the previous oracle has no candidates, decisions that were only redundant because of the changed prices are missing, a full rerun is needed");
+        }
+        previous.optimal_partitions()
+    };
+
+    let previous_dir = previous_stats_file.parent().unwrap_or(Path::new(""));
+    let partitions = partitions.into_iter().map(|p| previous_dir.join(p)).collect_vec();
+    let decisions = load_decisions(partitions.iter().map(|p| p.as_path()).collect(), false)?;
+    let no_previous = decisions.len();
+
+    let unchanged = decisions.into_iter().filter(|d| changes.is_unchanged(d)).collect_vec();
+    info!(no_previous, no_unchanged = unchanged.len(), "Loaded unchanged decisions of the previous oracle");
+    Ok(unchanged)
+}
+
+/// Merges the unchanged decisions of the previous oracle with the optimal decisions of the re-enumerated write choices,
+/// re-reduces them, and writes them as a single partition with the ids of the id mapping. Returns the number of optimal decisions.
+pub fn merge_unchanged(previous_stats_file: &Path, changes: &PriceChanges, partitions: &[PathBuf], output_file: &Path, id_mapping: Option<&IdMapping>, reduction: &OracleReduction) -> io::Result<usize> {
+    let unchanged = unchanged_decisions(previous_stats_file, changes)?;
+    let affected = load_decisions(partitions.iter().map(|p| p.as_path()).collect(), false)?;
+
+    let app_regions = app_regions_of(&affected);
+    let previous_app_regions = app_regions_of(&unchanged);
+    if !unchanged.is_empty() && !affected.is_empty() && previous_app_regions != app_regions {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "App regions of the previous oracle ({:?}) differ from the re-enumerated decisions ({:?})", previous_app_regions, app_regions)));
+    }
+    let app_regions = if app_regions.is_empty() { previous_app_regions } else { app_regions };
+
+    let no_affected = affected.len();
+    let (reduction_time, optimal) = reduction.reduce(unchanged.into_iter().chain(affected).collect());
+    write_partition(output_file, &optimal, &app_regions, id_mapping)?;
+
+    info!(no_affected, no_optimal = optimal.len(), ?reduction_time, output_file = %output_file.to_string_lossy(), "Merged unchanged decisions of the previous oracle");
+    Ok(optimal.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{object_store::{Cost, ObjectStoreStruct}, Region};
+
+    fn object_store(id: u16, region: &Region, size_cost: f64, egress_cost: f64) -> ObjectStore {
+        let mut cost = Cost::new(size_cost, "storage");
+        cost.add_egress_costs(NetworkCostMap::from_iter([(region.clone(), egress_cost)]));
+        ObjectStore::new(ObjectStoreStruct { id, cost, region: region.clone(), name: "General Purpose".to_string() })
+    }
+
+    #[test]
+    fn test_price_changes() {
+        let regions = [Region { id: 0, name: "aws-eu-west-1".to_string() }, Region { id: 1, name: "gcp-europe-west1".to_string() }];
+        let app_regions = regions.iter().map(|r| ApplicationRegion { region: r.clone(), ..Default::default() }).collect_vec();
+        let previous = [object_store(0, &regions[0], 1.0, 1.0), object_store(1, &regions[1], 1.0, 1.0)];
+
+        let unchanged = PriceChanges::new(&app_regions, &previous, &app_regions, &previous).unwrap();
+        assert_eq!(unchanged.unchanged.len(), 2);
+        assert!(!unchanged.increased);
+
+        // Cheaper egress of one vendor
+        let current = [object_store(0, &regions[0], 1.0, 0.5), object_store(1, &regions[1], 1.0, 1.0)];
+        let changes = PriceChanges::new(&app_regions, &previous, &app_regions, &current).unwrap();
+        assert_eq!(changes.changed_ids(&current), HashSet::from([0]));
+        assert!(!changes.increased);
+
+        // More expensive storage
+        let current = [object_store(0, &regions[0], 1.0, 1.0), object_store(1, &regions[1], 2.0, 1.0)];
+        let changes = PriceChanges::new(&app_regions, &previous, &app_regions, &current).unwrap();
+        assert_eq!(changes.changed_ids(&current), HashSet::from([1]));
+        assert!(changes.increased);
+
+        let err = PriceChanges::new(&app_regions, &previous, &app_regions[..1], &previous).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_unchanged_decisions_invalid() {
+        let dir = std::env::temp_dir().join(format!("incremental_precomputation_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let decision = |object_store: &str, apps: &[&str]| Decision {
+            replication_scheme: Some(skypie_proto_messages::Scheme {
+                object_stores: vec![object_store.to_string()],
+                app_assignments: apps.iter().map(|app| skypie_proto_messages::Assignment { app: app.to_string(), object_store: object_store.to_string() }).collect(),
+                ..Default::default()
+            }),
+            cost_wl_halfplane: vec![1.0; 3 * apps.len() + 4],
+            ..Default::default()
+        };
+        let changes = PriceChanges { unchanged: HashSet::from(["x".to_string()]), increased: true };
+        let reduction = OracleReduction { batch_size: 10, optimizer: None, use_clarkson: false };
+
+        let err = unchanged_decisions(&dir.join("missing.proto.bin"), &changes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        // Increased prices take the candidates, the partition of the optimal decisions is missing
+        write_partition(&dir.join("candidates.proto.bin"), &[decision("x", &["a"]), decision("y", &["a"])], &["a".to_string()], None).unwrap();
+        let stats = skypie_proto_messages::Wrapper::new(vec![], vec!["candidates.proto.bin".to_string()], vec!["optimal.proto.bin".to_string()], 1, 1, 7, "optimizer".to_string(), "{}".to_string());
+        stats.save(dir.join("stats").to_str().unwrap());
+        let stats_file = dir.join("stats.proto.bin");
+        let unchanged = unchanged_decisions(&stats_file, &changes).unwrap();
+        assert_eq!(unchanged.iter().map(|d| d.replication_scheme.as_ref().unwrap().object_stores.clone()).collect_vec(), [["x"]]);
+        let decreased = PriceChanges { increased: false, ..changes };
+        assert_eq!(unchanged_decisions(&stats_file, &decreased).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        // Corrupt partition
+        std::fs::write(dir.join("optimal.proto.bin"), [0xff; 16]).unwrap();
+        assert!(unchanged_decisions(&stats_file, &decreased).is_err());
+
+        // Re-enumerated decisions of other app regions
+        let affected = dir.join("affected.proto.bin");
+        write_partition(&affected, &[decision("y", &["b"])], &["b".to_string()], None).unwrap();
+        let err = merge_unchanged(&stats_file, &PriceChanges { increased: true, ..decreased }, &[affected], &dir.join("merged.proto.bin"), None, &reduction).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(!dir.join("merged.proto.bin").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
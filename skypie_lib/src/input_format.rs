@@ -0,0 +1,264 @@
+//! Column names, naming rules and region aliases of the input files of the loader.
+//! The defaults are the formats of `storage_pricing.csv`, `network_cost_v2.csv` and the latency files,
+//! other price feeds are read directly by configuring their columns in a JSON file, e.g.,
+//! `{"network_columns": {"cost": "price_usd"}, "region_name": "{region}", "region_aliases": {"IE1": "aws-eu-west-1"}}`.
+
+use std::{collections::HashMap, fs::File, io::{self, BufReader}, path::{Path, PathBuf}};
+
+use csv::StringRecord;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    latency_record::{LatencyRecord, LatencyRecordRaw}, network_record::{NetworkRecord, NetworkRecordRaw},
+    object_store::{Cost, ObjectStoreStruct, ObjectStoreStructRaw}, region::Region, region_catalogue::REGION_CATALOGUE,
+};
+
+/// Column names of the object store file
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ObjectStoreColumns {
+    pub vendor: String,
+    pub region: String,
+    pub name: String,
+    pub group: String,
+    pub tier: String,
+    pub starting_range: String,
+    pub ending_range: String,
+    pub unit: String,
+    pub price_per_unit: String,
+}
+
+impl Default for ObjectStoreColumns {
+    fn default() -> Self {
+        ObjectStoreColumns {
+            vendor: "Vendor".to_string(),
+            region: "Region".to_string(),
+            name: "Name".to_string(),
+            group: "Group".to_string(),
+            tier: "Tier".to_string(),
+            starting_range: "StartingRange".to_string(),
+            ending_range: "EndingRange".to_string(),
+            unit: "Unit".to_string(),
+            price_per_unit: "PricePerUnit".to_string(),
+        }
+    }
+}
+
+/// Column names of the network cost file
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NetworkColumns {
+    pub src_vendor: String,
+    pub src_region: String,
+    pub dest_vendor: String,
+    pub dest_region: String,
+    pub cost: String,
+}
+
+impl Default for NetworkColumns {
+    fn default() -> Self {
+        NetworkColumns {
+            src_vendor: "src_vendor".to_string(),
+            src_region: "src_region".to_string(),
+            dest_vendor: "dest_vendor".to_string(),
+            dest_region: "dest_region".to_string(),
+            cost: "cost".to_string(),
+        }
+    }
+}
+
+/// Column names of the latency file
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LatencyColumns {
+    pub src_vendor: String,
+    pub src_region: String,
+    pub dest_vendor: String,
+    pub dest_region: String,
+    pub latency: String,
+}
+
+impl Default for LatencyColumns {
+    fn default() -> Self {
+        LatencyColumns {
+            src_vendor: "src_vendor".to_string(),
+            src_region: "src_region".to_string(),
+            dest_vendor: "dest_vendor".to_string(),
+            dest_region: "dest_region".to_string(),
+            latency: "latency".to_string(),
+        }
+    }
+}
+
+/// Format of the input files of the loader
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct InputFormat {
+    pub object_store_columns: ObjectStoreColumns,
+    pub network_columns: NetworkColumns,
+    pub latency_columns: LatencyColumns,
+    /// Template of region names, "{vendor}" and "{region}" are replaced by the values of the columns.
+    /// Files without vendor column set the vendor column to "" and use "{region}".
+    pub region_name: String,
+    /// Template of object store names, "{name}" and "{tier}" are replaced by the values of the columns
+    pub object_store_name: String,
+    /// Region names by alias, applied after joining the region name, e.g., to map the identifiers of a feed to the names of other files
+    pub region_aliases: HashMap<String, String>,
+}
+
+impl Default for InputFormat {
+    fn default() -> Self {
+        InputFormat {
+            object_store_columns: ObjectStoreColumns::default(),
+            network_columns: NetworkColumns::default(),
+            latency_columns: LatencyColumns::default(),
+            region_name: "{vendor}-{region}".to_string(),
+            object_store_name: "{name}-{tier}".to_string(),
+            region_aliases: HashMap::new(),
+        }
+    }
+}
+
+/// Reads the rows of a CSV file, renaming the configured columns to the fields of `T`.
+/// Other columns with the name of a configured field are ignored, so they cannot shadow the configured column.
+fn read_csv<T: DeserializeOwned>(path: &Path, columns: &[(&str, &str)]) -> io::Result<Vec<T>> {
+    // Open the file first, since csv errors lose the kind of io errors, e.g., NotFound
+    let mut reader = csv::Reader::from_reader(File::open(path)?);
+    let headers = reader.headers()?.iter().map(|header| {
+        match columns.iter().find(|(column, _)| *column == header) {
+            Some((_, field)) => *field,
+            None if columns.iter().any(|(_, field)| *field == header) => "",
+            None => header,
+        }
+    }).collect::<StringRecord>();
+
+    reader.records()
+        .map(|record| record.and_then(|r| r.deserialize(Some(&headers))))
+        .collect::<Result<Vec<T>, csv::Error>>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.to_string_lossy(), e)))
+}
+
+impl InputFormat {
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        serde_json::from_reader(BufReader::new(File::open(path)?))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.to_string_lossy(), e)))
+    }
+
+    /// Format of the file, if any, else the default format
+    pub fn from_optional_file(path: &Option<PathBuf>) -> io::Result<Self> {
+        path.as_deref().map_or_else(|| Ok(InputFormat::default()), InputFormat::from_file)
+    }
+
+    /// Region name of the vendor and region columns, after resolving the aliases of the format and of the region catalogue
+    pub fn region_name(&self, vendor: &str, region: &str) -> String {
+        let name = self.region_name.replace("{vendor}", vendor).replace("{region}", region);
+        REGION_CATALOGUE.canonical_name(self.region_aliases.get(&name).unwrap_or(&name))
+    }
+
+    pub fn object_store(&self, raw: ObjectStoreStructRaw) -> ObjectStoreStruct {
+        ObjectStoreStruct {
+            id: u16::MAX,
+            region: Region { id: u16::MAX, name: self.region_name(&raw.vendor, &raw.region) },
+            name: self.object_store_name.replace("{name}", &raw.name).replace("{tier}", &raw.tier),
+            cost: Cost::new(raw.price_per_unit, &raw.group),
+        }
+    }
+
+    pub(crate) fn network_record(&self, raw: NetworkRecordRaw) -> NetworkRecord {
+        NetworkRecord {
+            src: Region { id: u16::MAX, name: self.region_name(&raw.src_vendor, &raw.src_region) },
+            dest: Region { id: u16::MAX, name: self.region_name(&raw.dest_vendor, &raw.dest_region) },
+            cost: raw.cost,
+        }
+    }
+
+    pub(crate) fn latency_record(&self, raw: LatencyRecordRaw) -> LatencyRecord {
+        LatencyRecord {
+            src: Region { id: u16::MAX, name: self.region_name(&raw.src_vendor, &raw.src_region) },
+            dest: Region { id: u16::MAX, name: self.region_name(&raw.dest_vendor, &raw.dest_region) },
+            latency: raw.latency,
+        }
+    }
+
+    pub fn read_object_stores(&self, path: &Path) -> io::Result<Vec<ObjectStoreStruct>> {
+        let c = &self.object_store_columns;
+        let columns = [(c.vendor.as_str(), "Vendor"), (&c.region, "Region"), (&c.name, "Name"), (&c.group, "Group"), (&c.tier, "Tier"),
+            (&c.starting_range, "StartingRange"), (&c.ending_range, "EndingRange"), (&c.unit, "Unit"), (&c.price_per_unit, "PricePerUnit")];
+        Ok(read_csv::<ObjectStoreStructRaw>(path, &columns)?.into_iter().map(|raw| self.object_store(raw)).collect())
+    }
+
+    pub(crate) fn read_network(&self, path: &Path) -> io::Result<Vec<NetworkRecord>> {
+        let c = &self.network_columns;
+        let columns = [(c.src_vendor.as_str(), "src_vendor"), (&c.src_region, "src_region"), (&c.dest_vendor, "dest_vendor"), (&c.dest_region, "dest_region"), (&c.cost, "cost")];
+        Ok(read_csv::<NetworkRecordRaw>(path, &columns)?.into_iter().map(|raw| self.network_record(raw)).collect())
+    }
+
+    pub(crate) fn read_latency(&self, path: &Path) -> io::Result<Vec<LatencyRecord>> {
+        let c = &self.latency_columns;
+        let columns = [(c.src_vendor.as_str(), "src_vendor"), (&c.src_region, "src_region"), (&c.dest_vendor, "dest_vendor"), (&c.dest_region, "dest_region"), (&c.latency, "latency")];
+        Ok(read_csv::<LatencyRecordRaw>(path, &columns)?.into_iter().map(|raw| self.latency_record(raw)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_network() {
+        let path = std::env::temp_dir().join(format!("skypie_input_format_{}.csv", std::process::id()));
+        std::fs::write(&path, "from,to,price_usd,cost\nIE1,us-east-1,0.02,1.0\nus-east-1,IE1,0.09,1.0\n").unwrap();
+
+        let format: InputFormat = serde_json::from_str(r#"{
+            "network_columns": {"src_vendor": "", "src_region": "from", "dest_vendor": "", "dest_region": "to", "cost": "price_usd"},
+            "region_name": "{region}",
+            "region_aliases": {"IE1": "aws-eu-west-1", "us-east-1": "aws-us-east-1"}
+        }"#).unwrap();
+        // Unset fields keep their defaults
+        assert_eq!(format.object_store_columns, ObjectStoreColumns::default());
+
+        let records = format.read_network(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].src.name.as_str(), records[0].dest.name.as_str(), records[0].cost), ("aws-eu-west-1", "aws-us-east-1", 0.02));
+        assert_eq!(records[1].cost, 0.09);
+
+        assert_eq!(InputFormat::default().region_name("aws", "eu-west-1"), "aws-eu-west-1");
+        // Aliases of the region catalogue
+        assert_eq!(InputFormat::default().region_name("gcp", "us-east1"), "gcp-us-east1-b");
+    }
+
+    #[test]
+    fn test_invalid_input_format() {
+        let dir = std::env::temp_dir().join(format!("skypie_invalid_input_format_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+
+        // Format files
+        assert_eq!(InputFormat::from_file(&dir.join("missing.json")).unwrap_err().kind(), io::ErrorKind::NotFound);
+        let truncated = write("truncated.json", r#"{"region_name": "{region}""#);
+        assert_eq!(InputFormat::from_file(&truncated).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let wrong_type = write("wrong_type.json", r#"{"region_aliases": ["aws-eu-west-1"]}"#);
+        assert_eq!(InputFormat::from_optional_file(&Some(wrong_type)).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Price files
+        let format = InputFormat::default();
+        assert_eq!(format.read_network(&dir.join("missing.csv")).unwrap_err().kind(), io::ErrorKind::NotFound);
+        let missing_column = write("missing_column.csv", "src_vendor,src_region,dest_vendor,dest_region\naws,eu-west-1,aws,us-east-1\n");
+        let err = format.read_network(&missing_column).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("cost"), "{}", err);
+        let invalid_cost = write("invalid_cost.csv", "src_vendor,src_region,dest_vendor,dest_region,cost\naws,eu-west-1,aws,us-east-1,free\n");
+        assert_eq!(format.read_network(&invalid_cost).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let invalid_price = write("invalid_price.csv", "Vendor,Region,Name,Group,Tier,StartingRange,EndingRange,Unit,PricePerUnit\naws,eu-west-1,s3,storage,General Purpose,0,inf,GB-Month,\n");
+        assert_eq!(format.read_object_stores(&invalid_price).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let ragged = write("ragged.csv", "src_vendor,src_region,dest_vendor,dest_region,latency\naws,eu-west-1,aws\n");
+        assert_eq!(format.read_latency(&ragged).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
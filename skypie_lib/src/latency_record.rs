@@ -2,15 +2,18 @@ use std::collections::HashMap;
 
 use serde::Deserialize;
 
-use crate::region::Region;
+use crate::{input_format::InputFormat, region::Region};
 
+/// Latency record by the default column names, see `InputFormat` for other columns
 #[derive(Debug, Deserialize)]
 pub(crate) struct LatencyRecordRaw {
-    src_vendor: String,
-    src_region: String,
-    dest_vendor: String,
-    dest_region: String,
-    latency: f64
+    #[serde(default)]
+    pub src_vendor: String,
+    pub src_region: String,
+    #[serde(default)]
+    pub dest_vendor: String,
+    pub dest_region: String,
+    pub latency: f64
 }
 
 #[derive(Debug,Clone)]
@@ -24,14 +27,7 @@ pub(crate) struct LatencyRecord {
 
 impl From<LatencyRecordRaw> for LatencyRecord {
     fn from(raw: LatencyRecordRaw) -> Self {
-        let src_name = format!("{}-{}", raw.src_vendor, raw.src_region);
-        let dest_name = format!("{}-{}", raw.dest_vendor, raw.dest_region);
-
-        Self {
-            src: Region{id: u16::MAX, name:src_name},
-            dest: Region{id: u16::MAX, name:dest_name},
-            latency: raw.latency,
-        }
+        InputFormat::default().latency_record(raw)
     }
 }
 
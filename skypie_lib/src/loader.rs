@@ -1,33 +1,50 @@
-use std::{collections::{HashMap, HashSet}, path::PathBuf};
+use std::{collections::{HashMap, HashSet}, io, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+
+use tracing::{debug, info, warn};
 
 use crate::{
-    network_record::{NetworkRecord, NetworkRecordRaw, NetworkCostMaps},
-    object_store::{ObjectStoreStruct, ObjectStoreStructRaw, ObjectStore}, region::Region, identifier::Identifier,
-    ApplicationRegion, compatibility_checker::{CompatibilityChecker, DefaultCompatibilityChecker}, latency_record::{LatencyRecordRaw, LatencyRecord, LatencyMaps, LatencyMap}, compatibility_checker_network_slos::CompatibilityCheckerNetworkSLOs
+    network_record::NetworkCostMaps,
+    object_store::{ObjectStoreStruct, ObjectStore}, region::Region, identifier::Identifier, input_format::InputFormat, id_catalogue::{pinned_object_store_ids, pinned_region_ids, IdCatalogue},
+    ApplicationRegion, compatibility_checker::{CompatibilityChecker, DefaultCompatibilityChecker, WriteCompatibilityChecker, DefaultWriteCompatibilityChecker}, latency_record::{LatencyMaps, LatencyMap}, compatibility_checker_network_slos::CompatibilityCheckerNetworkSLOs, compatibility_checker_write_slos::CompatibilityCheckerWriteSLOs, stats::object_store_name
 };
 use itertools::Itertools;
 use regex::Regex;
+use skypie_proto_messages::{InputFile, Provenance};
 
 pub struct Loader {
     pub object_stores: Vec<ObjectStore>,
     pub app_regions: Vec<ApplicationRegion>,
     pub network_latency: LatencyMaps,
     pub compatibility_checker_slos: Box<dyn CompatibilityChecker>,
+    /// Content hashes of the input files and the selectors, to record which inputs produced an oracle
+    pub provenance: Provenance,
 }
 
 impl Loader {
     pub fn with_region_and_object_store_names(network_file_path: &PathBuf, object_store_file_path: &PathBuf, region_list: Vec<Region>, object_store_list: &Vec<String>, latency_file_path: &Option<PathBuf>, latency_slo: &Option<f64>, verbose: Option<i32>) -> Self {
-        Loader::load(network_file_path, object_store_file_path, None, None, Some(region_list), Some(object_store_list), latency_file_path, latency_slo, verbose)
+        Loader::load(network_file_path, object_store_file_path, None, None, Some(region_list), Some(object_store_list), latency_file_path, latency_slo, &None, &InputFormat::default(), None, verbose).unwrap()
     }
 
     pub fn new(network_file_path: &PathBuf, object_store_file_path: &PathBuf, region_pattern: &str, object_store_pattern: &str, latency_file_path: &Option<PathBuf>, latency_slo: &Option<f64>, verbose: Option<i32>) -> Loader {
-        Loader::load(network_file_path, object_store_file_path, Some(region_pattern), Some(object_store_pattern), None, None, latency_file_path, latency_slo, verbose)
+        Loader::with_input_format(network_file_path, object_store_file_path, region_pattern, object_store_pattern, latency_file_path, latency_slo, &InputFormat::default(), verbose)
+    }
+
+    /// Load input files with other column names, naming rules or region aliases than the default format
+    pub fn with_input_format(network_file_path: &PathBuf, object_store_file_path: &PathBuf, region_pattern: &str, object_store_pattern: &str, latency_file_path: &Option<PathBuf>, latency_slo: &Option<f64>, input_format: &InputFormat, verbose: Option<i32>) -> Loader {
+        Loader::with_id_catalogue(network_file_path, object_store_file_path, region_pattern, object_store_pattern, latency_file_path, latency_slo, &None, input_format, None, verbose).unwrap()
+    }
+
+    /// Load input files and pin the ids of the regions and object stores to their ids in the id catalogue, see `IdCatalogue`.
+    /// Without id catalogue, the ids are assigned by sorting the names. Fails with InvalidData if a loaded region or object store is not in the id catalogue.
+    /// With a write latency SLO, regions without latency data are dropped as for the read latency SLO, see `load_write_compatibility_checker`.
+    pub fn with_id_catalogue(network_file_path: &PathBuf, object_store_file_path: &PathBuf, region_pattern: &str, object_store_pattern: &str, latency_file_path: &Option<PathBuf>, latency_slo: &Option<f64>, write_latency_slo: &Option<f64>, input_format: &InputFormat, id_catalogue: Option<&IdCatalogue>, verbose: Option<i32>) -> io::Result<Loader> {
+        Loader::load(network_file_path, object_store_file_path, Some(region_pattern), Some(object_store_pattern), None, None, latency_file_path, latency_slo, write_latency_slo, input_format, id_catalogue, verbose)
     }
 
-    fn load(network_file_path: &PathBuf, object_store_file_path: &PathBuf, region_pattern: Option<&str>, object_store_pattern: Option<&str>, region_list: Option<Vec<Region>>, object_store_list: Option<&Vec<String>>, latency_file_path: &Option<PathBuf>, latency_slo: &Option<f64>, verbose: Option<i32>) -> Loader {
+    fn load(network_file_path: &PathBuf, object_store_file_path: &PathBuf, region_pattern: Option<&str>, object_store_pattern: Option<&str>, region_list: Option<Vec<Region>>, object_store_list: Option<&Vec<String>>, latency_file_path: &Option<PathBuf>, latency_slo: &Option<f64>, write_latency_slo: &Option<f64>, input_format: &InputFormat, id_catalogue: Option<&IdCatalogue>, verbose: Option<i32>) -> io::Result<Loader> {
 
         let verbose = verbose.unwrap_or(0);
-        let (network_egress, regions, region_names) = Loader::load_network(network_file_path, region_pattern, region_list, Some(verbose));
+        let (network_egress, regions, region_names) = Loader::load_network(network_file_path, region_pattern, region_list, input_format, id_catalogue, Some(verbose))?;
         
         // Load network ingress costs, currently 0.0
         let network_ingress = network_egress
@@ -53,11 +70,11 @@ impl Loader {
 
 
         // Load network latency and compatibility checker
-        let (network_latency, region_names, regions, network_egress, network_ingress) = if latency_file_path.is_some() && latency_slo.is_some() {
+        // Latency data is only loaded with a read or write latency SLO, since it drops regions without latency data
+        let (network_latency, region_names, regions, network_egress, network_ingress) = if latency_file_path.is_some() && (latency_slo.is_some() || write_latency_slo.is_some()) {
             let latency_file_path = latency_file_path.clone().unwrap();
-            assert!(latency_file_path.exists());
 
-            let (network_latency, region_names_with_latency_data) = Loader::load_latency(&latency_file_path, &region_names, Some(verbose));
+            let (network_latency, region_names_with_latency_data) = Loader::load_latency(&latency_file_path, &region_names, input_format, id_catalogue, Some(verbose))?;
 
             // Align network regions with regions that have latency data: filter out regions without latency data and update region IDs
             let network_egress = network_egress.into_iter().filter(|(region, _costs)|region_names_with_latency_data.contains_key(&region.name)).map(|(region, costs)|{
@@ -98,7 +115,7 @@ impl Loader {
         let compatibility_checker = Self::load_compatibility_checker(network_latency.clone(), latency_slo);
         
         
-        let object_stores = Loader::load_object_stores(object_store_file_path, &network_egress, &network_ingress, &region_names, object_store_pattern, object_store_list, Some(verbose));
+        let object_stores = Loader::load_object_stores(object_store_file_path, &network_egress, &network_ingress, &region_names, object_store_pattern, object_store_list, input_format, id_catalogue, Some(verbose))?;
 
         assert!(object_stores.len() > 0);
 
@@ -126,32 +143,39 @@ impl Loader {
         } */
         
         if verbose > 1 {
-            println!("Number of object stores: {}, number of regions: {}", object_stores.len(), app_regions.len());
+            info!(no_object_stores = object_stores.len(), no_regions = app_regions.len(), "Loaded scenario");
         }
 
-        Loader {
+        let provenance = Provenance {
+            input_files: InputFile::of_inputs(network_file_path, object_store_file_path, latency_file_path.as_deref())?,
+            load_timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            region_selector: region_pattern.map(|p| p.to_string()).unwrap_or_else(|| app_regions.iter().map(|r| &r.region.name).join("|")),
+            object_store_selector: object_store_pattern.map(|p| p.to_string()).unwrap_or_else(|| object_store_list.into_iter().flatten().join("|")),
+            latency_slo: *latency_slo,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            ..Default::default()
+        };
+
+        Ok(Loader {
             object_stores,
             app_regions,
             network_latency,
-            compatibility_checker_slos: compatibility_checker
-        }
+            compatibility_checker_slos: compatibility_checker,
+            provenance,
+        })
     }
 
     pub fn load_latency(
         latency_file_path: &PathBuf,
         region_names: &HashMap<String, Region>,
+        input_format: &InputFormat,
+        id_catalogue: Option<&IdCatalogue>,
         verbose: Option<i32>
-    ) -> (LatencyMaps, HashMap<String, Region>) {
+    ) -> io::Result<(LatencyMaps, HashMap<String, Region>)> {
 
         let verbose = verbose.unwrap_or(0);
-        
-        let rdr2 = csv::Reader::from_path(latency_file_path).unwrap();
-        let iter2: csv::DeserializeRecordsIntoIter<std::fs::File, LatencyRecordRaw> =
-            rdr2.into_deserialize();
 
-        let network_latency: LatencyMaps = iter2.filter_map(|x| {
-                // Convert to LatencyRecord
-                let mut l: LatencyRecord = x.unwrap().into();
+        let network_latency: LatencyMaps = input_format.read_latency(latency_file_path)?.into_iter().filter_map(|mut l| {
                 if region_names.contains_key(&l.src.name) && region_names.contains_key(&l.dest.name) {
                     // Translate region names to region IDs
                     l.src = region_names.get(&l.src.name).unwrap().clone();
@@ -175,22 +199,23 @@ impl Loader {
             }).collect_vec();
         
         if missing_source_latency.len() > 0 && verbose > 0 {
-            println!("Missing source latency for regions: {:?}", missing_source_latency);
+            warn!(?missing_source_latency, "Missing source latency for regions");
         }
         if missing_source_dest_latency.len() > 0 && verbose > 0 {
-            println!("Missing source/dest latency for regions: {:?}", missing_source_dest_latency);
+            warn!(?missing_source_dest_latency, "Missing source/dest latency for regions");
         }
 
         let missing_dest_latency: HashSet<_> = missing_source_dest_latency.iter().map(|(_src, dest)|dest).collect();
 
-        // Recompute region ids for regions with latency data
+        // Recompute region ids for regions with latency data, pinned ids are kept
+        let pinned_ids = pinned_region_ids(id_catalogue, region_names.keys().map(|name| name.as_str()))?;
         let regions_with_latency: HashMap<String, Region> = region_names.into_iter()
             .filter(|(_name, r)|!missing_source_latency.contains(r) && !missing_dest_latency.contains(r))
             .map(|(n ,r)|(n.clone(), r.clone()))
             .sorted_by_key(|(name, _region)|name.clone())
             .enumerate()
             .map(|(i, (name, mut region))|{
-                region.id = i as u16;
+                region.id = pinned_ids.as_ref().map_or(i as u16, |ids| ids[&name]);
                 (name, region)
             })
             .collect();
@@ -205,7 +230,7 @@ impl Loader {
             (src, latency_map)
         }).collect();
 
-        return (network_latency, regions_with_latency);
+        return Ok((network_latency, regions_with_latency));
     }
 
     fn load_compatibility_checker(
@@ -221,12 +246,31 @@ impl Loader {
         }
     }
 
+    /// Load the write compatibility checker, requires the latency data to be loaded.
+    /// Without write latency SLO, all write choices are compatible.
+    pub fn load_write_compatibility_checker(
+        &self,
+        write_latency_slo: &Option<f64>,
+        write_quorum: &Option<usize>,
+    ) -> Box<dyn WriteCompatibilityChecker> {
+
+        if let Some(write_latency_slo) = write_latency_slo {
+            assert!(!self.network_latency.is_empty(), "Write latency SLO requires latency data, set the latency file and pass the write latency SLO to the loader!");
+
+            Box::new(CompatibilityCheckerWriteSLOs::new(self.network_latency.clone(), *write_latency_slo, *write_quorum))
+        } else {
+            Box::new(DefaultWriteCompatibilityChecker{})
+        }
+    }
+
     fn load_network(
         network_file_path: &PathBuf,
         region_pattern: Option<&str>,
         region_list: Option<Vec<Region>>,
+        input_format: &InputFormat,
+        id_catalogue: Option<&IdCatalogue>,
         verbose: Option<i32>
-    ) -> (NetworkCostMaps, Vec<Region>, HashMap<String, Region>) {
+    ) -> io::Result<(NetworkCostMaps, Vec<Region>, HashMap<String, Region>)> {
         
         let verbose = verbose.unwrap_or(0);
 
@@ -234,20 +278,17 @@ impl Loader {
 
             let re = Regex::new(region_pattern).unwrap();
             
-            let rdr2 = csv::Reader::from_path(network_file_path).unwrap();
-            let iter2: csv::DeserializeRecordsIntoIter<std::fs::File, NetworkRecordRaw> =
-            rdr2.into_deserialize();
-            
             // Collect all region names, including the destination regions
-            let regions = iter2
-            .map(|r: Result<NetworkRecordRaw, csv::Error>| -> NetworkRecord { r.unwrap().into() })
+            let regions = input_format.read_network(network_file_path)?.into_iter()
             //.inspect(|r| println!("Raw network: {:?}", r))
             .filter(|r| re.is_match(&r.src.name) && re.is_match(&r.dest.name))
             .map(|r| {
                 [r.src.clone(), r.dest.clone()]
-            }).flatten().unique().sorted().enumerate().map(|(i, r)| Region{id: i as u16, name: r.name}).collect_vec();
+            }).flatten().unique().collect_vec();
 
-            regions
+            // Pinned ids of the id catalogue, else set region IDs by sorting and enumerating them
+            let pinned_ids = pinned_region_ids(id_catalogue, regions.iter().map(|r| r.name.as_str()))?;
+            regions.into_iter().sorted().enumerate().map(|(i, r)| Region{id: pinned_ids.as_ref().map_or(i as u16, |ids| ids[&r.name]), name: r.name}).sorted().collect_vec()
         }
         else if let Some(region_list) = region_list {
             region_list
@@ -258,12 +299,7 @@ impl Loader {
 
         let region_names: HashMap<String, Region> = HashMap::from_iter(regions.iter().map(|r| (r.name.clone(), r.clone())));
 
-        let rdr = csv::Reader::from_path(network_file_path).unwrap();
-        let iter: csv::DeserializeRecordsIntoIter<std::fs::File, NetworkRecordRaw> =
-            rdr.into_deserialize();
-
-        let mut network_costs: NetworkCostMaps = iter
-            .map(|r: Result<NetworkRecordRaw, csv::Error>| -> NetworkRecord { r.unwrap().into() })
+        let mut network_costs: NetworkCostMaps = input_format.read_network(network_file_path)?.into_iter()
             //.inspect(|r| println!("Raw network: {:?}", r))
             .filter(|r| region_names.contains_key(&r.src.name) && region_names.contains_key(&r.dest.name))
             //.inspect(|r| println!("Filtered network: {:?}", r))
@@ -319,21 +355,22 @@ impl Loader {
 
         if regions.len() != unique && verbose > 0 {
             for r in &regions {
-                println!("Region: {:?}", r);
+                debug!(region = ?r, "Duplicate region id");
             }
         }
         assert_eq!(regions.len(), unique);
-        if region_pattern.is_some() {
+        // Pinned ids can have gaps
+        if region_pattern.is_some() && id_catalogue.is_none() {
             assert_eq!(min, 0);
             assert_eq!(max as usize, regions.len() - 1);
         }
 
         regions.iter().for_each(|r|{assert!(r.get_id() != u16::MAX, "Found region with id u16::MAX {:?}", r)});
 
-        return (network_costs, regions, region_names);
+        return Ok((network_costs, regions, region_names));
     }
 
-    fn load_object_stores(object_store_file_path: &PathBuf, egress_costs: &NetworkCostMaps, ingress_costs: &NetworkCostMaps, region_names: &HashMap<String, Region>, object_store_pattern: Option<&str>, object_store_list: Option<&Vec<String>>, verbose: Option<i32>) -> Vec<ObjectStore> {
+    fn load_object_stores(object_store_file_path: &PathBuf, egress_costs: &NetworkCostMaps, ingress_costs: &NetworkCostMaps, region_names: &HashMap<String, Region>, object_store_pattern: Option<&str>, object_store_list: Option<&Vec<String>>, input_format: &InputFormat, id_catalogue: Option<&IdCatalogue>, verbose: Option<i32>) -> io::Result<Vec<ObjectStore>> {
         let verbose = verbose.unwrap_or(0);
 
         let object_store_regex = if object_store_list.is_none() {
@@ -346,12 +383,7 @@ impl Loader {
         let default_vec = vec![];
         let object_store_set:HashSet<&String> = HashSet::from_iter(object_store_list.unwrap_or(&default_vec));
 
-        let rdr = csv::Reader::from_path(object_store_file_path).unwrap();
-        let iter: csv::DeserializeRecordsIntoIter<std::fs::File, ObjectStoreStructRaw> =
-            rdr.into_deserialize();
-
-        let object_stores: Vec<ObjectStore> = iter
-            .map(|x| x.unwrap().into())
+        let object_stores: HashMap<String, ObjectStore> = input_format.read_object_stores(object_store_file_path)?.into_iter()
             .filter(|r: &ObjectStoreStruct| {
                 let full_name = format!("{}-{}", r.region.name, r.name);
                 region_names.contains_key(&r.region.name)
@@ -366,7 +398,7 @@ impl Loader {
 
                 // Set region with correct id
                 if !region_names.contains_key(&region_name) && verbose > 0 {
-                    println!("WARN: Region {:?} not found, skipping object store: {}", region_name, key);
+                    warn!(region = ?region_name, object_store = %key, "Region not found, skipping object store");
                 }
                 else {
                     let region = region_names.get(&region_name).unwrap().clone();
@@ -377,9 +409,14 @@ impl Loader {
                 entry.cost.merge(object_store.cost);
 
                 agg
-            }).into_iter()
-            // Sort by name
-            .sorted_by(|a,b|{a.0.cmp(&b.0)})
+            });
+
+        // Validate all object store names before assigning their pinned ids
+        let oracle_names = object_stores.values().map(object_store_name).collect_vec();
+        let pinned_ids = pinned_object_store_ids(id_catalogue, oracle_names.iter().map(|name| name.as_str()))?;
+
+        let object_stores: Vec<ObjectStore> = object_stores.into_iter()
+            .sorted_by_key(|(name, _o)|name.clone())
             .map(|(_n,  o)|{o})
             // Filter object stores whose region does not have network costs
             .inspect(|x|{
@@ -387,18 +424,19 @@ impl Loader {
                     //println!("Found network costs for {:?}, in {:?}", x.name, x.region);
                 } else {
                     if verbose > 0 {
-                        println!("WARN: No network costs for {:?}, in {:?}", x.name, x.region);
+                        warn!(object_store = ?x.name, region = ?x.region, "No network costs");
                     }
                 }
             })
             .filter(|x| egress_costs.contains_key(&x.region) && ingress_costs.contains_key(&x.region))
-            // Set ids of object stores by enumerating them
+            // Set ids of object stores to their pinned ids, else by enumerating them
             .enumerate()
             .map(|(i, x)| {
                 let mut x = x.clone();
-                x.id = i as u16;
+                x.id = pinned_ids.as_ref().map_or(i as u16, |ids| ids[&object_store_name(&x)]);
                 x
             })
+            .sorted_by_key(|x| x.id)
             // Add network egress/ingress costs
             .map(|x| {
                 let mut x = x.clone();
@@ -411,7 +449,7 @@ impl Loader {
                 debug_assert_eq!(region_egress_costs, x.cost.get_transfer);
                 let region_ingress_costs = x.cost.get_ingress_cost(&app_region, &x.region);
                 if region_ingress_costs != x.cost.put_transfer && verbose > 1 {
-                    println!("WARN: Region ingress costs {:?} != put transfer costs {:?} for object store {:?}", region_ingress_costs, x.cost.put_transfer, x);
+                    warn!(?region_ingress_costs, put_transfer_costs = ?x.cost.put_transfer, object_store = ?x, "Region ingress costs differ from put transfer costs");
                 }
                 debug_assert_eq!(region_ingress_costs, x.cost.put_transfer);
 
@@ -427,13 +465,107 @@ impl Loader {
 
         if object_stores.len() != unique && verbose > 0 {
             for r in &object_stores {
-                println!("Object store: {:?}", r);
+                debug!(object_store = ?r, "Duplicate object store id");
             }
         }
         assert_eq!(object_stores.len(), unique);
-        assert_eq!(min, 0);
-        assert_eq!(max as usize, object_stores.len() - 1);
+        // Pinned ids can have gaps
+        if id_catalogue.is_none() {
+            assert_eq!(min, 0);
+            assert_eq!(max as usize, object_stores.len() - 1);
+        }
+
+        return Ok(object_stores);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_data(name: &str) -> PathBuf {
+        PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data")).join(name)
+    }
+
+    #[test]
+    fn test_latency_only_with_slo() {
+        let load = |latency_slo: Option<f64>, write_latency_slo: Option<f64>| Loader::with_id_catalogue(
+            &test_data("loader_network.csv"), &test_data("loader_storage.csv"), "aws-", "", &Some(test_data("loader_latency.csv")),
+            &latency_slo, &write_latency_slo, &InputFormat::default(), None, None).unwrap();
+
+        // Without SLO, regions without latency data are kept
+        let loader = load(None, None);
+        assert_eq!(loader.app_regions.len(), 3);
+        assert!(loader.network_latency.is_empty());
+
+        // ap-south-1 has no latency data
+        for loader in [load(Some(10.0), None), load(None, Some(10.0))] {
+            assert_eq!(loader.app_regions.iter().map(|r| r.region.name.as_str()).sorted().collect_vec(), vec!["aws-eu-west-1", "aws-us-east-1"]);
+            assert_eq!(loader.object_stores.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_id_catalogue() {
+        let path = std::env::temp_dir().join(format!("skypie_loader_id_catalogue_{}.csv", std::process::id()));
+        let load_selected = |content: &str, region_pattern: &str, latency_slo: Option<f64>| {
+            std::fs::write(&path, content).unwrap();
+            let id_catalogue = IdCatalogue::from_file(&path).unwrap();
+            Loader::with_id_catalogue(&test_data("loader_network.csv"), &test_data("loader_storage.csv"), region_pattern, "", &Some(test_data("loader_latency.csv")), &latency_slo, &None, &InputFormat::default(), Some(&id_catalogue), None)
+        };
+        let load = |content: &str| load_selected(content, "aws-", None);
+        let ids = |loader: &Loader| (
+            loader.app_regions.iter().map(|r| (r.region.name.clone(), r.get_id())).sorted().collect_vec(),
+            loader.object_stores.iter().map(|o| (object_store_name(o), o.get_id())).collect_vec(),
+        );
+
+        // Ids are the pinned ids of the catalogue, also if the selector or the latency data exclude regions
+        let catalogue = "kind,name,id\nregion,aws-us-east-1,10\nregion,aws-eu-west-1,20\nregion,aws-ap-south-1,30\nobject_store,aws-us-east-1-s3-General Purpose,2\nobject_store,aws-eu-west-1-s3-General Purpose,1\nobject_store,aws-ap-south-1-s3-General Purpose,0\n";
+        let loader = load(catalogue).unwrap();
+        assert_eq!(ids(&loader), (
+            vec![("aws-ap-south-1".to_string(), 30), ("aws-eu-west-1".to_string(), 20), ("aws-us-east-1".to_string(), 10)],
+            vec![("aws-ap-south-1-s3-General Purpose".to_string(), 0), ("aws-eu-west-1-s3-General Purpose".to_string(), 1), ("aws-us-east-1-s3-General Purpose".to_string(), 2)],
+        ));
+        let loader = load_selected(catalogue, "aws-(us|ap)-", None).unwrap();
+        assert_eq!(ids(&loader), (
+            vec![("aws-ap-south-1".to_string(), 30), ("aws-us-east-1".to_string(), 10)],
+            vec![("aws-ap-south-1-s3-General Purpose".to_string(), 0), ("aws-us-east-1-s3-General Purpose".to_string(), 2)],
+        ));
+        // aws-ap-south-1 has no latency data
+        let loader = load_selected(catalogue, "aws-", Some(10.0)).unwrap();
+        assert_eq!(ids(&loader), (
+            vec![("aws-eu-west-1".to_string(), 20), ("aws-us-east-1".to_string(), 10)],
+            vec![("aws-eu-west-1-s3-General Purpose".to_string(), 1), ("aws-us-east-1-s3-General Purpose".to_string(), 2)],
+        ));
+        assert!(loader.network_latency.keys().all(|r| [10, 20].contains(&r.id)));
+
+        // Regions and object stores missing from the catalogue fail instead of panicking
+        let missing_region = load("kind,name,id\nregion,aws-us-east-1,0\n").err().unwrap();
+        let missing_object_store = load("kind,name,id\nregion,aws-us-east-1,0\nregion,aws-eu-west-1,1\nregion,aws-ap-south-1,2\n").err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(missing_region.kind(), io::ErrorKind::InvalidData);
+        assert!(missing_region.to_string().contains("aws-ap-south-1, aws-eu-west-1"), "{}", missing_region);
+        assert_eq!(missing_object_store.kind(), io::ErrorKind::InvalidData);
+    }
 
-        return object_stores;
+    #[test]
+    fn test_invalid_input_files() {
+        let path = std::env::temp_dir().join(format!("skypie_loader_malformed_{}.csv", std::process::id()));
+        std::fs::write(&path, "no,valid,columns\n1,2,3\n").unwrap();
+        let load = |network: &PathBuf, storage: &PathBuf, latency: Option<PathBuf>| Loader::with_id_catalogue(
+            network, storage, "aws-", "", &latency, &latency.as_ref().map(|_| 10.0), &None, &InputFormat::default(), None, None).err().unwrap().kind();
+
+        // Missing and malformed files fail instead of panicking
+        let missing = test_data("missing.csv");
+        assert_eq!(load(&missing, &test_data("loader_storage.csv"), None), io::ErrorKind::NotFound);
+        assert_eq!(load(&test_data("loader_network.csv"), &missing, None), io::ErrorKind::NotFound);
+        assert_eq!(load(&test_data("loader_network.csv"), &test_data("loader_storage.csv"), Some(missing)), io::ErrorKind::NotFound);
+        assert_eq!(load(&path, &test_data("loader_storage.csv"), None), io::ErrorKind::InvalidData);
+        assert_eq!(load(&test_data("loader_network.csv"), &path, None), io::ErrorKind::InvalidData);
+        assert_eq!(load(&test_data("loader_network.csv"), &test_data("loader_storage.csv"), Some(path.clone())), io::ErrorKind::InvalidData);
+        // Without latency SLO, the latency file is not loaded but hashed for the provenance
+        let load_without_slo = |latency: PathBuf| Loader::with_id_catalogue(&test_data("loader_network.csv"), &test_data("loader_storage.csv"), "aws-", "", &Some(latency), &None, &None, &InputFormat::default(), None, None);
+        assert_eq!(load_without_slo(test_data("missing.csv")).err().unwrap().kind(), io::ErrorKind::NotFound);
+        std::fs::remove_file(&path).unwrap();
     }
 }
@@ -0,0 +1,352 @@
+use std::{collections::{HashMap, HashSet, VecDeque}, path::{Path, PathBuf}, sync::{mpsc, Arc, Mutex}, time::{Duration, Instant}};
+
+use skypie_proto_messages::{load_wrapper, oracle_container, ProtobufFileSink};
+use tracing::{info, info_span, warn};
+
+use crate::{
+    chunks::{split_chunks, Chunk},
+    combination_ranking::CombinationRanking,
+    compatibility_checker::{CompatibilityChecker, WriteCompatibilityChecker},
+    incremental_precomputation::{merge_unchanged, PriceChanges},
+    input_format::InputFormat,
+    id_catalogue::IdCatalogue,
+    log_entry::SkyPieLogEntryType,
+    metrics::{serve_metrics, Metrics},
+    monitor::MonitorMovingAverage,
+    object_store::ObjectStore,
+    oracle_reduction::OracleReduction,
+    progress::{Progress, ProgressReport},
+    redundancy_elimination::RedundancyElimination,
+    stats::{new_stats, oracle_header, partition_file_names, stats_file_name, update_stats},
+    write_choice_candidates::write_choice_candidates,
+    write_choice_constraints::WriteChoiceConstraints,
+    ApplicationRegion, Args, BatcherMap, Decision, Loader
+};
+
+/// Log entries of the workers, same as sent to the logger in the deployed precomputation
+enum LogEntry {
+    Time(SkyPieLogEntryType, Duration),
+    Count(SkyPieLogEntryType, usize),
+}
+
+/// Read-only input shared by all workers
+struct Shared<'a> {
+    args: &'a Args,
+    regions: &'a Vec<ApplicationRegion>,
+    object_stores: &'a [ObjectStore],
+    object_store_id_map: &'a HashMap<u16, ObjectStore>,
+    compatibility_checker_slos: &'a dyn CompatibilityChecker,
+    write_compatibility_checker: &'a dyn WriteCompatibilityChecker,
+    ranking: &'a CombinationRanking,
+    constraints: &'a WriteChoiceConstraints,
+    // Object stores with changed prices in incremental mode, only write choices with one of them are enumerated
+    changed_object_stores: Option<&'a HashSet<u16>>,
+    // Chunks not yet taken by any worker
+    chunks: Mutex<VecDeque<Chunk>>,
+    metrics: Arc<Mutex<Metrics>>,
+}
+
+/// Path of the partition of a worker next to the output file, /dev/null without output file
+fn partition_path(output_file_name: &Option<PathBuf>, partitions: &[String], worker_id: usize) -> PathBuf {
+    match output_file_name {
+        Some(output_file_name) => output_file_name.parent().unwrap_or(Path::new("")).join(&partitions[worker_id]),
+        None => PathBuf::from("/dev/null"),
+    }
+}
+
+/// Run the whole precomputation in this process: Each worker thread takes the next chunk of write choices,
+/// computes and reduces the candidates, and writes the same partitions and stats as the deployed precomputation.
+pub fn local_precomputation(args: Args) {
+    let input_format = InputFormat::from_optional_file(&args.input_format).unwrap();
+    let id_catalogue = IdCatalogue::from_optional_file(&args.id_catalogue).unwrap();
+    let loader = Loader::with_id_catalogue(&args.network_file, &args.object_store_file, &args.region_selector, &args.object_store_selector, &args.latency_file, &args.latency_slo, &args.write_latency_slo, &input_format, id_catalogue.as_ref(), None).unwrap();
+    let write_compatibility_checker = loader.load_write_compatibility_checker(&args.write_latency_slo, &args.write_quorum);
+    // Fail before loading the previous oracle
+    args.unread_replica_pruning().unwrap();
+    let constraints = WriteChoiceConstraints::from_args(&args).unwrap();
+
+    if args.checkpoint_interval.is_some() || args.resume {
+        warn!("Checkpoints are not supported in local mode, ignoring them");
+    }
+
+    std::fs::create_dir_all(&args.experiment_name).unwrap();
+
+    let price_changes = args.previous_oracle.as_ref().map(|previous_oracle| {
+        let previous = Loader::with_id_catalogue(args.previous_network_file.as_ref().unwrap(), args.previous_object_store_file.as_ref().unwrap(), &args.region_selector, &args.object_store_selector, &args.latency_file, &args.latency_slo, &args.write_latency_slo, &input_format, id_catalogue.as_ref(), None).unwrap();
+        load_wrapper(previous_oracle).unwrap().check_provenance(&previous.provenance.input_files, args.provenance_check).unwrap();
+        PriceChanges::new(&previous.app_regions, &previous.object_stores, &loader.app_regions, &loader.object_stores).unwrap()
+    });
+    let changed_object_stores = price_changes.as_ref().map(|c| c.changed_ids(&loader.object_stores));
+    if let Some(changed_object_stores) = &changed_object_stores {
+        info!(no_changed_object_stores = changed_object_stores.len(), no_object_stores = loader.object_stores.len(), "Incremental precomputation");
+        assert!(args.output_file_name.is_some(), "Incremental precomputation requires an output file");
+    }
+
+    let (mut stats, optimizer_name) = new_stats(&loader, &args).unwrap();
+
+    let no_workers = args.redundancy_elimination_workers as usize;
+    let optimal_partitions = partition_file_names(&args.output_file_name, args.redundancy_elimination_workers);
+    let candidate_partitions = partition_file_names(&args.output_candidates_file_name, args.redundancy_elimination_workers);
+
+    let object_store_ids = loader.object_stores.iter().map(|x| x.id).collect::<Vec<_>>();
+    let ranking = CombinationRanking::new(object_store_ids, args.replication_factor, args.replication_factor_max.unwrap_or(args.replication_factor));
+    let object_store_id_map = loader.object_stores.iter().map(|x| (x.id, x.clone())).collect::<HashMap<_,_>>();
+
+    let metrics = Arc::new(Mutex::new(Metrics::new(vec![("experiment".to_string(), args.experiment_name.clone())])));
+    if let Some(port) = args.metrics_port {
+        serve_metrics(&args.metrics_address, port, metrics.clone()).unwrap();
+    }
+
+    let shared = Shared {
+        args: &args,
+        regions: &loader.app_regions,
+        object_stores: &loader.object_stores,
+        object_store_id_map: &object_store_id_map,
+        compatibility_checker_slos: loader.compatibility_checker_slos.as_ref(),
+        write_compatibility_checker: write_compatibility_checker.as_ref(),
+        ranking: &ranking,
+        constraints: &constraints,
+        changed_object_stores: changed_object_stores.as_ref(),
+        chunks: Mutex::new(split_chunks(ranking.total(), args.chunk_size, &HashMap::new()).into()),
+        metrics: metrics.clone(),
+    };
+
+    info!(write_choices = ranking.total(), no_workers, "Precomputing");
+
+    let mut progress = Progress::new(ranking.total(), 0, Duration::from_secs(args.progress_interval));
+    let progress_file_name = ProgressReport::file_name(&args.experiment_name);
+
+    let mut times = HashMap::<SkyPieLogEntryType, Duration>::new();
+    let mut counts = HashMap::<SkyPieLogEntryType, usize>::new();
+    let (log_send, log_recv) = mpsc::channel();
+
+    std::thread::scope(|s| {
+        for worker_id in 0..no_workers {
+            let log_send = log_send.clone();
+            let shared = &shared;
+            let candidates_file = partition_path(&args.output_candidates_file_name, &candidate_partitions, worker_id);
+            let optimal_file = partition_path(&args.output_file_name, &optimal_partitions, worker_id);
+
+            s.spawn(move || run_worker(shared, worker_id, &candidates_file, &optimal_file, log_send));
+        }
+        drop(log_send);
+
+        // Aggregate logs until all workers are done
+        for entry in log_recv {
+            match entry {
+                LogEntry::Time(entry_type, duration) => *times.entry(entry_type).or_default() += duration,
+                LogEntry::Count(entry_type, count) => *counts.entry(entry_type).or_default() += count,
+            }
+            if let Some(report) = progress.report_if_due(&counts) {
+                report.print();
+                report.save(&progress_file_name);
+                let mut metrics = metrics.lock().unwrap();
+                metrics.update_from_log(&times, &counts);
+                metrics.update_from_progress(&report);
+            }
+        }
+    });
+
+    let report = progress.report(&counts);
+    report.print();
+    report.save(&progress_file_name);
+
+    let mut metrics = metrics.lock().unwrap();
+    metrics.update_from_log(&times, &counts);
+    metrics.update_from_progress(&report);
+    metrics.save(&Metrics::file_name(&args.experiment_name));
+
+    update_stats(&mut stats, args.replication_factor as u64, &optimizer_name, &times, &counts);
+
+    if let (Some(previous_oracle), Some(price_changes), Some(output_file_name)) = (&args.previous_oracle, &price_changes, &args.output_file_name) {
+        let reduction = OracleReduction { batch_size: args.batch_size, optimizer: args.optimizer.clone(), use_clarkson: args.use_clarkson };
+        let partitions = (0..no_workers).map(|worker_id| partition_path(&args.output_file_name, &optimal_partitions, worker_id)).collect::<Vec<_>>();
+        let no_optimal = merge_unchanged(previous_oracle, price_changes, &partitions, output_file_name, stats.id_mapping.as_ref(), &reduction).unwrap();
+
+        // The merged partition replaces the partitions of the workers, the candidates only cover the re-enumerated write choices
+        let merged_partition = output_file_name.file_name().unwrap().to_string_lossy().to_string();
+        for run in stats.runs_mut() {
+            run.candidate_partitions.clear();
+            run.no_facets = Some(no_optimal as i64);
+            for optimizer in run.optimal_partitions_by_optimizer.values_mut() {
+                optimizer.optimal_partitions = vec![merged_partition.clone()];
+                optimizer.no_facets = Some(no_optimal as i64);
+            }
+        }
+    }
+
+    stats.save(&stats_file_name(&args.experiment_name));
+    info!(stats = %stats_file_name(&args.experiment_name), "DONE: stats written");
+}
+
+fn run_worker(shared: &Shared, worker_id: usize, candidates_file: &Path, optimal_file: &Path, log: mpsc::Sender<LogEntry>) {
+    let _span = info_span!("worker", worker_id, experiment = %shared.args.experiment_name).entered();
+    let start = Instant::now();
+    let args = shared.args;
+
+    let redundancy_elimination = RedundancyElimination::new(args.batch_size, args.optimizer.clone(), args.use_clarkson, shared.regions.len());
+    let mut batcher = BatcherMap::<Decision>::new(args.batch_size);
+    let header = oracle_header(shared.regions, shared.object_stores);
+    let mut candidate_sink = oracle_container::create(candidates_file, &header, 1024 * 1024, 1024).unwrap();
+    let mut optimal_sink = oracle_container::create(optimal_file, &header, 1024 * 1024, 1024).unwrap();
+    let mut optimal_monitor = MonitorMovingAverage::new(1000);
+    let monitor_name = format!("optimal_{}", worker_id);
+
+    // Decisions with unread replicas are dominated by the smaller write choice, if it is enumerated
+    let prune_unread_replicas = args.unread_replica_pruning().unwrap();
+    let prunable = |no_replicas: usize| prune_unread_replicas && no_replicas > args.replication_factor;
+
+    let next_chunk = || shared.chunks.lock().unwrap().pop_front();
+    while let Some(chunk) = next_chunk() {
+        let _span = info_span!("chunk", chunk = chunk.id, start = chunk.start, end = chunk.end).entered();
+        let write_choices = shared.ranking.iter_range(chunk.start..chunk.end)
+            .filter(|ids| match shared.changed_object_stores {
+                Some(changed) => ids.iter().any(|id| changed.contains(id)),
+                None => true,
+            })
+            .filter(|ids| shared.constraints.is_satisfied_by_ids(shared.object_store_id_map, ids));
+
+        for write_choice_ids in write_choices {
+            let prune = prunable(write_choice_ids.len());
+            let (candidates, pruned_write_choices) = write_choice_candidates(&write_choice_ids, shared.regions, shared.object_store_id_map, shared.compatibility_checker_slos, shared.write_compatibility_checker, prune);
+            if pruned_write_choices > 0 {
+                log.send(LogEntry::Count(SkyPieLogEntryType::PrunedWriteChoiceCount, pruned_write_choices)).unwrap();
+            }
+
+            let mut pruned_candidates = 0;
+            let mut no_candidates = 0;
+            for decision in candidates {
+                if prune && !decision.reads_all_replicas() {
+                    pruned_candidates += 1;
+                    continue;
+                }
+
+                no_candidates += 1;
+                candidate_sink.write(decision.clone().into_compact_proto()).unwrap();
+                if let Some(batch) = batcher.add(decision) {
+                    reduce_batch(&redundancy_elimination, batch, &mut optimal_sink, &mut optimal_monitor, &log);
+                }
+            }
+            if pruned_candidates > 0 {
+                log.send(LogEntry::Count(SkyPieLogEntryType::PrunedCandidateCount, pruned_candidates)).unwrap();
+            }
+            if no_candidates > 0 {
+                log.send(LogEntry::Count(SkyPieLogEntryType::CandidateCount, no_candidates)).unwrap();
+            }
+        }
+
+        log.send(LogEntry::Count(SkyPieLogEntryType::WriteChoiceCount, (chunk.end - chunk.start) as usize)).unwrap();
+        shared.metrics.lock().unwrap().update_from_monitor(&monitor_name, &optimal_monitor);
+    }
+
+    // Drain the last incomplete batch
+    if let Some(batch) = batcher.flush() {
+        reduce_batch(&redundancy_elimination, batch, &mut optimal_sink, &mut optimal_monitor, &log);
+    }
+    shared.metrics.lock().unwrap().update_from_monitor(&monitor_name, &optimal_monitor);
+
+    candidate_sink.flush().unwrap();
+    optimal_sink.flush().unwrap();
+    oracle_container::seal(candidates_file).unwrap();
+    oracle_container::seal(optimal_file).unwrap();
+
+    log.send(LogEntry::Time(SkyPieLogEntryType::Total, start.elapsed())).unwrap();
+    info!("Worker done");
+}
+
+fn reduce_batch(redundancy_elimination: &RedundancyElimination, batch: Vec<Decision>, optimal_sink: &mut ProtobufFileSink, optimal_monitor: &mut MonitorMovingAverage, log: &mpsc::Sender<LogEntry>) {
+    let (duration, optimal) = redundancy_elimination.reduce(batch);
+
+    log.send(LogEntry::Time(SkyPieLogEntryType::RedundancyElimination, duration)).unwrap();
+    log.send(LogEntry::Count(SkyPieLogEntryType::OptimalCount, optimal.len())).unwrap();
+
+    for decision in optimal {
+        optimal_sink.write(decision.into_compact_proto()).unwrap();
+        optimal_monitor.add_arrival_time_now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use clap::Parser;
+    use skypie_proto_messages::{load_wrapper, oracle_container::{count_messages, OracleContainer}};
+
+    use super::{local_precomputation, partition_path};
+    use crate::Args;
+
+    fn test_data(name: &str) -> String {
+        format!("{}/test_data/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn args(network_file: &str, extra: &[&str]) -> Args {
+        let experiment = std::env::temp_dir().join(format!("local_precomputation_test_{}", std::process::id()));
+        let args = ["skypie", "-r", "aws-", "--object-store-selector", "", "--replication-factor", "1", "-b", "2",
+            "-n", network_file, "-s", &test_data("loader_storage.csv"), "--redundancy-elimination-workers", "1", "-e", experiment.to_str().unwrap()];
+        Args::parse_from(args.iter().chain(extra))
+    }
+
+    #[test]
+    fn test_partition_path() {
+        let partitions = vec!["optimal_0.proto.bin".to_string(), "optimal_1.proto.bin".to_string()];
+        assert_eq!(partition_path(&Some(PathBuf::from("out/optimal.proto.bin")), &partitions, 1), PathBuf::from("out/optimal_1.proto.bin"));
+        assert_eq!(partition_path(&None, &partitions, 1), PathBuf::from("/dev/null"));
+    }
+
+    #[test]
+    #[ignore = "requires the Python optimizer of the redundancy elimination"]
+    fn test_local_precomputation() {
+        let mut args = args(&test_data("loader_network.csv"), &[]);
+        args.redundancy_elimination_workers = 2;
+        let experiment = format!("{}_happy_path", args.experiment_name);
+        args.experiment_name = experiment.clone();
+        args.output_file_name = Some(Path::new(&experiment).join("optimal.proto.bin"));
+        args.output_candidates_file_name = Some(Path::new(&experiment).join("candidates.proto.bin"));
+        local_precomputation(args);
+
+        let stats = load_wrapper(&Path::new(&experiment).join("stats.proto.bin")).unwrap();
+        assert_eq!(stats.runs().count(), 1);
+        assert_eq!(stats.optimal_partitions(), ["optimal_0.proto.bin", "optimal_1.proto.bin"]);
+        assert_eq!(stats.candidate_partitions(), ["candidates_0.proto.bin", "candidates_1.proto.bin"]);
+        let id_mapping = stats.id_mapping.as_ref().unwrap();
+        assert_eq!((id_mapping.app_regions.len(), id_mapping.object_stores.len()), (3, 3));
+        assert_eq!(stats.provenance.as_ref().unwrap().region_selector, "aws-");
+
+        // Partitions of all workers are sealed, each of the 3 write choices of one replica has one candidate
+        let count = |partitions: Vec<&String>| partitions.iter().map(|p| count_messages(&Path::new(&experiment).join(p)).unwrap()).sum::<usize>();
+        assert_eq!(count(stats.candidate_partitions()), 3);
+        let no_optimal = count(stats.optimal_partitions());
+        assert!(no_optimal > 0);
+        assert_eq!(stats.runs().next().unwrap().no_facets, Some(no_optimal as i64));
+        for partition in stats.optimal_partitions() {
+            let header = OracleContainer::open(&Path::new(&experiment).join(partition)).unwrap().header().clone();
+            assert_eq!((header.app_regions.len(), header.object_stores.len()), (3, 3));
+        }
+        std::fs::remove_dir_all(&experiment).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "NotFound")]
+    fn test_missing_network_file() {
+        local_precomputation(args(&test_data("missing_network.csv"), &[]));
+    }
+
+    #[test]
+    #[should_panic(expected = "--prune-unread-replicas cannot be combined with --min-vendors")]
+    fn test_pruning_with_min_vendors() {
+        local_precomputation(args(&test_data("loader_network.csv"), &["--prune-unread-replicas", "--min-vendors", "2"]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid regex for --required-region aws-(")]
+    fn test_invalid_required_region() {
+        local_precomputation(args(&test_data("loader_network.csv"), &["--required-region", "aws-("]));
+    }
+
+    #[test]
+    #[should_panic(expected = "NotFound")]
+    fn test_missing_id_catalogue() {
+        local_precomputation(args(&test_data("loader_network.csv"), &["--id-catalogue", &test_data("missing_catalogue.csv")]));
+    }
+}
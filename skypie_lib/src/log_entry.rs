@@ -1,7 +1,3 @@
-//use std::time::SystemTime;
-
-//use chrono::{DateTime, Utc};
-//use influxdb::InfluxDbWriteable;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
@@ -9,27 +5,13 @@ use serde::{Deserialize, Serialize};
 pub enum SkyPieLogEntryType {
     Total,
     RedundancyElimination,
+    /// Cycle time of a central write choice generator, not reported since the workers enumerate the write choices of their chunks
     WriteChoiceGeneration,
-    OptimalCount
-}
-
-/* #[derive(InfluxDbWriteable, Debug)]
-pub struct SkyPieLogEntry {
-    time: DateTime<Utc>,
-    cycle_time: f64,
-    count: u64,
-    #[influxdb(tag)] stage: String,
-    #[influxdb(tag)] experiment: String,
+    OptimalCount,
+    PrunedWriteChoiceCount,
+    PrunedCandidateCount,
+    WriteChoiceCount,
+    CandidateCount,
+    /// Time of the coordinator assigning chunks to workers
+    ChunkScheduling,
 }
-
-impl SkyPieLogEntry {
-    pub fn new(cycle_time: f64, count: u64, stage: String, experiment: String) -> Self {
-        Self {
-            time: SystemTime::now().into(),
-            cycle_time,
-            count,
-            stage,
-            experiment,
-        }
-    }
-} */
\ No newline at end of file
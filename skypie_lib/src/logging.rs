@@ -0,0 +1,43 @@
+use std::io;
+
+use tracing_subscriber::EnvFilter;
+
+/// Filter of the given level or directives, e.g., "debug" or "info,skypie_lib=trace". RUST_LOG takes precedence if set.
+pub fn log_filter(level: &str) -> io::Result<EnvFilter> {
+    if let Ok(filter) = EnvFilter::try_from_default_env() {
+        return Ok(filter);
+    }
+    EnvFilter::try_new(level).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid log level {:?}: {}", level, e)))
+}
+
+/// Initialize levelled logging to stdout, as plain text or JSON lines with the fields of the current spans.
+/// RUST_LOG takes precedence over the given level, fails if the level is invalid.
+pub fn init_logging(level: &str, json: bool) -> io::Result<()> {
+    let builder = tracing_subscriber::fmt().with_env_filter(log_filter(level)?);
+
+    // Ignore repeated initialization, e.g., in tests
+    let _ = if json {
+        builder.json().with_current_span(true).with_span_list(true).try_init()
+    } else {
+        builder.try_init()
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::log_filter;
+
+    #[test]
+    fn test_invalid_log_level() {
+        // Only without RUST_LOG, which takes precedence
+        if std::env::var("RUST_LOG").is_ok() {
+            return;
+        }
+
+        assert!(log_filter("info,skypie_lib=trace").is_ok());
+        assert_eq!(log_filter("skypie_lib=loud").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{log_entry::SkyPieLogEntryType, monitor::MonitorMovingAverage, progress::ProgressReport};
+
+// Slow or idle clients must not block the endpoint
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricType {
+    Counter,
+    Gauge,
+}
+
+struct MetricFamily {
+    metric_type: MetricType,
+    help: String,
+    // Labels of the sample -> value
+    samples: BTreeMap<Vec<(String, String)>, f64>,
+}
+
+/// Metrics of the precomputation in the OpenMetrics text format, see https://openmetrics.io
+pub struct Metrics {
+    families: BTreeMap<String, MetricFamily>,
+    // Labels of all samples, e.g., the experiment
+    labels: Vec<(String, String)>,
+}
+
+impl Metrics {
+    pub fn new(labels: Vec<(String, String)>) -> Self {
+        Metrics { families: BTreeMap::new(), labels }
+    }
+
+    pub fn file_name(experiment_name: &str) -> PathBuf {
+        PathBuf::from(format!("{}/metrics.txt", experiment_name))
+    }
+
+    /// Metrics of the monitors of a worker, written at its shutdown
+    pub fn worker_file_name(experiment_name: &str, worker_id: usize) -> PathBuf {
+        PathBuf::from(format!("{}/metrics_{}.txt", experiment_name, worker_id))
+    }
+
+    pub fn set(&mut self, name: &str, metric_type: MetricType, help: &str, labels: &[(&str, &str)], value: f64) {
+        let family = self.families.entry(name.to_string()).or_insert_with(|| MetricFamily {
+            metric_type,
+            help: help.to_string(),
+            samples: BTreeMap::new(),
+        });
+        debug_assert_eq!(family.metric_type, metric_type, "Metric {} redefined with a different type", name);
+
+        let labels = labels.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        family.samples.insert(labels, value);
+    }
+
+    /// Accumulated times and counts as sent by the services to the logger
+    pub fn update_from_log(&mut self, times: &HashMap<SkyPieLogEntryType, Duration>, counts: &HashMap<SkyPieLogEntryType, usize>) {
+        for (entry_type, duration) in times {
+            self.set("skypie_time_seconds", MetricType::Counter, "Accumulated time per stage", &[("type", &format!("{:?}", entry_type))], duration.as_secs_f64());
+        }
+        for (entry_type, count) in counts {
+            self.set("skypie_items", MetricType::Counter, "Accumulated count per item type", &[("type", &format!("{:?}", entry_type))], *count as f64);
+        }
+    }
+
+    /// Items and moving average throughput of a monitor, e.g., of the optimal decisions of a worker
+    pub fn update_from_monitor(&mut self, monitor_name: &str, monitor: &MonitorMovingAverage) {
+        self.set("skypie_monitor_items", MetricType::Counter, "Items seen by a monitor", &[("monitor", monitor_name)], monitor.get_count() as f64);
+        if let Some(throughput) = monitor.get_throughput() {
+            self.set("skypie_monitor_throughput", MetricType::Gauge, "Moving average of items per second of a monitor", &[("monitor", monitor_name)], throughput);
+        }
+    }
+
+    pub fn update_from_progress(&mut self, report: &ProgressReport) {
+        self.set("skypie_write_choices", MetricType::Gauge, "Total number of write choices", &[], report.write_choices_total as f64);
+        self.set("skypie_progress_ratio", MetricType::Gauge, "Fraction of write choices done", &[], report.fraction_done);
+        self.set("skypie_throughput", MetricType::Gauge, "Items per second since the previous progress report", &[("type", "write_choices")], report.write_choices_per_second);
+        self.set("skypie_throughput", MetricType::Gauge, "Items per second since the previous progress report", &[("type", "candidates")], report.candidates_per_second);
+        self.set("skypie_elapsed_seconds", MetricType::Gauge, "Time since the start of the logger", &[], report.elapsed_seconds);
+        if let Some(eta) = report.eta_seconds {
+            self.set("skypie_eta_seconds", MetricType::Gauge, "Estimated remaining time", &[], eta);
+        }
+    }
+
+    fn format_labels(&self, labels: &[(String, String)]) -> String {
+        let labels = self.labels.iter().chain(labels.iter())
+            .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+            .collect::<Vec<_>>();
+
+        if labels.is_empty() { String::new() } else { format!("{{{}}}", labels.join(",")) }
+    }
+
+    /// Exposition in the OpenMetrics text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (name, family) in &self.families {
+            let (type_name, suffix) = match family.metric_type {
+                MetricType::Counter => ("counter", "_total"),
+                MetricType::Gauge => ("gauge", ""),
+            };
+            out.push_str(&format!("# TYPE {} {}\n", name, type_name));
+            out.push_str(&format!("# HELP {} {}\n", name, family.help));
+            for (labels, value) in &family.samples {
+                out.push_str(&format!("{}{}{} {}\n", name, suffix, self.format_labels(labels), value));
+            }
+        }
+        out.push_str("# EOF\n");
+        out
+    }
+
+    pub fn save(&self, file_name: &Path) {
+        std::fs::write(file_name, self.render()).unwrap();
+    }
+}
+
+/// Serve the metrics at http://address:port/metrics in a background thread, returns the bound address.
+/// Each connection is handled in its own thread with timeouts.
+pub fn serve_metrics(address: &str, port: u16, metrics: Arc<Mutex<Metrics>>) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind((address, port))?;
+    let local_addr = listener.local_addr()?;
+    tracing::info!(%local_addr, "Serving metrics at /metrics");
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let metrics = metrics.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = handle_connection(stream, &metrics) {
+                            tracing::debug!(%e, "Metrics request failed");
+                        }
+                    });
+                }
+                Err(e) => tracing::warn!(%e, "Metrics connection failed"),
+            }
+        }
+    });
+
+    Ok(local_addr)
+}
+
+fn handle_connection(mut stream: TcpStream, metrics: &Mutex<Metrics>) -> io::Result<()> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+
+    // Only the request line matters, e.g., "GET /metrics HTTP/1.1"
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let response = if request_line.split_whitespace().nth(1) == Some("/metrics") {
+        let body = metrics.lock().unwrap().render();
+        format!("HTTP/1.1 200 OK\r\nContent-Type: application/openmetrics-text; version=1.0.0; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    stream.write_all(response.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io::{Read, Write}, net::TcpStream, sync::{Arc, Mutex}, time::Duration};
+
+    use crate::{log_entry::SkyPieLogEntryType, monitor::MonitorMovingAverage};
+
+    use super::{serve_metrics, Metrics};
+
+    #[test]
+    fn test_render_openmetrics() {
+        let mut metrics = Metrics::new(vec![("experiment".to_string(), "exp \"1\"".to_string())]);
+        let times = HashMap::from([(SkyPieLogEntryType::Total, Duration::from_millis(1500))]);
+        let counts = HashMap::from([(SkyPieLogEntryType::OptimalCount, 42)]);
+        metrics.update_from_log(&times, &counts);
+
+        assert_eq!(metrics.render(), "\
+# TYPE skypie_items counter
+# HELP skypie_items Accumulated count per item type
+skypie_items_total{experiment=\"exp \\\"1\\\"\",type=\"OptimalCount\"} 42
+# TYPE skypie_time_seconds counter
+# HELP skypie_time_seconds Accumulated time per stage
+skypie_time_seconds_total{experiment=\"exp \\\"1\\\"\",type=\"Total\"} 1.5
+# EOF
+");
+    }
+
+    #[test]
+    fn test_monitor_metrics() {
+        let mut metrics = Metrics::new(vec![]);
+        let mut monitor = MonitorMovingAverage::new(10);
+        metrics.update_from_monitor("optimal", &monitor);
+        assert!(!metrics.render().contains("skypie_monitor_throughput"));
+
+        monitor.add_arrival_time_now();
+        metrics.update_from_monitor("optimal", &monitor);
+        let rendered = metrics.render();
+        assert!(rendered.contains("skypie_monitor_items_total{monitor=\"optimal\"} 1\n"));
+        assert!(rendered.contains("skypie_monitor_throughput{monitor=\"optimal\"} "));
+    }
+
+    #[test]
+    fn test_serve_metrics() {
+        let mut metrics = Metrics::new(vec![]);
+        metrics.update_from_log(&HashMap::new(), &HashMap::from([(SkyPieLogEntryType::OptimalCount, 7)]));
+        let address = serve_metrics("127.0.0.1", 0, Arc::new(Mutex::new(metrics))).unwrap();
+        assert!(address.ip().is_loopback());
+
+        // An idle connection does not block other requests
+        let _idle = TcpStream::connect(address).unwrap();
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("skypie_items_total{type=\"OptimalCount\"} 7"));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404"));
+    }
+}
@@ -58,8 +58,7 @@ impl MonitorMovingAverage {
     }
 
     pub fn get_throughput(&self) -> Option<f64> {
-        
-        let average = self.get_arrival_time_average().unwrap();
+        let average = self.get_arrival_time_average()?;
         let average = average.as_secs_f64();
         let average = 1.0 / average;
 
@@ -73,10 +72,10 @@ impl MonitorMovingAverage {
     pub fn print(&self, prefix: &str, sample_rate: Option<i32>) {
         if let Some(rate) = sample_rate {
             if self.count % rate as usize == 0 {
-                println!("{}{}", prefix, self);
+                tracing::info!("{}{}", prefix, self);
             }
         } else {
-            println!("{}{}", prefix, self);
+            tracing::info!("{}{}", prefix, self);
         }
     }
 
@@ -84,7 +83,7 @@ impl MonitorMovingAverage {
 
 impl fmt::Display for MonitorMovingAverage {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let throughput = self.get_throughput().unwrap();
+        let throughput = self.get_throughput().unwrap_or(0.0);
         let count = self.get_count();
         write!(f, "{} items, {:.2} items/s", count, throughput)
     }
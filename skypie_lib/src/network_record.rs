@@ -1,16 +1,19 @@
 use std::collections::HashMap;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::region::Region;
+use crate::{input_format::InputFormat, region::Region};
 
-#[derive(Debug, Deserialize)]
+/// Network cost record by the default column names, see `InputFormat` for other columns
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub(crate) struct NetworkRecordRaw {
-    src_vendor: String,
-    src_region: String,
-    dest_vendor: String,
-    dest_region: String,
-    cost: f64
+    #[serde(default)]
+    pub src_vendor: String,
+    pub src_region: String,
+    #[serde(default)]
+    pub dest_vendor: String,
+    pub dest_region: String,
+    pub cost: f64
 }
 
 #[derive(Debug,Clone)]
@@ -24,14 +27,7 @@ pub(crate) struct NetworkRecord {
 
 impl From<NetworkRecordRaw> for NetworkRecord {
     fn from(raw: NetworkRecordRaw) -> Self {
-        let src_name = format!("{}-{}", raw.src_vendor, raw.src_region);
-        let dest_name = format!("{}-{}", raw.dest_vendor, raw.dest_region);
-
-        Self {
-            src: Region{id: u16::MAX, name:src_name},
-            dest: Region{id: u16::MAX, name:dest_name},
-            cost: raw.cost,
-        }
+        InputFormat::default().network_record(raw)
     }
 }
 
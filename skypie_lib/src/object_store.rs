@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{ApplicationRegion, Tombstone};
+use crate::{input_format::InputFormat, ApplicationRegion, Tombstone};
 use crate::network_record::NetworkCostMap;
 use crate::{region::Region, range::Range};
 use std::collections::HashMap;
@@ -39,7 +39,7 @@ impl Cost {
             "put request" => cost.put_cost = price_per_unit,
             "put transfer" => cost.put_transfer = price_per_unit,
             "storage" => cost.size_cost = price_per_unit,
-            _ => println!("Warning unknown price group: {}", group)
+            _ => tracing::warn!(group, "Unknown price group")
         }
 
         return cost;
@@ -122,36 +122,35 @@ impl Default for Cost {
     }
 }
 
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct ObjectStoreStructRaw
 {
-    //"Vendor", "Region", "Name", "Tier", "Group", /* "StartingRange", "EndingRange", */ "PricePerUnit"
-    // Set column names for Deserializer
-    #[serde(rename = "Vendor")]
+    //"Vendor", "Region", "Name", "Group", "Tier", "StartingRange", "EndingRange", "Unit", "PricePerUnit"
+    // Set column names for Deserializer, see `InputFormat` for other columns
+    #[serde(rename = "Vendor", default)]
     pub vendor: String,
     #[serde(rename = "Region")]
     pub region: String,
     #[serde(rename = "Name")]
     pub name: String,
-    #[serde(rename = "Tier")]
-    pub tier: String,
     #[serde(rename = "Group")]
     pub group: String,
+    #[serde(rename = "Tier")]
+    pub tier: String,
+    // Volume tier of the price, the loader merges the tiers by maximum
+    #[serde(rename = "StartingRange", default)]
+    pub starting_range: Option<f64>,
+    #[serde(rename = "EndingRange", default)]
+    pub ending_range: Option<f64>,
+    #[serde(rename = "Unit", default)]
+    pub unit: String,
     #[serde(rename = "PricePerUnit")]
     pub price_per_unit: f64,
 }
 
 impl From<ObjectStoreStructRaw> for ObjectStoreStruct {
     fn from(raw: ObjectStoreStructRaw) -> Self {
-        let region = Region{id: u16::MAX, name: format!("{}-{}", raw.vendor, raw.region)};
-        let name = format!("{}-{}", raw.name, raw.tier);
-        let cost: Cost = Cost::new(raw.price_per_unit, &raw.group);
-        Self {
-            id: u16::MAX,
-            region,
-            name,
-            cost
-        }
+        InputFormat::default().object_store(raw)
     }
 }
 
@@ -11,7 +11,7 @@ use crate::{
 pub(crate) fn opt_assignments(
     write_choice: Box<WriteChoice>,
     region: &ApplicationRegion,
-    compatibility_checker: &Box<dyn CompatibilityChecker>
+    compatibility_checker: &dyn CompatibilityChecker
 ) -> std::vec::IntoIter<(ObjectStore, Range)>
 {
     debug_assert_ne!(write_choice.object_stores.len(), 0);
@@ -162,7 +162,7 @@ mod tests {
 
         let compatibility_checker: Box::<dyn CompatibilityChecker> = Box::new(crate::compatibility_checker::DefaultCompatibilityChecker{});
         let res: Vec<(ObjectStore, Range)> =
-            opt_assignments(Box::<WriteChoice>::new(write_choice), &region, &compatibility_checker)
+            opt_assignments(Box::<WriteChoice>::new(write_choice), &region, compatibility_checker.as_ref())
                 .sorted_by(|(a, _), (b, _)| Ord::cmp(&a.id, &b.id))
                 .collect();
         res.iter()
@@ -0,0 +1,312 @@
+//! Diff of two oracles of the same scenario, e.g., before and after a price update.
+//! Decisions are matched by their write choice and assignments, not by their position in the partitions.
+
+use std::{collections::BTreeMap, fmt, io, path::{Path, PathBuf}};
+
+use clap::Parser;
+use itertools::Itertools;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use skypie_proto_messages::{dimension_names, load_optimal_decisions, Decision};
+use tracing::{info, info_span};
+
+#[derive(Debug, Parser)]
+pub struct DiffArgs {
+    /// Stats file of the old oracle
+    #[clap(long)]
+    pub old: PathBuf,
+
+    /// Stats file of the new oracle
+    #[clap(long)]
+    pub new: PathBuf,
+
+    /// CSV file of the added and removed decisions and of the coefficient changes of kept decisions
+    #[clap(short = 'o', long)]
+    pub output_file_name: Option<PathBuf>,
+
+    /// Relative difference of coefficients that counts as change
+    #[clap(long, default_value_t = 1e-9)]
+    pub tolerance: f64,
+
+    /// Number of sampled workloads to estimate the share of the workload space that maps to a different decision, no estimate if 0
+    #[clap(long, default_value_t = 1000)]
+    pub samples: usize,
+
+    /// Seed of the sampled workloads
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Log level or filter directives, e.g., "debug" or "info,skypie_lib=trace", overridden by RUST_LOG
+    #[clap(long, default_value = "info")]
+    pub log_level: String,
+
+    /// Log as JSON lines, e.g., for log aggregation
+    #[clap(long)]
+    pub log_json: bool,
+}
+
+/// Identity of a decision: its write choice and the object store of each app region, both sorted by name
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DecisionKey {
+    pub write_choice: Vec<String>,
+    pub assignments: Vec<(String, String)>,
+}
+
+impl DecisionKey {
+    pub fn of(decision: &Decision) -> Self {
+        let scheme = decision.replication_scheme.clone().unwrap_or_default();
+        DecisionKey {
+            write_choice: scheme.object_stores.into_iter().sorted().collect(),
+            assignments: scheme.app_assignments.into_iter().map(|a| (a.app, a.object_store)).sorted().collect(),
+        }
+    }
+
+    fn assignments_str(&self) -> String {
+        self.assignments.iter().map(|(app, object_store)| format!("{}->{}", app, object_store)).join(";")
+    }
+}
+
+impl fmt::Display for DecisionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.write_choice.join(","), self.assignments_str())
+    }
+}
+
+/// Optimal decisions of an oracle by key, with the coefficients ordered by the sorted app regions
+struct Oracle {
+    app_regions: Vec<String>,
+    decisions: BTreeMap<DecisionKey, Vec<f64>>,
+}
+
+impl Oracle {
+    fn load(stats_file: &Path) -> io::Result<Self> {
+        let _span = info_span!("load_oracle", stats_file = %stats_file.to_string_lossy()).entered();
+        Self::from_decisions(load_optimal_decisions(stats_file)?)
+    }
+
+    fn from_decisions(decisions: Vec<Decision>) -> io::Result<Self> {
+        let mut app_regions = None;
+        let mut by_key = BTreeMap::new();
+
+        for decision in decisions {
+            let apps = decision.replication_scheme.iter().flat_map(|s| s.app_assignments.iter().map(|a| a.app.clone())).collect_vec();
+            let coefficients = sorted_coefficients(&apps, &decision.cost_wl_halfplane)?;
+            let apps = apps.into_iter().sorted().collect_vec();
+            match &app_regions {
+                Some(app_regions) if *app_regions != apps => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Decisions of the oracle have different app regions: {:?} vs. {:?}", app_regions, apps)));
+                },
+                Some(_) => {},
+                None => app_regions = Some(apps),
+            }
+            by_key.entry(DecisionKey::of(&decision)).or_insert(coefficients);
+        }
+
+        Ok(Oracle { app_regions: app_regions.unwrap_or_default(), decisions: by_key })
+    }
+
+    /// Key of the cheapest decision for the workload, i.e., the coefficients of storage, put, get, ingress and egress
+    fn optimal_for(&self, workload: &[f64]) -> Option<&DecisionKey> {
+        self.decisions.iter()
+            .map(|(key, coefficients)| (key, coefficients[0] + coefficients[1..].iter().zip(workload).map(|(c, w)| c * w).sum::<f64>()))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(key, _)| key)
+    }
+}
+
+/// Halfplane coefficients with the per-region coefficients ordered by the sorted app regions
+fn sorted_coefficients(apps: &[String], coefficients: &[f64]) -> io::Result<Vec<f64>> {
+    let n = apps.len();
+    if coefficients.len() != 3 * n + 4 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Decision has {} coefficients, expected {} for {} app regions", coefficients.len(), 3 * n + 4, n)));
+    }
+
+    let order = (0..n).sorted_by_key(|&i| &apps[i]).collect_vec();
+    let mut sorted = coefficients[..3].to_vec();
+    for offset in [3, 3 + n, 3 + 2 * n] {
+        sorted.extend(order.iter().map(|&i| coefficients[offset + i]));
+    }
+    sorted.push(coefficients[3 + 3 * n]);
+    Ok(sorted)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoefficientChange {
+    pub dimension: String,
+    pub old: f64,
+    pub new: f64,
+}
+
+#[derive(Debug)]
+pub struct OracleDiff {
+    pub added: Vec<DecisionKey>,
+    pub removed: Vec<DecisionKey>,
+    /// Decisions in both oracles with their coefficient changes, if any
+    pub kept: Vec<(DecisionKey, Vec<CoefficientChange>)>,
+    /// Share of the sampled workloads whose optimal decision differs between the oracles
+    pub changed_workload_share: Option<f64>,
+}
+
+impl OracleDiff {
+    pub fn no_changed(&self) -> usize {
+        self.kept.iter().filter(|(_, changes)| !changes.is_empty()).count()
+    }
+
+    /// Writes a row per added or removed decision and per coefficient change of a kept decision
+    pub fn write_csv(&self, file_name: &Path) -> io::Result<()> {
+        let mut writer = csv::Writer::from_path(file_name)?;
+        writer.write_record(["status", "write_choice", "assignments", "dimension", "old", "new"])?;
+
+        for (status, keys) in [("added", &self.added), ("removed", &self.removed)] {
+            for key in keys {
+                writer.write_record([status, &key.write_choice.join(","), &key.assignments_str(), "", "", ""])?;
+            }
+        }
+        for (key, changes) in &self.kept {
+            for change in changes {
+                writer.write_record(["changed", &key.write_choice.join(","), &key.assignments_str(), &change.dimension, &change.old.to_string(), &change.new.to_string()])?;
+            }
+        }
+
+        writer.flush()
+    }
+}
+
+fn diff_decisions(old: &Oracle, new: &Oracle, tolerance: f64, samples: usize, seed: u64) -> io::Result<OracleDiff> {
+    if old.app_regions != new.app_regions && !old.decisions.is_empty() && !new.decisions.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Oracles have different app regions: {:?} vs. {:?}", old.app_regions, new.app_regions)));
+    }
+    let dimensions = dimension_names(&old.app_regions);
+
+    let added = new.decisions.keys().filter(|k| !old.decisions.contains_key(*k)).cloned().collect_vec();
+    let removed = old.decisions.keys().filter(|k| !new.decisions.contains_key(*k)).cloned().collect_vec();
+    let kept = old.decisions.iter()
+        .filter_map(|(key, old_coefficients)| new.decisions.get(key).map(|new_coefficients| {
+            let changes = dimensions.iter().zip(old_coefficients.iter().zip(new_coefficients))
+                .filter(|(_, (&o, &n))| (o - n).abs() > tolerance * o.abs().max(n.abs()))
+                .map(|(dimension, (&old, &new))| CoefficientChange { dimension: dimension.clone(), old, new })
+                .collect_vec();
+            (key.clone(), changes)
+        }))
+        .collect_vec();
+
+    let changed_workload_share = if samples > 0 && !old.decisions.is_empty() && !new.decisions.is_empty() {
+        let workloads = sample_workloads(dimensions.len() - 2, samples, seed);
+        let changed = workloads.par_iter().filter(|w| old.optimal_for(w) != new.optimal_for(w)).count();
+        Some(changed as f64 / samples as f64)
+    } else {
+        None
+    };
+
+    Ok(OracleDiff { added, removed, kept, changed_workload_share })
+}
+
+/// Workloads with log-uniform parameters between 10^-3 and 10^3, i.e., storage, put, get, ingress and egress volumes
+fn sample_workloads(no_parameters: usize, samples: usize, seed: u64) -> Vec<Vec<f64>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..samples)
+        .map(|_| (0..no_parameters).map(|_| 10f64.powf(rng.gen_range(-3.0..3.0))).collect())
+        .collect()
+}
+
+/// Diff of the optimal decisions of the old and new oracle, see `OracleDiff`
+pub fn diff_oracles(old: &Path, new: &Path, tolerance: f64, samples: usize, seed: u64) -> io::Result<OracleDiff> {
+    diff_decisions(&Oracle::load(old)?, &Oracle::load(new)?, tolerance, samples, seed)
+}
+
+/// Diff the oracles of the arguments, log a summary and write the details to the output file
+pub fn diff(args: DiffArgs) {
+    let diff = diff_oracles(&args.old, &args.new, args.tolerance, args.samples, args.seed).unwrap();
+
+    let changed_dimensions = diff.kept.iter()
+        .flat_map(|(_, changes)| changes.iter().map(|c| c.dimension.as_str()))
+        .counts()
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+    info!(
+        added = diff.added.len(),
+        removed = diff.removed.len(),
+        kept = diff.kept.len(),
+        changed = diff.no_changed(),
+        changed_workload_share = ?diff.changed_workload_share,
+        ?changed_dimensions,
+        "Diff of oracles"
+    );
+
+    if let Some(output_file_name) = &args.output_file_name {
+        diff.write_csv(output_file_name).unwrap();
+        info!(output_file_name = %output_file_name.to_string_lossy(), "Wrote diff");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skypie_proto_messages::{Assignment, Scheme};
+
+    fn decision(object_store: &str, costs: [f64; 2]) -> Decision {
+        let apps = ["b", "a"];
+        Decision {
+            replication_scheme: Some(Scheme {
+                object_stores: vec![object_store.to_string()],
+                app_assignments: apps.iter().map(|app| Assignment { app: app.to_string(), object_store: object_store.to_string() }).collect(),
+                ..Default::default()
+            }),
+            // Storage costs, put costs, and get costs of "b" differ
+            cost_wl_halfplane: vec![0.0, costs[0], 1.0, costs[1], 1.0, 0.0, 0.0, 0.0, 0.0, -1.0],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Oracle::from_decisions(vec![decision("x", [1.0, 1.0]), decision("y", [2.0, 0.5])]).unwrap();
+        let new = Oracle::from_decisions(vec![decision("y", [2.0, 0.25]), decision("z", [0.5, 1.0])]).unwrap();
+        assert_eq!(old.app_regions, ["a", "b"]);
+
+        let diff = diff_decisions(&old, &new, 1e-9, 100, 0).unwrap();
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].write_choice, ["z"]);
+        assert_eq!(diff.removed[0].write_choice, ["x"]);
+        assert_eq!(diff.kept.len(), 1);
+        // Get costs of "b" are sorted after those of "a"
+        assert_eq!(diff.kept[0].1, vec![CoefficientChange { dimension: "get_b".to_string(), old: 0.5, new: 0.25 }]);
+        let share = diff.changed_workload_share.unwrap();
+        assert!(share > 0.0 && share <= 1.0);
+
+        let same = diff_decisions(&old, &old, 1e-9, 100, 0).unwrap();
+        assert_eq!(same.no_changed(), 0);
+        assert_eq!(same.changed_workload_share, Some(0.0));
+    }
+
+    #[test]
+    fn test_diff_invalid() {
+        // Coefficients of another number of app regions
+        let mut truncated = decision("x", [1.0, 1.0]);
+        truncated.cost_wl_halfplane.pop();
+        let err = Oracle::from_decisions(vec![truncated]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Decisions with different app regions within and across oracles
+        let mut other_apps = decision("y", [1.0, 1.0]);
+        other_apps.replication_scheme.as_mut().unwrap().app_assignments[0].app = "c".to_string();
+        let err = Oracle::from_decisions(vec![decision("x", [1.0, 1.0]), other_apps.clone()]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let old = Oracle::from_decisions(vec![decision("x", [1.0, 1.0])]).unwrap();
+        let new = Oracle::from_decisions(vec![other_apps]).unwrap();
+        assert_eq!(diff_decisions(&old, &new, 1e-9, 0, 0).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        // Empty oracles have no app regions
+        let empty = Oracle::from_decisions(vec![]).unwrap();
+        assert_eq!(diff_decisions(&old, &empty, 1e-9, 10, 0).unwrap().removed.len(), 1);
+
+        // Missing and corrupt stats files
+        let dir = std::env::temp_dir().join(format!("oracle_diff_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let missing = dir.join("missing.proto.bin");
+        assert_eq!(diff_oracles(&missing, &missing, 1e-9, 0, 0).err().unwrap().kind(), io::ErrorKind::NotFound);
+        let corrupt = dir.join("corrupt.proto.bin");
+        std::fs::write(&corrupt, [0xff; 16]).unwrap();
+        assert!(diff_oracles(&corrupt, &corrupt, 1e-9, 0, 0).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,275 @@
+//! Merge of precomputed oracles, e.g., of different replication factors or scenarios, into one oracle.
+//! Runs are keyed by their replication factor and name, runs with the same key are combined.
+
+use std::{fs, io, path::{Path, PathBuf}, str::FromStr};
+
+use clap::Parser;
+use itertools::Itertools;
+use skypie_proto_messages::{load_decisions, load_oracle_header, load_wrapper, PartitionPlacement, Provenance, Setting, Wrapper};
+use tracing::{info, info_span};
+
+use crate::oracle_reduction::{app_regions_of, OracleReduction};
+
+#[derive(Debug, Parser)]
+pub struct MergeArgs {
+    /// Stats file of an oracle to merge, optionally with the name of its runs in the merged oracle, e.g., "results/aws/2/stats.proto.bin:aws-2", can be repeated
+    #[clap(long = "input", required = true)]
+    pub inputs: Vec<MergeInput>,
+
+    /// Output directory of the merged oracle
+    #[clap(short = 'o', long)]
+    pub output_dir: PathBuf,
+
+    /// Name of the stats file in the output directory, without extension
+    #[clap(long, default_value = "stats")]
+    pub output_file: String,
+
+    /// Placement of the partition files: "reference" to the original files, "hard-link" or "copy" them into the output directory
+    #[clap(long, default_value = "reference")]
+    pub placement: PartitionPlacement,
+
+    /// Merge the candidates instead of the optimal decisions of the inputs
+    #[clap(long)]
+    pub replace_with_candidates: bool,
+
+    /// Re-reduce the optimal decisions of all inputs into a single partition
+    #[clap(long)]
+    pub reduce: bool,
+
+    #[clap(flatten)]
+    pub reduction: OracleReduction,
+
+    /// Log level or filter directives, e.g., "debug" or "info,skypie_lib=trace", overridden by RUST_LOG
+    #[clap(long, default_value = "info")]
+    pub log_level: String,
+
+    /// Log as JSON lines, e.g., for log aggregation
+    #[clap(long)]
+    pub log_json: bool,
+}
+
+/// Oracle to merge
+#[derive(Clone, Debug)]
+pub struct MergeInput {
+    pub stats_file: PathBuf,
+    /// Name of the runs of the oracle in the merged oracle, keeps the original names if not set
+    pub run_name: Option<String>,
+}
+
+impl FromStr for MergeInput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (stats_file, run_name) = match s.rsplit_once(':') {
+            Some((stats_file, run_name)) if !run_name.is_empty() => (stats_file, Some(run_name.to_string())),
+            _ => (s, None),
+        };
+        Ok(MergeInput { stats_file: PathBuf::from(stats_file), run_name })
+    }
+}
+
+/// Dimensions of the decisions of a run, the app regions are only known for partitions with header
+#[derive(Debug)]
+struct DimensionLayout {
+    no_dimensions: Option<i64>,
+    app_regions: Option<Vec<String>>,
+}
+
+impl DimensionLayout {
+    fn of_run(run: &skypie_proto_messages::Run, output_dir: &Path) -> io::Result<Self> {
+        let first_partition = run.optimal_partitions_by_optimizer.values().flat_map(|o| o.optimal_partitions.iter()).next();
+        let header = match first_partition {
+            Some(partition) => load_oracle_header(&output_dir.join(partition))?,
+            None => None,
+        };
+
+        Ok(DimensionLayout {
+            no_dimensions: header.as_ref().map(|h| h.no_dimensions as i64).or(run.no_dimensions),
+            app_regions: header.map(|h| h.app_regions),
+        })
+    }
+
+    fn conflicts_with(&self, other: &Self) -> bool {
+        let conflicting_app_regions = match (&self.app_regions, &other.app_regions) {
+            (Some(a), Some(b)) => a != b,
+            _ => false,
+        };
+        self.no_dimensions != other.no_dimensions || conflicting_app_regions
+    }
+}
+
+/// Identity of an oracle to detect inputs given twice: its provenance, which includes the load time of the inputs,
+/// or its stats file for oracles without provenance
+#[derive(PartialEq)]
+enum OracleIdentity {
+    Provenance(Provenance),
+    StatsFile(PathBuf),
+}
+
+/// Merges the stats of the oracles and places their partitions in the output directory.
+/// Each input gets its own subdirectory of the output directory, since partitions of different oracles have the same names.
+/// Fails if the decisions of the oracles have different dimensions or an oracle is given twice under the same run name,
+/// since its decisions and facets would be counted twice.
+pub fn merge_oracles(inputs: &[MergeInput], output_dir: &Path, placement: PartitionPlacement, replace_with_candidates: bool) -> io::Result<Wrapper> {
+    fs::create_dir_all(output_dir)?;
+
+    let mut merged = Wrapper::default();
+    let mut first_layout: Option<(&Path, DimensionLayout)> = None;
+    let mut merged_oracles = Vec::new();
+
+    for (i, input) in inputs.iter().enumerate() {
+        let _span = info_span!("merge_oracle", stats_file = %input.stats_file.to_string_lossy()).entered();
+
+        let mut wrapper = load_wrapper(&input.stats_file)?;
+        let identity = match &wrapper.provenance {
+            Some(provenance) => OracleIdentity::Provenance(provenance.clone()),
+            None => OracleIdentity::StatsFile(fs::canonicalize(&input.stats_file)?),
+        };
+        let identity = (identity, input.run_name.clone());
+        if merged_oracles.contains(&identity) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                "Oracle {} is given twice with the same run name", input.stats_file.to_string_lossy())));
+        }
+        merged_oracles.push(identity);
+        let original_path = input.stats_file.parent().unwrap_or(Path::new(""));
+        wrapper.relocate(original_path, output_dir, Path::new(&i.to_string()), placement, replace_with_candidates)?;
+
+        if let (Some(run_name), Some(tier_advise)) = (&input.run_name, wrapper.tier_advise.as_mut()) {
+            for setting in tier_advise.replication_factor.values_mut() {
+                let mut renamed = Setting::default();
+                for run in std::mem::take(&mut setting.runs).into_values() {
+                    renamed.combine(&Setting { runs: [(run_name.clone(), run)].into_iter().collect() })?;
+                }
+                *setting = renamed;
+            }
+        }
+
+        for run in wrapper.tier_advise.iter().flat_map(|t| t.replication_factor.values()).flat_map(|s| s.runs.values()) {
+            let layout = DimensionLayout::of_run(run, output_dir)?;
+            match &first_layout {
+                Some((first_file, first)) if first.conflicts_with(&layout) => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                        "Conflicting dimensions of {} ({:?}) and {} ({:?})",
+                        first_file.to_string_lossy(), first, input.stats_file.to_string_lossy(), layout)));
+                },
+                Some(_) => {},
+                None => first_layout = Some((&input.stats_file, layout)),
+            }
+        }
+
+        merged.combine(&wrapper)?;
+        info!("Merged oracle");
+    }
+
+    Ok(merged)
+}
+
+/// Re-reduces the optimal decisions of all runs of the merged oracle into a single partition of the output directory.
+/// Returns the stats of the reduced oracle, see `OracleReduction::save`.
+pub fn reduce_merged(merged: &Wrapper, output_dir: &Path, reduction: &OracleReduction) -> io::Result<Wrapper> {
+    let runs = merged.tier_advise.iter().flat_map(|t| t.replication_factor.values()).flat_map(|s| s.runs.values()).collect_vec();
+    let partitions = merged.optimal_partitions().into_iter().map(|p| output_dir.join(p)).collect_vec();
+    let min_replication_factor = runs.iter().filter_map(|r| r.min_replication_factor).min().unwrap_or(0);
+    let max_replication_factor = runs.iter().filter_map(|r| r.max_replication_factor).max().unwrap_or(0);
+
+    let decisions = load_decisions(partitions.iter().map(|p| p.as_path()).collect(), false)?;
+    let app_regions = app_regions_of(&decisions);
+    let (reduction_time, optimal) = reduction.reduce(decisions);
+
+    let mut reduced = reduction.save(output_dir, &optimal, &app_regions, merged.id_mapping.as_ref(), min_replication_factor..=max_replication_factor, reduction_time)?;
+    reduced.provenance = merged.provenance.clone();
+    Ok(reduced)
+}
+
+/// Merge the oracles of the arguments, optionally re-reduce them, and save the stats in the output directory
+pub fn merge(args: MergeArgs) {
+    let merged = merge_oracles(&args.inputs, &args.output_dir, args.placement, args.replace_with_candidates).unwrap();
+
+    let stats = if args.reduce {
+        reduce_merged(&merged, &args.output_dir, &args.reduction).unwrap()
+    } else {
+        merged
+    };
+
+    let stats_file = args.output_dir.join(&args.output_file);
+    stats.save(stats_file.to_str().unwrap());
+    info!(stats_file = %stats_file.to_string_lossy(), "Saved merged oracle");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skypie_proto_messages::{oracle_container::{self, SCHEMA_VERSION}, Decision, OracleHeader, Scheme};
+
+    /// Oracle with a single partition of one decision in a new directory, returns the stats file
+    fn write_oracle(dir: &Path, replication_factor: u64, app_regions: &[&str]) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let no_dimensions = 3 * app_regions.len() + 4;
+        let header = OracleHeader {
+            schema_version: SCHEMA_VERSION,
+            no_dimensions: no_dimensions as u64,
+            app_regions: app_regions.iter().map(|r| r.to_string()).collect(),
+            object_stores: vec!["aws-eu-west-1-General Purpose".to_string()],
+            ..Default::default()
+        };
+        let mut sink = oracle_container::create(&dir.join("optimal_0.proto.bin"), &header, 1024, 64).unwrap();
+        sink.write(Decision {
+            replication_scheme: Some(Scheme { object_store_ids: vec![0], app_object_store_ids: vec![0; app_regions.len()], ..Default::default() }),
+            cost_wl_halfplane: vec![1.0; no_dimensions],
+            ..Default::default()
+        }).unwrap();
+        sink.flush().unwrap();
+
+        let stats = Wrapper::new(header.object_stores.clone(), vec![], vec!["optimal_0.proto.bin".to_string()], replication_factor,
+            app_regions.len() as i64, no_dimensions as i64, "optimizer".to_string(), "{}".to_string());
+        stats.save(dir.join("stats").to_str().unwrap());
+        dir.join("stats.proto.bin")
+    }
+
+    #[test]
+    fn test_merge_oracles() {
+        let dir = std::env::temp_dir().join(format!("oracle_merge_test_{}", std::process::id()));
+        let input = |stats_file: PathBuf, run_name: Option<&str>| MergeInput { stats_file, run_name: run_name.map(|n| n.to_string()) };
+        let rf1 = write_oracle(&dir.join("rf1"), 1, &["aws-eu-west-1"]);
+        let rf2 = write_oracle(&dir.join("rf2"), 2, &["aws-eu-west-1"]);
+        let rf2_other_chunk = write_oracle(&dir.join("rf2_other_chunk"), 2, &["aws-eu-west-1"]);
+        let other_scenario = write_oracle(&dir.join("other"), 2, &["aws-eu-west-1"]);
+        let conflicting = write_oracle(&dir.join("conflicting"), 2, &["aws-eu-west-1", "aws-us-east-1"]);
+
+        let inputs = vec![input(rf1, None), input(rf2.clone(), None), input(rf2_other_chunk, None), input(other_scenario, Some("other"))];
+        let merged = merge_oracles(&inputs, &dir.join("merged"), PartitionPlacement::Copy, false).unwrap();
+        let tier_advise = merged.tier_advise.unwrap();
+        assert_eq!(tier_advise.replication_factor.keys().sorted().collect_vec(), [&1, &2]);
+        let runs = &tier_advise.replication_factor[&2].runs;
+        assert_eq!(runs.keys().sorted().collect_vec(), ["other", "place_holder"]);
+        // Oracles of the same run are combined, with their partitions in separate directories
+        let optimal = &runs["place_holder"].optimal_partitions_by_optimizer["optimizer"];
+        assert_eq!(optimal.optimal_partitions, ["1/optimal_0.proto.bin", "2/optimal_0.proto.bin"]);
+        assert_eq!(optimal.no_facets, Some(2));
+        assert!(dir.join("merged/3/optimal_0.proto.bin").exists());
+
+        let referenced = merge_oracles(&inputs[..1], &dir.join("referenced"), PartitionPlacement::Reference, false).unwrap();
+        let partition = &referenced.tier_advise.unwrap().replication_factor[&1].runs["place_holder"].optimal_partitions_by_optimizer["optimizer"].optimal_partitions[0];
+        assert!(Path::new(partition).is_absolute());
+
+        let err = merge_oracles(&[inputs[0].clone(), input(conflicting, None)], &dir.join("conflict"), PartitionPlacement::Reference, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // The same oracle twice would count its facets twice
+        let err = merge_oracles(&[input(rf2.clone(), None), input(dir.join("rf2/../rf2/stats.proto.bin"), None)], &dir.join("twice"), PartitionPlacement::Reference, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        // Also as a copy with the same provenance, but not under another run name
+        let mut stats = load_wrapper(&rf2).unwrap();
+        stats.provenance = Some(Provenance { load_timestamp: 42, ..Default::default() });
+        stats.save(dir.join("rf2/stats").to_str().unwrap());
+        fs::create_dir_all(dir.join("rf2_copy")).unwrap();
+        fs::copy(&rf2, dir.join("rf2_copy/stats.proto.bin")).unwrap();
+        fs::copy(dir.join("rf2/optimal_0.proto.bin"), dir.join("rf2_copy/optimal_0.proto.bin")).unwrap();
+        let copy = dir.join("rf2_copy/stats.proto.bin");
+        let err = merge_oracles(&[input(rf2.clone(), None), input(copy.clone(), None)], &dir.join("copy"), PartitionPlacement::Reference, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        merge_oracles(&[input(rf2, None), input(copy, Some("copy"))], &dir.join("renamed_copy"), PartitionPlacement::Reference, false).unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+//! Projection of an oracle onto a subset of its app regions, e.g., for a deployment in few regions from an oracle of many.
+//! The workload of the removed app regions is fixed to zero, so their assignments and coefficients are dropped.
+
+use std::{collections::HashSet, io, path::{Path, PathBuf}};
+
+use clap::Parser;
+use itertools::Itertools;
+use regex::Regex;
+use skypie_proto_messages::{load_optimal_decisions, load_wrapper, Decision, Wrapper};
+use tracing::info;
+
+use crate::{oracle_diff::DecisionKey, oracle_reduction::{app_regions_of, OracleReduction}};
+
+#[derive(Debug, Parser)]
+pub struct ProjectArgs {
+    /// Stats file of the oracle to project
+    #[clap(short, long)]
+    pub input: PathBuf,
+
+    /// Regex selector for the app regions to keep
+    #[clap(short, long)]
+    pub region_selector: String,
+
+    /// Output directory of the projected oracle
+    #[clap(short = 'o', long)]
+    pub output_dir: PathBuf,
+
+    /// Name of the stats file in the output directory, without extension
+    #[clap(long, default_value = "stats")]
+    pub output_file: String,
+
+    #[clap(flatten)]
+    pub reduction: OracleReduction,
+
+    /// Log level or filter directives, e.g., "debug" or "info,skypie_lib=trace", overridden by RUST_LOG
+    #[clap(long, default_value = "info")]
+    pub log_level: String,
+
+    /// Log as JSON lines, e.g., for log aggregation
+    #[clap(long)]
+    pub log_json: bool,
+}
+
+/// Projects the decision onto the app regions at the positions of its assignments
+pub fn project_decision(mut decision: Decision, kept: &[usize]) -> Decision {
+    if let Some(scheme) = decision.replication_scheme.as_mut() {
+        let n = scheme.app_assignments.len();
+        debug_assert_eq!(decision.cost_wl_halfplane.len(), 3 * n + 4);
+
+        scheme.app_assignments = kept.iter().map(|&i| scheme.app_assignments[i].clone()).collect();
+        scheme.app_object_store_ids.clear();
+
+        // Intercept, storage, put, then get, ingress and egress per app region, and the cost
+        let costs = &decision.cost_wl_halfplane;
+        let mut projected = costs[..3].to_vec();
+        for offset in [3, 3 + n, 3 + 2 * n] {
+            projected.extend(kept.iter().map(|&i| costs[offset + i]));
+        }
+        projected.push(costs[3 + 3 * n]);
+        decision.cost_wl_halfplane = projected;
+    }
+    decision
+}
+
+/// Projects the decisions with names onto the app regions and drops duplicates, i.e., decisions with the same write choice and
+/// assignments or the same halfplane. Fails if an app region is not assigned by a decision or its halfplane does not match its assignments.
+pub fn project_decisions(decisions: Vec<Decision>, app_regions: &[String]) -> io::Result<Vec<Decision>> {
+    let mut keys = HashSet::new();
+    let mut halfplanes = HashSet::new();
+    let mut projected = Vec::new();
+
+    for decision in decisions {
+        let apps = decision.replication_scheme.iter().flat_map(|s| s.app_assignments.iter().map(|a| &a.app)).collect_vec();
+        let kept = app_regions.iter()
+            .map(|r| apps.iter().position(|a| *a == r).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Decision does not assign app region {}", r))))
+            .collect::<io::Result<Vec<_>>>()?;
+        if decision.cost_wl_halfplane.len() != 3 * apps.len() + 4 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "Decision has {} coefficients, expected {} for {} app regions", decision.cost_wl_halfplane.len(), 3 * apps.len() + 4, apps.len())));
+        }
+
+        let decision = project_decision(decision, &kept);
+        let halfplane = decision.cost_wl_halfplane.iter().map(|c| c.to_bits()).collect_vec();
+        if keys.insert(DecisionKey::of(&decision)) && halfplanes.insert(halfplane) {
+            projected.push(decision);
+        }
+    }
+
+    Ok(projected)
+}
+
+/// Projects the oracle onto its app regions matching the selector and re-reduces it into the output directory
+pub fn project_oracle(stats_file: &Path, region_selector: &Regex, output_dir: &Path, reduction: &OracleReduction) -> io::Result<Wrapper> {
+    let stats = load_wrapper(stats_file)?;
+    let runs = stats.tier_advise.iter().flat_map(|t| t.replication_factor.values()).flat_map(|s| s.runs.values()).collect_vec();
+    let min_replication_factor = runs.iter().filter_map(|r| r.min_replication_factor).min().unwrap_or(0);
+    let max_replication_factor = runs.iter().filter_map(|r| r.max_replication_factor).max().unwrap_or(0);
+
+    let decisions = load_optimal_decisions(stats_file)?;
+    let app_regions = app_regions_of(&decisions).into_iter().filter(|r| region_selector.is_match(r)).collect_vec();
+    if app_regions.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("No app region of the oracle matches {}", region_selector)));
+    }
+
+    let no_decisions = decisions.len();
+    let projected = project_decisions(decisions, &app_regions)?;
+    info!(no_decisions, no_projected = projected.len(), ?app_regions, "Projected oracle");
+
+    std::fs::create_dir_all(output_dir)?;
+    let (reduction_time, optimal) = reduction.reduce(projected);
+    let mut projected = reduction.save(output_dir, &optimal, &app_regions, stats.id_mapping.as_ref(), min_replication_factor..=max_replication_factor, reduction_time)?;
+    projected.provenance = stats.provenance;
+    Ok(projected)
+}
+
+/// Project the oracle of the arguments and save the stats in the output directory
+pub fn project(args: ProjectArgs) {
+    let region_selector = Regex::new(&args.region_selector).unwrap();
+    let stats = project_oracle(&args.input, &region_selector, &args.output_dir, &args.reduction).unwrap();
+
+    let stats_file = args.output_dir.join(&args.output_file);
+    stats.save(stats_file.to_str().unwrap());
+    info!(stats_file = %stats_file.to_string_lossy(), "Saved projected oracle");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skypie_proto_messages::{Assignment, Scheme};
+    use crate::oracle_reduction::write_partition;
+
+    fn decision(write_choice: &[&str], assignments: [&str; 3], costs: Vec<f64>) -> Decision {
+        Decision {
+            replication_scheme: Some(Scheme {
+                object_stores: write_choice.iter().map(|o| o.to_string()).collect(),
+                app_assignments: ["a", "b", "c"].iter().zip(assignments).map(|(app, o)| Assignment { app: app.to_string(), object_store: o.to_string() }).collect(),
+                ..Default::default()
+            }),
+            cost_wl_halfplane: costs,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_project_decisions() {
+        let costs = (0..13).map(|x| x as f64).collect_vec();
+        let decisions = vec![
+            decision(&["x", "y"], ["x", "y", "x"], costs.clone()),
+            // Same decision for the kept app regions
+            decision(&["x", "y"], ["x", "x", "x"], costs.clone()),
+            decision(&["y"], ["y", "y", "y"], costs.iter().map(|c| c * 2.0).collect()),
+        ];
+
+        let projected = project_decisions(decisions, &["c".to_string(), "a".to_string()]).unwrap();
+        assert_eq!(projected.len(), 2);
+
+        let scheme = projected[0].replication_scheme.as_ref().unwrap();
+        assert_eq!(scheme.object_stores, ["x", "y"]);
+        assert_eq!(scheme.app_assignments.iter().map(|a| a.app.as_str()).collect_vec(), ["c", "a"]);
+        // Intercept, storage, put, get, ingress and egress of "c" and "a", cost
+        assert_eq!(projected[0].cost_wl_halfplane, vec![0.0, 1.0, 2.0, 5.0, 3.0, 8.0, 6.0, 11.0, 9.0, 12.0]);
+
+        let err = project_decisions(projected, &["b".to_string()]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_project_invalid() {
+        let costs = (0..13).map(|x| x as f64).collect_vec();
+        let err = project_decisions(vec![decision(&["x"], ["x", "x", "x"], costs[..12].to_vec())], &["a".to_string()]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let dir = std::env::temp_dir().join(format!("oracle_projection_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let reduction = OracleReduction { batch_size: 10, optimizer: None, use_clarkson: false };
+        let selector = Regex::new("^a$").unwrap();
+        let err = project_oracle(&dir.join("missing.proto.bin"), &selector, &dir.join("projected"), &reduction).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        // Selector without a matching app region
+        let app_regions = ["a".to_string(), "b".to_string(), "c".to_string()];
+        write_partition(&dir.join("optimal.proto.bin"), &[decision(&["x"], ["x", "x", "x"], costs)], &app_regions, None).unwrap();
+        let stats = Wrapper::new(vec!["x".to_string()], vec![], vec!["optimal.proto.bin".to_string()], 1, 3, 13, "optimizer".to_string(), "{}".to_string());
+        stats.save(dir.join("stats").to_str().unwrap());
+        let err = project_oracle(&dir.join("stats.proto.bin"), &Regex::new("^d$").unwrap(), &dir.join("projected"), &reduction).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!dir.join("projected").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+//! Re-reduction of the decisions of existing oracles, e.g., after merging or projecting them
+
+use std::{collections::{BTreeSet, HashMap}, io, ops::RangeInclusive, path::Path, time::{Duration, Instant}};
+
+use itertools::Itertools;
+use skypie_proto_messages::{dimension_names, oracle_container::{self, SCHEMA_VERSION}, Decision, IdMapping, OracleHeader, Wrapper};
+use tracing::info;
+
+use crate::{optimizer_stats::get_optimizer_json, redundancy_elimination::RedundancyElimination};
+
+/// Name of the single partition of a re-reduced oracle
+pub const REDUCED_PARTITION: &str = "optimal.proto.bin";
+/// Name of the single candidate partition of a re-priced oracle
+pub const CANDIDATE_PARTITION: &str = "candidates.proto.bin";
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct OracleReduction {
+    /// Batch size of the re-reduction
+    #[clap(short = 'b', long, default_value_t = 1000)]
+    pub batch_size: usize,
+
+    #[clap(long)]
+    pub optimizer: Option<String>,
+
+    #[clap(long)]
+    pub use_clarkson: bool,
+}
+
+/// Writes the decisions with names as a sealed partition with a header of their app regions and object stores.
+/// With an id mapping, e.g., of the original oracle, the header keeps its ids, else the ids are the positions of the sorted names.
+/// Fails with `InvalidData` if a name is not in the id mapping or the app regions are not in the order of their ids.
+pub fn write_partition(file_name: &Path, decisions: &[Decision], app_regions: &[String], id_mapping: Option<&IdMapping>) -> io::Result<OracleHeader> {
+    let object_stores = decisions.iter()
+        .filter_map(|d| d.replication_scheme.as_ref())
+        .flat_map(|s| s.object_stores.iter().chain(s.app_assignments.iter().map(|a| &a.object_store)))
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    let (object_stores, app_region_ids, object_store_ids) = match id_mapping {
+        Some(id_mapping) => {
+            let id = |kind: &str, ids: &HashMap<String, u32>, name: &String| ids.get(name).copied()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} {} is not in the id mapping", kind, name)));
+            let app_region_ids = app_regions.iter().map(|r| id("App region", &id_mapping.app_regions, r)).collect::<io::Result<Vec<_>>>()?;
+            let (object_store_ids, object_stores) = object_stores.into_iter()
+                .map(|o| Ok((id("Object store", &id_mapping.object_stores, &o)?, o)))
+                .collect::<io::Result<Vec<_>>>()?
+                .into_iter().sorted().unzip();
+            (object_stores, app_region_ids, object_store_ids)
+        }
+        None => (object_stores.into_iter().collect(), vec![], vec![]),
+    };
+    let header = OracleHeader {
+        schema_version: SCHEMA_VERSION,
+        no_dimensions: dimension_names(app_regions).len() as u64,
+        app_regions: app_regions.to_vec(),
+        object_stores,
+        app_region_ids,
+        object_store_ids,
+    };
+    header.validate()?;
+
+    let mut sink = oracle_container::create(file_name, &header, 1024 * 1024, 1024)?;
+    for decision in decisions {
+        sink.write(decision.clone().encode(&header)?)?;
+    }
+    sink.flush()?;
+    oracle_container::seal(file_name)?;
+
+    Ok(header)
+}
+
+/// App regions of the decisions in the order of their assignments
+pub fn app_regions_of(decisions: &[Decision]) -> Vec<String> {
+    decisions.iter()
+        .filter_map(|d| d.replication_scheme.as_ref())
+        .map(|s| s.app_assignments.iter().map(|a| a.app.clone()).collect_vec())
+        .next()
+        .unwrap_or_default()
+}
+
+/// Reduces the decisions in batches, growing the batches until all remaining decisions are reduced in a single batch.
+/// Decisions that are redundant within a batch are redundant in the whole set, so the batches can be reduced independently.
+fn reduce_all(mut decisions: Vec<Decision>, redundancy_elimination: &RedundancyElimination, batch_size: usize) -> Vec<Decision> {
+    let mut batch_size = batch_size.max(1);
+    loop {
+        let no_candidates = decisions.len();
+        let single_batch = no_candidates <= batch_size;
+
+        decisions = decisions.chunks(batch_size)
+            .flat_map(|batch| {
+                let halfplanes = batch.iter().map(|d| d.cost_wl_halfplane.as_slice()).collect_vec();
+                redundancy_elimination.reduce_halfplanes(&halfplanes).into_iter().map(|id| batch[id].clone()).collect_vec()
+            })
+            .collect();
+        info!(no_candidates, no_optimal = decisions.len(), batch_size, "Re-reduced decisions");
+
+        if single_batch {
+            return decisions;
+        }
+        if decisions.len() == no_candidates {
+            batch_size *= 2;
+        }
+    }
+}
+
+impl OracleReduction {
+    /// Optimal decisions of the decisions with names and the time it took to compute them
+    pub fn reduce(&self, decisions: Vec<Decision>) -> (Duration, Vec<Decision>) {
+        let start = Instant::now();
+        let no_app_regions = app_regions_of(&decisions).len();
+        let redundancy_elimination = RedundancyElimination::new(self.batch_size, self.optimizer.clone(), self.use_clarkson, no_app_regions);
+        let optimal = reduce_all(decisions, &redundancy_elimination, self.batch_size);
+
+        (start.elapsed(), optimal)
+    }
+
+    /// Writes the decisions as the single partition of the output directory, see `REDUCED_PARTITION`,
+    /// and returns the stats of the oracle with a single run keyed by the maximal replication factor and the ids of the partition.
+    /// The partition keeps the ids of the id mapping, e.g., of the original oracle, see `write_partition`.
+    pub fn save(&self, output_dir: &Path, decisions: &[Decision], app_regions: &[String], id_mapping: Option<&IdMapping>, replication_factors: RangeInclusive<u64>, reduction_time: Duration) -> io::Result<Wrapper> {
+        let header = write_partition(&output_dir.join(REDUCED_PARTITION), decisions, app_regions, id_mapping)?;
+        let no_dimensions = header.no_dimensions;
+
+        let (optimizer_name, optimizer_type) = get_optimizer_json(self.batch_size, self.optimizer.clone(), self.use_clarkson);
+        let mut stats = Wrapper::new(header.object_stores.clone(), vec![], vec![REDUCED_PARTITION.to_string()], *replication_factors.end(),
+            app_regions.len() as i64, no_dimensions as i64, optimizer_name, optimizer_type);
+        stats.id_mapping = Some(IdMapping::of_header(&header));
+        let partitioner_time_ns = reduction_time.as_nanos() as i64;
+        for run in stats.runs_mut() {
+            run.min_replication_factor = Some(*replication_factors.start());
+            run.no_facets = Some(decisions.len() as i64);
+            run.partitioner_time_ns = Some(partitioner_time_ns);
+            for optimizer in run.optimal_partitions_by_optimizer.values_mut() {
+                optimizer.no_facets = Some(decisions.len() as i64);
+                optimizer.partitioner_time_ns = Some(partitioner_time_ns);
+                optimizer.partitioner_computation_time_ns = Some(partitioner_time_ns);
+            }
+        }
+
+        info!(no_optimal = decisions.len(), ?reduction_time, output_dir = %output_dir.to_string_lossy(), "Saved re-reduced oracle");
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skypie_proto_messages::{load_decisions, Assignment, Scheme};
+
+    #[test]
+    fn test_write_partition() {
+        let file_name = std::env::temp_dir().join(format!("oracle_reduction_test_{}.proto.bin", std::process::id()));
+        let decision = |apps: &[&str]| Decision {
+            replication_scheme: Some(Scheme {
+                object_stores: vec!["x".to_string()],
+                app_assignments: apps.iter().map(|app| Assignment { app: app.to_string(), object_store: "x".to_string() }).collect(),
+                ..Default::default()
+            }),
+            cost_wl_halfplane: vec![1.0; 3 * apps.len() + 4],
+            ..Default::default()
+        };
+        let app_regions = ["a".to_string(), "b".to_string()];
+
+        let header = write_partition(&file_name, &[decision(&["a", "b"])], &app_regions, None).unwrap();
+        assert_eq!(header.object_stores, ["x"]);
+        let decisions = load_decisions(vec![&file_name], false).unwrap();
+        assert_eq!(decisions[0].replication_scheme, decision(&["a", "b"]).replication_scheme.map(|s| Scheme { object_store_ids: vec![0], app_object_store_ids: vec![0, 0], ..s }));
+
+        // Assignments in another order than the app regions of the header
+        let err = write_partition(&file_name, &[decision(&["b", "a"])], &app_regions, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // The ids of the id mapping are kept, including their gaps
+        let id_mapping = IdMapping {
+            app_regions: HashMap::from([("a".to_string(), 3), ("b".to_string(), 7)]),
+            object_stores: HashMap::from([("x".to_string(), 5)]),
+        };
+        let header = write_partition(&file_name, &[decision(&["a", "b"])], &app_regions, Some(&id_mapping)).unwrap();
+        assert_eq!((header.app_region_ids.as_slice(), header.object_store_ids.as_slice()), ([3, 7].as_slice(), [5].as_slice()));
+        assert_eq!(IdMapping::of_header(&header), id_mapping);
+        let decisions = load_decisions(vec![&file_name], false).unwrap();
+        assert_eq!(decisions[0].replication_scheme, decision(&["a", "b"]).replication_scheme.map(|s| Scheme { object_store_ids: vec![5], app_object_store_ids: vec![5, 5], ..s }));
+
+        // Names without id and app regions out of the order of their ids
+        let err = write_partition(&file_name, &[decision(&["a", "b"])], &app_regions, Some(&IdMapping::default())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let id_mapping = IdMapping { app_regions: HashMap::from([("a".to_string(), 7), ("b".to_string(), 3)]), ..id_mapping };
+        let err = write_partition(&file_name, &[decision(&["a", "b"])], &app_regions, Some(&id_mapping)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(file_name).unwrap();
+    }
+}
@@ -0,0 +1,345 @@
+//! Re-pricing of an existing oracle under new price sheets without re-enumerating its write choices.
+//! The scheme of a decision fully determines its cost, so the candidates are re-priced and re-reduced.
+//! Candidates that were never stored, i.e., assignments that were not optimal for any workload under the old prices,
+//! may be optimal under the new prices, in which case the report asks for a full rerun.
+
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, fs, io, path::{Path, PathBuf}};
+
+use clap::Parser;
+use itertools::Itertools;
+use skypie_proto_messages::{load_decisions, load_wrapper, Provenance, Wrapper};
+use tracing::{info, warn};
+
+use crate::{
+    compatibility_checker::CompatibilityChecker, input_format::InputFormat, object_store::ObjectStore, opt_assignments::opt_assignments,
+    oracle_reduction::{app_regions_of, write_partition, OracleReduction, CANDIDATE_PARTITION}, read_choice::ReadChoice,
+    stats::object_store_name, ApplicationRegion, Decision, Loader, WriteChoice,
+};
+
+#[derive(Debug, Parser)]
+pub struct RepriceArgs {
+    /// Stats file of the oracle to re-price
+    #[clap(short, long)]
+    pub input: PathBuf,
+
+    /// Network cost file with the new prices
+    #[clap(short, long)]
+    pub network_file: PathBuf,
+
+    /// Storage pricing file with the new prices
+    #[clap(short = 's', long)]
+    pub object_store_file: PathBuf,
+
+    /// JSON file with the column names, naming rules and region aliases of the price files, see `InputFormat`
+    #[clap(long)]
+    pub input_format: Option<PathBuf>,
+
+    /// Regex selector for the regions to load, must match all app regions and object store regions of the oracle
+    #[clap(long, default_value = "")]
+    pub region_selector: String,
+
+    /// Regex selector for the object stores to load, must match all object stores of the oracle
+    #[clap(long, default_value = "")]
+    pub object_store_selector: String,
+
+    /// Latency file of the latency SLO of the oracle, if any
+    #[clap(long)]
+    pub latency_file: Option<PathBuf>,
+
+    /// Latency SLO of the oracle, if any
+    #[clap(long)]
+    pub latency_slo: Option<f64>,
+
+    /// Output directory of the re-priced oracle
+    #[clap(short = 'o', long)]
+    pub output_dir: PathBuf,
+
+    /// Name of the stats file in the output directory, without extension
+    #[clap(long, default_value = "stats")]
+    pub output_file: String,
+
+    #[clap(flatten)]
+    pub reduction: OracleReduction,
+
+    /// Log level or filter directives, e.g., "debug" or "info,skypie_lib=trace", overridden by RUST_LOG
+    #[clap(long, default_value = "info")]
+    pub log_level: String,
+
+    /// Log as JSON lines, e.g., for log aggregation
+    #[clap(long)]
+    pub log_json: bool,
+}
+
+/// App regions and object stores with new prices, by their names in oracles
+pub struct Prices {
+    app_regions: HashMap<String, ApplicationRegion>,
+    object_stores: HashMap<String, ObjectStore>,
+}
+
+impl Prices {
+    pub fn new(app_regions: &[ApplicationRegion], object_stores: &[ObjectStore]) -> Self {
+        Prices {
+            app_regions: app_regions.iter().map(|r| (r.region.name.clone(), r.clone())).collect(),
+            object_stores: object_stores.iter().map(|o| (object_store_name(o), o.clone())).collect(),
+        }
+    }
+
+    fn app_region(&self, name: &str) -> io::Result<&ApplicationRegion> {
+        self.app_regions.get(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("App region {} is not in the price sheets", name)))
+    }
+
+    fn object_store(&self, name: &str) -> io::Result<&ObjectStore> {
+        self.object_stores.get(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Object store {} is not in the price sheets", name)))
+    }
+
+    fn write_choice(&self, object_stores: &[String]) -> io::Result<WriteChoice> {
+        let object_stores = object_stores.iter().map(|o| self.object_store(o).cloned()).collect::<io::Result<_>>()?;
+        Ok(WriteChoice { object_stores })
+    }
+
+    /// Decision with the new prices of the decision with names, app regions in the order of its assignments
+    pub fn decision(&self, decision: &skypie_proto_messages::Decision) -> io::Result<Decision> {
+        let scheme = decision.replication_scheme.as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Decision without replication scheme"))?;
+        let read_choice = scheme.app_assignments.iter()
+            .map(|a| Ok((self.app_region(&a.app)?.clone(), self.object_store(&a.object_store)?.clone())))
+            .collect::<io::Result<ReadChoice>>()?;
+
+        Ok(Decision { write_choice: self.write_choice(&scheme.object_stores)?, read_choice })
+    }
+}
+
+/// Replaces the halfplanes of the decisions with names by their halfplanes under the new prices
+pub fn reprice_decisions(decisions: Vec<skypie_proto_messages::Decision>, prices: &Prices) -> io::Result<Vec<skypie_proto_messages::Decision>> {
+    decisions.into_iter()
+        .map(|mut decision| {
+            decision.cost_wl_halfplane = prices.decision(&decision)?.get_halfplane_ineq();
+            Ok(decision)
+        })
+        .collect()
+}
+
+/// Assignment that is optimal for some workload under the new prices but not among the candidates of its write choice
+#[derive(Clone, Debug, PartialEq)]
+pub struct MissingAssignment {
+    pub write_choice: Vec<String>,
+    pub app: String,
+    pub object_store: String,
+}
+
+/// Assignments of the write choices of the candidates that are optimal under the new prices but not among the candidates.
+/// The enumeration only keeps the assignments that are optimal for some workload, so the missing ones were never stored.
+pub fn missing_assignments(candidates: &[skypie_proto_messages::Decision], prices: &Prices, compatibility_checker: &Box<dyn CompatibilityChecker>) -> io::Result<Vec<MissingAssignment>> {
+    // Assigned object stores by write choice and app region
+    let mut assigned: BTreeMap<Vec<String>, BTreeMap<&str, BTreeSet<&str>>> = BTreeMap::new();
+    for scheme in candidates.iter().filter_map(|d| d.replication_scheme.as_ref()) {
+        let by_app = assigned.entry(scheme.object_stores.iter().sorted().cloned().collect()).or_default();
+        for assignment in &scheme.app_assignments {
+            by_app.entry(&assignment.app).or_default().insert(&assignment.object_store);
+        }
+    }
+
+    let mut missing = Vec::new();
+    for (write_choice, by_app) in assigned {
+        let object_stores = prices.write_choice(&write_choice)?;
+        for (app, object_stores_of_app) in by_app {
+            let region = prices.app_region(app)?;
+            for (object_store, _) in opt_assignments(Box::new(object_stores.clone()), region, compatibility_checker.as_ref()) {
+                let object_store = object_store_name(&object_store);
+                if !object_stores_of_app.contains(object_store.as_str()) {
+                    missing.push(MissingAssignment { write_choice: write_choice.clone(), app: app.to_string(), object_store });
+                }
+            }
+        }
+    }
+
+    Ok(missing)
+}
+
+/// Outcome of re-pricing an oracle
+#[derive(Debug, Default)]
+pub struct RepriceReport {
+    pub no_candidates: usize,
+    pub no_optimal: usize,
+    /// The oracle has no candidate partitions, so only its previously optimal decisions were re-priced
+    pub only_optimal_decisions: bool,
+    pub missing_assignments: Vec<MissingAssignment>,
+    /// The oracle was precomputed with pruned unread replicas and durability constraints or a write latency SLO
+    pub pruned_with_constraints: bool,
+}
+
+impl RepriceReport {
+    /// Whether decisions that are not in the re-priced candidates may be optimal under the new prices.
+    /// Decisions pruned for unread replicas are only dominated by the smaller write choice if no constraint or write SLO excludes it.
+    pub fn requires_rerun(&self) -> bool {
+        self.only_optimal_decisions || !self.missing_assignments.is_empty() || self.pruned_with_constraints
+    }
+}
+
+/// Whether the oracle pruned unread replicas together with constraints that can exclude the smaller write choice,
+/// see `Args::unread_replica_pruning`. Distinct regions hold for any smaller write choice.
+fn pruned_with_constraints(stats: &Wrapper) -> bool {
+    let provenance = stats.provenance.clone().unwrap_or_default();
+    let pruned = provenance.prune_unread_replicas
+        || stats.runs().any(|r| r.no_pruned_write_choices.unwrap_or(0) > 0 || r.no_pruned_candidates.unwrap_or(0) > 0);
+    let constrained = provenance.write_latency_slo.is_some()
+        || provenance.write_choice_constraints.iter().any(|c| c != "--distinct-regions");
+    pruned && constrained
+}
+
+/// Re-prices the candidates of the oracle, or its optimal decisions if it has no candidates, and re-reduces them into the output directory.
+/// The re-priced candidates are saved as well, see `CANDIDATE_PARTITION`, so that the re-priced oracle can be re-priced again.
+/// The stats keep the provenance and ids of the original oracle.
+pub fn reprice_oracle(stats_file: &Path, prices: &Prices, compatibility_checker: &Box<dyn CompatibilityChecker>, output_dir: &Path, reduction: &OracleReduction) -> io::Result<(Wrapper, RepriceReport)> {
+    let stats = load_wrapper(stats_file)?;
+    let min_replication_factor = stats.runs().filter_map(|r| r.min_replication_factor).min().unwrap_or(0);
+    let max_replication_factor = stats.runs().filter_map(|r| r.max_replication_factor).max().unwrap_or(0);
+
+    let candidate_partitions = stats.candidate_partitions();
+    let only_optimal_decisions = candidate_partitions.is_empty();
+    let partitions = if only_optimal_decisions { stats.optimal_partitions() } else { candidate_partitions };
+    let stats_dir = stats_file.parent().unwrap_or(Path::new(""));
+    let partitions = partitions.into_iter().map(|p| stats_dir.join(p)).collect_vec();
+
+    let candidates = load_decisions(partitions.iter().map(|p| p.as_path()).collect(), false)?;
+    let app_regions = app_regions_of(&candidates);
+    let missing_assignments = missing_assignments(&candidates, prices, compatibility_checker)?;
+    let candidates = reprice_decisions(candidates, prices)?;
+
+    fs::create_dir_all(output_dir)?;
+    let (reduction_time, optimal) = reduction.reduce(candidates.clone());
+    let mut repriced = reduction.save(output_dir, &optimal, &app_regions, stats.id_mapping.as_ref(), min_replication_factor..=max_replication_factor, reduction_time)?;
+    if !only_optimal_decisions {
+        write_partition(&output_dir.join(CANDIDATE_PARTITION), &candidates, &app_regions, stats.id_mapping.as_ref())?;
+        for run in repriced.runs_mut() {
+            run.candidate_partitions = vec![CANDIDATE_PARTITION.to_string()];
+        }
+    }
+
+    let pruned_with_constraints = pruned_with_constraints(&stats);
+    repriced.provenance = stats.provenance;
+    let report = RepriceReport { no_candidates: candidates.len(), no_optimal: optimal.len(), only_optimal_decisions, missing_assignments, pruned_with_constraints };
+    Ok((repriced, report))
+}
+
+/// Re-price the oracle of the arguments, report whether a full rerun is needed, and save the stats in the output directory
+pub fn reprice(args: RepriceArgs) {
+    let input_format = InputFormat::from_optional_file(&args.input_format).unwrap();
+    let loader = Loader::with_input_format(&args.network_file, &args.object_store_file, &args.region_selector, &args.object_store_selector, &args.latency_file, &args.latency_slo, &input_format, None);
+    let prices = Prices::new(&loader.app_regions, &loader.object_stores);
+
+    let (mut stats, report) = reprice_oracle(&args.input, &prices, &loader.compatibility_checker_slos, &args.output_dir, &args.reduction).unwrap();
+    // The re-priced oracle is produced by the new price inputs, with the write SLOs and write choices of the original oracle
+    let original = stats.provenance.take().unwrap_or_default();
+    stats.provenance = Some(Provenance {
+        write_latency_slo: original.write_latency_slo,
+        write_quorum: original.write_quorum,
+        prune_unread_replicas: original.prune_unread_replicas,
+        write_choice_constraints: original.write_choice_constraints,
+        ..loader.provenance.clone()
+    });
+    info!(no_candidates = report.no_candidates, no_optimal = report.no_optimal, "Re-priced oracle");
+
+    if report.only_optimal_decisions {
+        warn!("The oracle has no candidates, previously redundant decisions may be optimal under the new prices, a full rerun is needed");
+    }
+    if report.pruned_with_constraints {
+        warn!("The oracle pruned unread replicas together with durability constraints or a write latency SLO, pruned decisions may be optimal under the new prices");
+    }
+    for missing in &report.missing_assignments {
+        warn!(write_choice = ?missing.write_choice, app = %missing.app, object_store = %missing.object_store, "Assignment is optimal under the new prices but was pruned");
+    }
+    if report.requires_rerun() {
+        warn!(no_missing_assignments = report.missing_assignments.len(), "Re-priced oracle may miss optimal decisions, a full rerun is needed");
+    }
+
+    let stats_file = args.output_dir.join(&args.output_file);
+    stats.save(stats_file.to_str().unwrap());
+    info!(stats_file = %stats_file.to_string_lossy(), "Saved re-priced oracle");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skypie_proto_messages::{Assignment, Scheme};
+
+    use crate::{compatibility_checker::DefaultCompatibilityChecker, network_record::NetworkCostMap, object_store::{Cost, ObjectStoreStruct}, Region};
+
+    fn object_store(id: u16, name: &str, region: &Region, get_cost: f64, egress_cost: f64) -> ObjectStore {
+        let mut cost = Cost::new(get_cost, "get request");
+        cost.add_egress_costs(NetworkCostMap::from_iter([(region.clone(), egress_cost)]));
+        cost.add_ingress_costs(NetworkCostMap::from_iter([(region.clone(), 0.0)]));
+        ObjectStore::new(ObjectStoreStruct { id, cost, region: region.clone(), name: name.to_string() })
+    }
+
+    #[test]
+    fn test_reprice() {
+        let region = Region { id: 0, name: "aws-eu-west-1".to_string() };
+        let app_region = ApplicationRegion {
+            region: region.clone(),
+            egress_cost: NetworkCostMap::from_iter([(region.clone(), 0.0)]),
+            ingress_cost: NetworkCostMap::from_iter([(region.clone(), 0.0)]),
+        };
+        // Cheap gets or cheap egress, each is optimal for some workload
+        let object_stores = [object_store(0, "a", &region, 10.0, 1.0), object_store(1, "b", &region, 2.0, 2.0)];
+        let prices = Prices::new(&[app_region], &object_stores);
+
+        // Under the old prices, only "b" was optimal for the write choice of both object stores
+        let candidate = skypie_proto_messages::Decision {
+            replication_scheme: Some(Scheme {
+                object_stores: vec!["aws-eu-west-1-a".to_string(), "aws-eu-west-1-b".to_string()],
+                app_assignments: vec![Assignment { app: "aws-eu-west-1".to_string(), object_store: "aws-eu-west-1-b".to_string() }],
+                ..Default::default()
+            }),
+            cost_wl_halfplane: vec![0.0; 7],
+            ..Default::default()
+        };
+
+        let compatibility_checker: Box<dyn CompatibilityChecker> = Box::new(DefaultCompatibilityChecker {});
+        let missing = missing_assignments(std::slice::from_ref(&candidate), &prices, &compatibility_checker).unwrap();
+        assert_eq!(missing, [MissingAssignment {
+            write_choice: vec!["aws-eu-west-1-a".to_string(), "aws-eu-west-1-b".to_string()],
+            app: "aws-eu-west-1".to_string(),
+            object_store: "aws-eu-west-1-a".to_string(),
+        }]);
+
+        let repriced = reprice_decisions(vec![candidate.clone()], &prices).unwrap();
+        let expected = prices.decision(&candidate).unwrap().get_halfplane_ineq();
+        assert_eq!(repriced[0].cost_wl_halfplane, expected);
+        // Get cost of "b" for the app region
+        assert_eq!(repriced[0].cost_wl_halfplane[3], 2.0);
+
+        let mut unknown = candidate;
+        unknown.replication_scheme.as_mut().unwrap().object_stores.push("aws-eu-west-1-c".to_string());
+        let err = reprice_decisions(vec![unknown], &prices).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_pruned_with_constraints() {
+        let stats = |provenance: Provenance, no_pruned_write_choices: i64| {
+            let mut stats = Wrapper::new(vec![], vec![], vec![], 2, 1, 7, "optimizer".to_string(), "{}".to_string());
+            stats.provenance = Some(provenance);
+            for run in stats.runs_mut() {
+                run.no_pruned_write_choices = Some(no_pruned_write_choices);
+            }
+            stats
+        };
+        let pruned = Provenance { prune_unread_replicas: true, ..Default::default() };
+        let min_vendors = vec!["--min-vendors 2".to_string()];
+
+        assert!(!pruned_with_constraints(&stats(pruned.clone(), 0)));
+        assert!(!pruned_with_constraints(&stats(Provenance { write_choice_constraints: vec!["--distinct-regions".to_string()], ..pruned.clone() }, 0)));
+        assert!(pruned_with_constraints(&stats(Provenance { write_choice_constraints: min_vendors.clone(), ..pruned.clone() }, 0)));
+        assert!(pruned_with_constraints(&stats(Provenance { write_latency_slo: Some(100.0), ..pruned }, 0)));
+        // Constraints without pruning, and oracles without the flag in their provenance that pruned write choices
+        assert!(!pruned_with_constraints(&stats(Provenance { write_choice_constraints: min_vendors.clone(), ..Default::default() }, 0)));
+        assert!(pruned_with_constraints(&stats(Provenance { write_choice_constraints: min_vendors, ..Default::default() }, 3)));
+
+        let report = RepriceReport { pruned_with_constraints: true, ..Default::default() };
+        assert!(report.requires_rerun());
+        assert!(!RepriceReport::default().requires_rerun());
+    }
+}
@@ -0,0 +1,279 @@
+//! Import of downloaded provider price lists into the object store and network cost files of the loader.
+//! The vendor specific parsing is in `price_import_aws`, `price_import_azure` and `price_import_gcp`,
+//! prices that none of them can map are reported as unmapped SKUs instead of being dropped silently.
+
+use std::{collections::{BTreeSet, HashMap}, io, path::{Path, PathBuf}};
+
+use clap::Parser;
+use tracing::{info, warn};
+
+use crate::{
+    network_record::NetworkRecordRaw, object_store::ObjectStoreStructRaw,
+    price_import_aws::import_aws, price_import_azure::import_azure, price_import_gcp::import_gcp,
+    region_catalogue::{Continent, REGION_CATALOGUE},
+};
+
+#[derive(Debug, Parser)]
+pub struct ImportArgs {
+    /// AWS price list JSON of Amazon S3, can be repeated, e.g., one file per region
+    #[clap(long)]
+    pub aws: Vec<PathBuf>,
+
+    /// Azure retail prices JSON of storage and bandwidth, can be repeated, e.g., one file per page
+    #[clap(long)]
+    pub azure: Vec<PathBuf>,
+
+    /// GCP Cloud Billing SKU JSON of Cloud Storage, can be repeated, e.g., one file per page
+    #[clap(long)]
+    pub gcp: Vec<PathBuf>,
+
+    /// Output object store file in the format of `storage_pricing.csv`
+    #[clap(short = 's', long)]
+    pub object_store_file: PathBuf,
+
+    /// Output network file in the format of `network_cost_v2.csv`
+    #[clap(short = 'n', long)]
+    pub network_file: PathBuf,
+
+    /// Output CSV file of the SKUs that could not be mapped
+    #[clap(long)]
+    pub unmapped_file: Option<PathBuf>,
+
+    /// Log level or filter directives, e.g., "debug" or "info,skypie_lib=trace", overridden by RUST_LOG
+    #[clap(long, default_value = "info")]
+    pub log_level: String,
+
+    /// Log as JSON lines, e.g., for log aggregation
+    #[clap(long)]
+    pub log_json: bool,
+}
+
+/// Destination of an egress price
+#[derive(Clone, Debug, PartialEq)]
+pub enum EgressDestination {
+    /// Region of the same or another vendor
+    Region { vendor: String, region: String },
+    /// Any other region of the same vendor on the continent
+    Continent(Continent),
+    /// Internet egress, i.e., to regions of other vendors
+    Internet,
+}
+
+/// Price per GB of transfers out of the region of the vendor
+#[derive(Clone, Debug, PartialEq)]
+pub struct EgressPrice {
+    pub vendor: String,
+    pub region: String,
+    pub destination: EgressDestination,
+    pub cost: f64,
+}
+
+/// Price list entry that could not be mapped to an object store or network price
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct UnmappedSku {
+    pub vendor: String,
+    pub sku: String,
+    pub description: String,
+    pub reason: String,
+}
+
+/// Volume tier of a price, ranges and price in the unit, e.g., GB
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriceTier {
+    pub start: f64,
+    pub end: f64,
+    pub price: f64,
+    pub unit: String,
+}
+
+/// Price of the first tier that is not free, e.g., after the free 100 GB of internet egress
+pub fn first_paid_tier(tiers: &[PriceTier]) -> Option<f64> {
+    tiers.iter()
+        .filter(|t| t.price > 0.0)
+        .min_by(|a, b| a.start.total_cmp(&b.start))
+        .or_else(|| tiers.first())
+        .map(|t| t.price)
+}
+
+/// Prices imported from the price lists of one or more vendors
+#[derive(Debug, Default)]
+pub struct PriceImport {
+    pub object_stores: Vec<ObjectStoreStructRaw>,
+    pub egress: Vec<EgressPrice>,
+    pub unmapped: Vec<UnmappedSku>,
+}
+
+impl PriceImport {
+    pub fn extend(&mut self, other: PriceImport) {
+        self.object_stores.extend(other.object_stores);
+        self.egress.extend(other.egress);
+        self.unmapped.extend(other.unmapped);
+    }
+
+    pub(crate) fn unmapped(&mut self, vendor: &str, sku: &str, description: &str, reason: &str) {
+        self.unmapped.push(UnmappedSku { vendor: vendor.to_string(), sku: sku.to_string(), description: description.to_string(), reason: reason.to_string() });
+    }
+
+    /// Rows of an object store price in all its volume tiers
+    pub(crate) fn object_store_price(&mut self, vendor: &str, region: &str, name: &str, group: &str, tier: &str, tiers: &[PriceTier]) {
+        for price_tier in tiers {
+            self.object_stores.push(ObjectStoreStructRaw {
+                vendor: vendor.to_string(),
+                region: region.to_string(),
+                name: name.to_string(),
+                group: group.to_string(),
+                tier: tier.to_string(),
+                starting_range: Some(price_tier.start),
+                ending_range: Some(price_tier.end),
+                unit: price_tier.unit.clone(),
+                price_per_unit: price_tier.price,
+            });
+        }
+    }
+
+    /// Network costs between all pairs of regions with object stores or egress prices.
+    /// A region pair takes the price of the pair, else the price for the continent of the destination if both have the same vendor,
+    /// else the internet egress price of the source. Pairs without price are skipped and counted as missing.
+    pub(crate) fn network_records(&self) -> (Vec<NetworkRecordRaw>, usize) {
+        let mut regions = BTreeSet::new();
+        regions.extend(self.object_stores.iter().map(|o| (o.vendor.clone(), o.region.clone())));
+        for egress in &self.egress {
+            regions.insert((egress.vendor.clone(), egress.region.clone()));
+            if let EgressDestination::Region { vendor, region } = &egress.destination {
+                regions.insert((vendor.clone(), region.clone()));
+            }
+        }
+
+        let mut by_source: HashMap<(&str, &str), Vec<&EgressPrice>> = HashMap::new();
+        for egress in &self.egress {
+            by_source.entry((egress.vendor.as_str(), egress.region.as_str())).or_default().push(egress);
+        }
+
+        let mut records = Vec::new();
+        let mut missing = 0;
+        for (src_vendor, src_region) in &regions {
+            let prices = by_source.get(&(src_vendor.as_str(), src_region.as_str())).map(|p| p.as_slice()).unwrap_or_default();
+            for (dest_vendor, dest_region) in &regions {
+                if src_vendor == dest_vendor && src_region == dest_region {
+                    continue;
+                }
+
+                let dest_continent = REGION_CATALOGUE.continent(dest_vendor, dest_region);
+                let price = |matches: &dyn Fn(&EgressDestination) -> bool| prices.iter().find(|p| matches(&p.destination)).map(|p| p.cost);
+                let cost = price(&|d| matches!(d, EgressDestination::Region { vendor, region } if vendor == dest_vendor && region == dest_region))
+                    .or_else(|| if src_vendor == dest_vendor {
+                        price(&|d| matches!(d, EgressDestination::Continent(c) if Some(*c) == dest_continent))
+                    } else {
+                        None
+                    })
+                    .or_else(|| price(&|d| *d == EgressDestination::Internet));
+
+                match cost {
+                    Some(cost) => records.push(NetworkRecordRaw {
+                        src_vendor: src_vendor.clone(), src_region: src_region.clone(), dest_vendor: dest_vendor.clone(), dest_region: dest_region.clone(), cost,
+                    }),
+                    None => missing += 1,
+                }
+            }
+        }
+
+        (records, missing)
+    }
+}
+
+pub(crate) fn invalid_data<E: std::fmt::Display>(path: &Path, e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.to_string_lossy(), e))
+}
+
+fn write_csv<T: serde::Serialize>(path: &Path, rows: &[T]) -> io::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for row in rows {
+        writer.serialize(row).map_err(|e| invalid_data(path, e))?;
+    }
+    writer.flush()
+}
+
+/// Imports the price lists of the arguments and writes the object store, network and unmapped SKU files
+pub fn import(args: ImportArgs) {
+    let mut prices = PriceImport::default();
+    for (vendor, paths, import_vendor) in [("aws", &args.aws, import_aws as fn(&Path) -> io::Result<PriceImport>), ("azure", &args.azure, import_azure), ("gcp", &args.gcp, import_gcp)] {
+        for path in paths {
+            let imported = import_vendor(path).unwrap();
+            info!(vendor, file = %path.to_string_lossy(), no_object_store_prices = imported.object_stores.len(), no_egress_prices = imported.egress.len(), no_unmapped = imported.unmapped.len(), "Imported price list");
+            prices.extend(imported);
+        }
+    }
+
+    let (network, no_missing) = prices.network_records();
+    if no_missing > 0 {
+        warn!(no_missing, "Region pairs without network price, the loader skips their regions");
+    }
+    if !prices.unmapped.is_empty() {
+        warn!(no_unmapped = prices.unmapped.len(), "SKUs could not be mapped");
+    }
+
+    write_csv(&args.object_store_file, &prices.object_stores).unwrap();
+    write_csv(&args.network_file, &network).unwrap();
+    if let Some(unmapped_file) = &args.unmapped_file {
+        write_csv(unmapped_file, &prices.unmapped).unwrap();
+    }
+    info!(no_object_store_prices = prices.object_stores.len(), no_network_costs = network.len(), "Wrote price files");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn egress(vendor: &str, region: &str, destination: EgressDestination, cost: f64) -> EgressPrice {
+        EgressPrice { vendor: vendor.to_string(), region: region.to_string(), destination, cost }
+    }
+
+    #[test]
+    fn test_network_records() {
+        let prices = PriceImport {
+            egress: vec![
+                egress("aws", "us-east-1", EgressDestination::Region { vendor: "aws".to_string(), region: "us-east-2".to_string() }, 0.01),
+                egress("aws", "us-east-1", EgressDestination::Internet, 0.09),
+                egress("gcp", "us-east1", EgressDestination::Continent(Continent::NorthAmerica), 0.02),
+                egress("gcp", "us-east1", EgressDestination::Continent(Continent::Europe), 0.05),
+                egress("gcp", "europe-west1", EgressDestination::Internet, 0.12),
+            ],
+            ..Default::default()
+        };
+
+        let (records, missing) = prices.network_records();
+        let cost = |src: &str, dest: &str| records.iter()
+            .find(|r| format!("{}-{}", r.src_vendor, r.src_region) == src && format!("{}-{}", r.dest_vendor, r.dest_region) == dest)
+            .map(|r| r.cost);
+        assert_eq!(cost("aws-us-east-1", "aws-us-east-2"), Some(0.01));
+        assert_eq!(cost("aws-us-east-1", "gcp-us-east1"), Some(0.09));
+        assert_eq!(cost("gcp-us-east1", "gcp-europe-west1"), Some(0.05));
+        // No price for other vendors
+        assert_eq!(cost("gcp-us-east1", "aws-us-east-1"), None);
+        assert_eq!(cost("gcp-europe-west1", "gcp-us-east1"), Some(0.12));
+        // All from aws-us-east-2, and from gcp-us-east1 to the other vendor
+        assert_eq!(missing, 3 + 2);
+    }
+
+    #[test]
+    fn test_import_invalid_files() {
+        let dir = std::env::temp_dir().join(format!("price_import_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        let truncated = write("truncated.json", "{\"products\": {");
+        let wrong_schema = write("wrong_schema.json", "{\"products\": 1, \"Items\": 1, \"skus\": 1}");
+
+        let imports: [fn(&Path) -> io::Result<PriceImport>; 3] = [import_aws, import_azure, import_gcp];
+        for import in imports {
+            assert_eq!(import(&dir.join("missing.json")).unwrap_err().kind(), io::ErrorKind::NotFound);
+            assert_eq!(import(&truncated).unwrap_err().kind(), io::ErrorKind::InvalidData);
+            assert_eq!(import(&wrong_schema).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
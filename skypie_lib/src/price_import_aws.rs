@@ -0,0 +1,161 @@
+//! Import of the AWS price list of Amazon S3, i.e., the offer file of the AWS Price List Bulk API.
+
+use std::{collections::{BTreeMap, HashMap}, fs::File, io::{self, BufReader}, path::Path};
+
+use serde::Deserialize;
+
+use crate::price_import::{first_paid_tier, invalid_data, EgressDestination, EgressPrice, PriceImport, PriceTier};
+
+const VENDOR: &str = "aws";
+const NAME: &str = "s3";
+
+#[derive(Deserialize)]
+struct Offer {
+    products: BTreeMap<String, Product>,
+    terms: Terms,
+}
+
+#[derive(Deserialize)]
+struct Product {
+    #[serde(rename = "productFamily", default)]
+    product_family: String,
+    #[serde(default)]
+    attributes: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct Terms {
+    #[serde(rename = "OnDemand", default)]
+    on_demand: HashMap<String, HashMap<String, Term>>,
+}
+
+#[derive(Deserialize)]
+struct Term {
+    #[serde(rename = "priceDimensions")]
+    price_dimensions: HashMap<String, PriceDimension>,
+}
+
+#[derive(Deserialize)]
+struct PriceDimension {
+    unit: String,
+    #[serde(rename = "beginRange")]
+    begin_range: String,
+    #[serde(rename = "endRange")]
+    end_range: String,
+    #[serde(rename = "pricePerUnit")]
+    price_per_unit: HashMap<String, String>,
+}
+
+/// Group and tier of the S3 usage types, without the region prefix of the usage type, e.g., "EUW1-".
+/// One Zone-Infrequent Access is named "Non-Critical Data", as in `storage_pricing.csv`.
+const USAGE_TYPES: &[(&str, &str, &str)] = &[
+    ("TimedStorage-ByteHrs", "storage", "General Purpose"),
+    ("TimedStorage-SIA-ByteHrs", "storage", "Infrequent Access"),
+    ("TimedStorage-ZIA-ByteHrs", "storage", "Non-Critical Data"),
+    ("Requests-Tier1", "put request", "General Purpose"),
+    ("Requests-Tier2", "get request", "General Purpose"),
+    ("Requests-SIA-Tier1", "put request", "Infrequent Access"),
+    ("Requests-SIA-Tier2", "get request", "Infrequent Access"),
+    ("Requests-ZIA-Tier1", "put request", "Non-Critical Data"),
+    ("Requests-ZIA-Tier2", "get request", "Non-Critical Data"),
+    ("Retrieval-SIA", "get transfer", "Infrequent Access"),
+    ("Retrieval-ZIA", "get transfer", "Non-Critical Data"),
+];
+
+fn usage_type(usage_type: &str) -> Option<(&'static str, &'static str)> {
+    USAGE_TYPES.iter()
+        .find(|(u, _, _)| usage_type == *u || usage_type.ends_with(&format!("-{}", u)))
+        .map(|(_, group, tier)| (*group, *tier))
+}
+
+/// Imports the storage, request and data transfer prices of an S3 offer file, prices in USD
+pub fn import_aws(path: &Path) -> io::Result<PriceImport> {
+    let offer: Offer = serde_json::from_reader(BufReader::new(File::open(path)?)).map_err(|e| invalid_data(path, e))?;
+
+    let mut import = PriceImport::default();
+    for (sku, product) in &offer.products {
+        let attribute = |name: &str| product.attributes.get(name).map(|a| a.as_str()).unwrap_or_default();
+        let description = format!("{} {}", product.product_family, attribute("usagetype"));
+
+        let mut tiers = Vec::new();
+        for dimension in offer.terms.on_demand.get(sku).into_iter().flat_map(|t| t.values()).flat_map(|t| t.price_dimensions.values()) {
+            let parse = |value: &str| value.parse::<f64>().map_err(|e| invalid_data(path, format!("SKU {}: {}", sku, e)));
+            let Some(price) = dimension.price_per_unit.get("USD") else {
+                continue;
+            };
+            tiers.push(PriceTier { start: parse(&dimension.begin_range)?, end: parse(&dimension.end_range)?, price: parse(price)?, unit: dimension.unit.clone() });
+        }
+        tiers.sort_by(|a, b| a.start.total_cmp(&b.start));
+        if tiers.is_empty() {
+            import.unmapped(VENDOR, sku, &description, "no on-demand price in USD");
+            continue;
+        }
+
+        if product.product_family == "Data Transfer" {
+            let destination = match attribute("transferType") {
+                "AWS Outbound" => EgressDestination::Internet,
+                "InterRegion Outbound" => EgressDestination::Region { vendor: VENDOR.to_string(), region: attribute("toRegionCode").to_string() },
+                _ => {
+                    import.unmapped(VENDOR, sku, &description, "unsupported transfer type");
+                    continue;
+                }
+            };
+            import.egress.push(EgressPrice {
+                vendor: VENDOR.to_string(),
+                region: attribute("fromRegionCode").to_string(),
+                destination,
+                cost: first_paid_tier(&tiers).unwrap_or_default(),
+            });
+            continue;
+        }
+
+        match usage_type(attribute("usagetype")) {
+            Some((group, tier)) => import.object_store_price(VENDOR, attribute("regionCode"), NAME, group, tier, &tiers),
+            None => import.unmapped(VENDOR, sku, &description, "unsupported usage type"),
+        }
+    }
+
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_aws() {
+        let import = import_aws(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/aws_s3_offer.json"))).unwrap();
+
+        let storage = import.object_stores.iter().filter(|o| o.group == "storage" && o.tier == "General Purpose").collect::<Vec<_>>();
+        assert_eq!(storage.len(), 2);
+        assert_eq!((storage[0].region.as_str(), storage[0].price_per_unit, storage[0].ending_range), ("eu-west-1", 0.023, Some(51200.0)));
+        assert_eq!(storage[1].ending_range, Some(f64::INFINITY));
+        assert!(import.object_stores.iter().any(|o| o.group == "get request" && o.tier == "Infrequent Access" && o.price_per_unit == 1e-6));
+
+        assert_eq!(import.egress, vec![
+            EgressPrice { vendor: "aws".to_string(), region: "eu-west-1".to_string(), destination: EgressDestination::Internet, cost: 0.09 },
+            EgressPrice { vendor: "aws".to_string(), region: "eu-west-1".to_string(), destination: EgressDestination::Region { vendor: "aws".to_string(), region: "us-east-1".to_string() }, cost: 0.02 },
+        ]);
+
+        assert_eq!(import.unmapped.len(), 1);
+        assert_eq!(import.unmapped[0].sku, "SKU5");
+    }
+
+    #[test]
+    fn test_import_aws_invalid_price() {
+        let path = std::env::temp_dir().join(format!("aws_invalid_price_test_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{
+            "products": { "SKU1": { "productFamily": "Storage", "attributes": { "usagetype": "EUW1-TimedStorage-ZIA-ByteHrs", "regionCode": "eu-west-1" } } },
+            "terms": { "OnDemand": { "SKU1": { "SKU1.T": { "priceDimensions": {
+                "SKU1.T.D": { "unit": "GB-Mo", "beginRange": "0", "endRange": "Inf", "pricePerUnit": { "USD": "0.01" } } } } } } }
+        }"#).unwrap();
+        let import = import_aws(&path).unwrap();
+        assert_eq!((import.object_stores[0].group.as_str(), import.object_stores[0].tier.as_str()), ("storage", "Non-Critical Data"));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, content.replace("\"0.01\"", "\"$0.01\"")).unwrap();
+        assert_eq!(import_aws(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
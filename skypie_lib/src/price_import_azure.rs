@@ -0,0 +1,144 @@
+//! Import of the Azure retail prices of Blob Storage and bandwidth, i.e., the pages of the Azure Retail Prices API.
+
+use std::{collections::BTreeMap, fs::File, io::{self, BufReader}, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    price_import::{first_paid_tier, invalid_data, EgressDestination, EgressPrice, PriceImport, PriceTier},
+    region_catalogue::{Continent, REGION_CATALOGUE},
+};
+
+const VENDOR: &str = "azure";
+
+#[derive(Deserialize)]
+struct RetailPrices {
+    #[serde(rename = "Items")]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    retail_price: f64,
+    #[serde(default)]
+    tier_minimum_units: f64,
+    #[serde(default)]
+    arm_region_name: String,
+    meter_name: String,
+    product_name: String,
+    sku_name: String,
+    service_name: String,
+    unit_of_measure: String,
+    sku_id: String,
+    #[serde(rename = "type")]
+    price_type: String,
+}
+
+/// Groups of the storage meters by the suffix of their names, e.g., "Hot LRS Write Operations"
+const METERS: &[(&str, &str)] = &[
+    ("Data Stored", "storage"),
+    ("Write Operations", "put request"),
+    ("Read Operations", "get request"),
+    ("Data Retrieval", "get transfer"),
+    ("Data Write", "put transfer"),
+];
+
+/// Number of units the price is for, e.g., 10000 for "10K"
+fn unit_quantity(unit_of_measure: &str) -> Option<f64> {
+    let number = unit_of_measure.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect::<String>();
+    let quantity = number.parse::<f64>().ok()?;
+    match unit_of_measure[number.len()..].chars().next() {
+        Some('K') => Some(quantity * 1e3),
+        Some('M') => Some(quantity * 1e6),
+        _ => Some(quantity),
+    }
+}
+
+/// Imports the storage, operation and bandwidth prices of locally redundant Blob Storage, prices in the currency of the file
+pub fn import_azure(path: &Path) -> io::Result<PriceImport> {
+    let prices: RetailPrices = serde_json::from_reader(BufReader::new(File::open(path)?)).map_err(|e| invalid_data(path, e))?;
+
+    let mut import = PriceImport::default();
+    // Volume tiers of a meter are separate items
+    let mut meters: BTreeMap<(&str, &str, &str, &str), Vec<&Item>> = BTreeMap::new();
+    for item in &prices.items {
+        let description = format!("{} {} {}", item.product_name, item.meter_name, item.arm_region_name);
+        if item.price_type != "Consumption" {
+            import.unmapped(VENDOR, &item.sku_id, &description, "not a consumption price");
+        } else if item.service_name == "Storage" && !item.sku_name.ends_with(" LRS") {
+            import.unmapped(VENDOR, &item.sku_id, &description, "unsupported redundancy");
+        } else if item.arm_region_name.is_empty() || REGION_CATALOGUE.continent(VENDOR, &item.arm_region_name).is_none() {
+            import.unmapped(VENDOR, &item.sku_id, &description, "unknown region");
+        } else {
+            meters.entry((&item.arm_region_name, &item.product_name, &item.sku_name, &item.meter_name)).or_default().push(item);
+        }
+    }
+
+    for ((region, product_name, sku_name, meter_name), mut items) in meters {
+        items.sort_by(|a, b| a.tier_minimum_units.total_cmp(&b.tier_minimum_units));
+        let (sku_id, description) = (&items[0].sku_id, format!("{} {} {}", product_name, meter_name, region));
+        let Some(quantity) = unit_quantity(&items[0].unit_of_measure) else {
+            import.unmapped(VENDOR, sku_id, &description, "unsupported unit of measure");
+            continue;
+        };
+        let unit = if meter_name.ends_with("Operations") { "requests" } else { items[0].unit_of_measure.as_str() };
+        let tiers = items.iter().enumerate().map(|(i, item)| PriceTier {
+            start: item.tier_minimum_units,
+            end: items.get(i + 1).map_or(f64::INFINITY, |next| next.tier_minimum_units),
+            price: item.retail_price / quantity,
+            unit: unit.to_string(),
+        }).collect::<Vec<_>>();
+
+        let egress = |destination| EgressPrice { vendor: VENDOR.to_string(), region: region.to_string(), destination, cost: first_paid_tier(&tiers).unwrap_or_default() };
+        if items[0].service_name == "Bandwidth" {
+            // Known region by the filter above
+            let continent = REGION_CATALOGUE.continent(VENDOR, region).unwrap();
+            if meter_name.contains("Intra Continent") {
+                import.egress.push(egress(EgressDestination::Continent(continent)));
+            } else if meter_name.contains("Inter Continent") {
+                import.egress.extend(Continent::ALL.into_iter().filter(|c| *c != continent).map(|c| egress(EgressDestination::Continent(c))));
+            } else if meter_name.ends_with("Data Transfer Out") {
+                import.egress.push(egress(EgressDestination::Internet));
+            } else {
+                import.unmapped(VENDOR, sku_id, &description, "unsupported bandwidth meter");
+            }
+            continue;
+        }
+
+        let tier = sku_name.split_whitespace().next().unwrap_or_default();
+        match METERS.iter().find(|(suffix, _)| meter_name.ends_with(suffix)) {
+            Some((_, group)) => import.object_store_price(VENDOR, region, product_name, group, tier, &tiers),
+            None => import.unmapped(VENDOR, sku_id, &description, "unsupported storage meter"),
+        }
+    }
+
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_azure() {
+        let import = import_azure(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/azure_retail_prices.json"))).unwrap();
+
+        let storage = import.object_stores.iter().filter(|o| o.group == "storage").collect::<Vec<_>>();
+        assert_eq!(storage.len(), 2);
+        assert_eq!((storage[0].name.as_str(), storage[0].tier.as_str(), storage[0].ending_range), ("Blob Storage", "Hot", Some(51200.0)));
+        assert_eq!(storage[1].starting_range, Some(51200.0));
+        let put = import.object_stores.iter().find(|o| o.group == "put request").unwrap();
+        assert!((put.price_per_unit - 5.4e-6).abs() < 1e-12);
+
+        let internet = import.egress.iter().filter(|e| e.destination == EgressDestination::Internet).collect::<Vec<_>>();
+        assert_eq!(internet.len(), 1);
+        assert_eq!(internet[0].cost, 0.087);
+        assert!(import.egress.contains(&EgressPrice { vendor: "azure".to_string(), region: "westeurope".to_string(), destination: EgressDestination::Continent(Continent::Europe), cost: 0.02 }));
+        assert!(import.egress.contains(&EgressPrice { vendor: "azure".to_string(), region: "westeurope".to_string(), destination: EgressDestination::Continent(Continent::Asia), cost: 0.05 }));
+
+        let mut reasons = import.unmapped.iter().map(|u| u.reason.as_str()).collect::<Vec<_>>();
+        reasons.sort();
+        assert_eq!(reasons, vec!["not a consumption price", "unsupported redundancy", "unsupported storage meter"]);
+    }
+}
@@ -0,0 +1,229 @@
+//! Import of the GCP prices of Cloud Storage and network egress, i.e., the pages of the SKUs of the Cloud Billing Catalog API.
+
+use std::{fs::File, io::{self, BufReader}, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    price_import::{first_paid_tier, invalid_data, EgressDestination, EgressPrice, PriceImport, PriceTier},
+    region_catalogue::Continent,
+};
+
+const VENDOR: &str = "gcp";
+const NAME: &str = "gcs";
+
+#[derive(Deserialize)]
+struct Skus {
+    skus: Vec<Sku>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Sku {
+    sku_id: String,
+    description: String,
+    category: Category,
+    #[serde(default)]
+    service_regions: Vec<String>,
+    #[serde(default)]
+    pricing_info: Vec<PricingInfo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Category {
+    #[serde(default)]
+    resource_family: String,
+    #[serde(default)]
+    usage_type: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PricingInfo {
+    pricing_expression: PricingExpression,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PricingExpression {
+    usage_unit: String,
+    tiered_rates: Vec<TieredRate>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TieredRate {
+    #[serde(default)]
+    start_usage_amount: f64,
+    unit_price: Money,
+}
+
+#[derive(Deserialize)]
+struct Money {
+    #[serde(default)]
+    units: String,
+    #[serde(default)]
+    nanos: i64,
+}
+
+impl Money {
+    fn value(&self) -> Option<f64> {
+        let units = if self.units.is_empty() { 0.0 } else { self.units.parse::<f64>().ok()? };
+        Some(units + self.nanos as f64 / 1e9)
+    }
+}
+
+/// Gigabytes per gibibyte, GCP prices data by GiB while the cost files are per GB
+const GB_PER_GIB: f64 = 1.073741824;
+
+/// GB per usage unit and the unit of the cost files, None for unsupported units
+fn usage_unit(unit: &str) -> Option<(f64, &'static str)> {
+    match unit {
+        "GiBy.mo" => Some((GB_PER_GIB, "GB-Month")),
+        "GiBy" => Some((GB_PER_GIB, "GB")),
+        "count" => Some((1.0, "")),
+        _ => None,
+    }
+}
+
+const STORAGE_CLASSES: &[&str] = &["Standard", "Nearline", "Coldline", "Archive"];
+
+/// Group of a Cloud Storage SKU by its description, e.g., "Regional Standard Class A Operations"
+fn group(description: &str) -> Option<&'static str> {
+    if description.contains("Class A Operations") {
+        Some("put request")
+    } else if description.contains("Class B Operations") {
+        Some("get request")
+    } else if description.contains("Data Retrieval") {
+        Some("get transfer")
+    } else if description.contains("Storage") {
+        Some("storage")
+    } else {
+        None
+    }
+}
+
+/// Destination of a network SKU by its description, e.g., "Network Inter Region Data Transfer Out from Belgium to Asia Pacific"
+fn destination(description: &str) -> Option<EgressDestination> {
+    if description.contains("Internet") {
+        Some(EgressDestination::Internet)
+    } else if let Some((_, geography)) = description.rsplit_once(" within ").or_else(|| description.rsplit_once(" to ")) {
+        Continent::of_geography(geography).map(EgressDestination::Continent)
+    } else {
+        None
+    }
+}
+
+/// Imports the regional storage, operation, retrieval and egress prices of a SKU list, prices in the currency of the file.
+/// Prices and volume tiers by GiB are converted to GB, SKUs of other usage units are unmapped.
+pub fn import_gcp(path: &Path) -> io::Result<PriceImport> {
+    let skus: Skus = serde_json::from_reader(BufReader::new(File::open(path)?)).map_err(|e| invalid_data(path, e))?;
+
+    let mut import = PriceImport::default();
+    for sku in &skus.skus {
+        let lowercase = sku.description.to_lowercase();
+        if sku.category.usage_type != "OnDemand" {
+            import.unmapped(VENDOR, &sku.sku_id, &sku.description, "not an on-demand price");
+            continue;
+        }
+        if lowercase.contains("multi-region") || lowercase.contains("dual-region") {
+            import.unmapped(VENDOR, &sku.sku_id, &sku.description, "unsupported location");
+            continue;
+        }
+        let Some(expression) = sku.pricing_info.first().map(|p| &p.pricing_expression) else {
+            import.unmapped(VENDOR, &sku.sku_id, &sku.description, "no pricing info");
+            continue;
+        };
+        let Some(prices) = expression.tiered_rates.iter().map(|r| r.unit_price.value()).collect::<Option<Vec<_>>>() else {
+            return Err(invalid_data(path, format!("SKU {}: invalid unit price", sku.sku_id)));
+        };
+        let tiers = usage_unit(&expression.usage_unit).map(|(gb, unit)| {
+            expression.tiered_rates.iter().zip(prices).enumerate().map(|(i, (rate, price))| PriceTier {
+                start: rate.start_usage_amount * gb,
+                end: expression.tiered_rates.get(i + 1).map_or(f64::INFINITY, |next| next.start_usage_amount * gb),
+                price: price / gb,
+                unit: unit.to_string(),
+            }).collect::<Vec<_>>()
+        });
+
+        match sku.category.resource_family.as_str() {
+            "Network" => match (destination(&sku.description), tiers) {
+                (Some(destination), Some(tiers)) => import.egress.extend(sku.service_regions.iter().map(|region| EgressPrice {
+                    vendor: VENDOR.to_string(), region: region.clone(), destination: destination.clone(), cost: first_paid_tier(&tiers).unwrap_or_default(),
+                })),
+                (Some(_), None) => import.unmapped(VENDOR, &sku.sku_id, &sku.description, "unsupported usage unit"),
+                (None, _) => import.unmapped(VENDOR, &sku.sku_id, &sku.description, "unsupported network destination"),
+            },
+            "Storage" => match (group(&sku.description), tiers) {
+                (Some(_), None) => import.unmapped(VENDOR, &sku.sku_id, &sku.description, "unsupported usage unit"),
+                (Some(group), Some(tiers)) => {
+                    let class = STORAGE_CLASSES.iter().find(|c| sku.description.contains(*c)).unwrap_or(&STORAGE_CLASSES[0]);
+                    let tier = format!("{} Storage", class);
+                    for region in &sku.service_regions {
+                        import.object_store_price(VENDOR, region, NAME, group, &tier, &tiers);
+                    }
+                }
+                (None, _) => import.unmapped(VENDOR, &sku.sku_id, &sku.description, "unsupported storage SKU"),
+            },
+            _ => import.unmapped(VENDOR, &sku.sku_id, &sku.description, "unsupported resource family"),
+        }
+    }
+
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_gcp() {
+        let import = import_gcp(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/gcp_skus.json"))).unwrap();
+
+        let storage = import.object_stores.iter().find(|o| o.group == "storage").unwrap();
+        assert_eq!((storage.region.as_str(), storage.name.as_str(), storage.tier.as_str(), storage.unit.as_str()), ("europe-west1", "gcs", "Standard Storage", "GB-Month"));
+        // Price per GiB converted to GB
+        assert!((storage.price_per_unit - 0.02 / GB_PER_GIB).abs() < 1e-12);
+        // Class A operations of all regions of the SKU
+        let put = import.object_stores.iter().filter(|o| o.group == "put request").collect::<Vec<_>>();
+        assert_eq!(put.len(), 2);
+        assert!((put[0].price_per_unit - 5e-6).abs() < 1e-12);
+        assert!(import.object_stores.iter().any(|o| o.group == "get transfer" && o.tier == "Nearline Storage"));
+
+        assert_eq!(import.egress, vec![
+            EgressPrice { vendor: "gcp".to_string(), region: "europe-west1".to_string(), destination: EgressDestination::Continent(Continent::Europe), cost: 0.02 / GB_PER_GIB },
+            EgressPrice { vendor: "gcp".to_string(), region: "europe-west1".to_string(), destination: EgressDestination::Continent(Continent::Asia), cost: 0.08 / GB_PER_GIB },
+            EgressPrice { vendor: "gcp".to_string(), region: "europe-west1".to_string(), destination: EgressDestination::Internet, cost: 0.12 / GB_PER_GIB },
+        ]);
+
+        let reasons = import.unmapped.iter().map(|u| u.reason.as_str()).collect::<Vec<_>>();
+        assert_eq!(reasons, vec!["unsupported location", "unsupported storage SKU"]);
+    }
+
+    #[test]
+    fn test_import_gcp_invalid_units() {
+        let path = std::env::temp_dir().join(format!("gcp_invalid_units_test_{}.json", std::process::id()));
+        let skus = |usage_unit: &str, units: &str| format!(r#"{{ "skus": [{{
+            "skuId": "SKU1", "description": "Standard Storage Belgium",
+            "category": {{ "resourceFamily": "Storage", "usageType": "OnDemand" }},
+            "serviceRegions": ["europe-west1"],
+            "pricingInfo": [{{ "pricingExpression": {{ "usageUnit": "{}", "tieredRates": [{{ "startUsageAmount": 0, "unitPrice": {{ "units": "{}", "nanos": 0 }} }}] }} }}]
+        }}] }}"#, usage_unit, units);
+
+        std::fs::write(&path, skus("GiBy.mo", "1")).unwrap();
+        let import = import_gcp(&path).unwrap();
+        assert!((import.object_stores[0].price_per_unit - 1.0 / GB_PER_GIB).abs() < 1e-12);
+
+        // Prices per TiB are not converted
+        std::fs::write(&path, skus("TiBy.mo", "1")).unwrap();
+        let import = import_gcp(&path).unwrap();
+        assert!(import.object_stores.is_empty());
+        assert_eq!(import.unmapped[0].reason, "unsupported usage unit");
+
+        std::fs::write(&path, skus("GiBy.mo", "one")).unwrap();
+        assert_eq!(import_gcp(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+use std::{collections::HashMap, fs::File, path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use hydroflow::serde_json;
+use serde::{Deserialize, Serialize};
+
+use crate::log_entry::SkyPieLogEntryType;
+
+/// Snapshot of the progress of a precomputation, polled by dashboards from the experiment directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgressReport {
+    pub write_choices_total: u64,
+    /// Write choices of completed chunks, including write choices skipped by constraints
+    pub write_choices_done: u64,
+    pub candidates: u64,
+    pub optimal: u64,
+    pub fraction_done: f64,
+    /// Throughput since the previous report
+    pub write_choices_per_second: f64,
+    pub candidates_per_second: f64,
+    pub elapsed_seconds: f64,
+    /// Remaining time at the average throughput of this run, None before any progress
+    pub eta_seconds: Option<f64>,
+}
+
+impl ProgressReport {
+    pub fn file_name(experiment_name: &str) -> PathBuf {
+        PathBuf::from(format!("{}/progress.json", experiment_name))
+    }
+
+    /// Save the report atomically, so that readers never see a partial file
+    pub fn save(&self, file_name: &Path) {
+        let tmp_file_name = file_name.with_extension("json.tmp");
+        let file = File::create(&tmp_file_name).unwrap();
+        serde_json::to_writer(&file, &self).unwrap();
+        std::fs::rename(&tmp_file_name, file_name).unwrap();
+    }
+
+    pub fn print(&self) {
+        let eta = self.eta_seconds.map_or("unknown".to_string(), |eta| format!("{:?}", Duration::from_secs_f64(eta)));
+        tracing::info!(write_choices_done = self.write_choices_done, write_choices_total = self.write_choices_total, fraction_done = self.fraction_done,
+            write_choices_per_second = self.write_choices_per_second, candidates = self.candidates,
+            candidates_per_second = self.candidates_per_second, optimal = self.optimal, %eta, "Progress");
+    }
+}
+
+/// Derives throughput and ETA from the accumulated counts of the precomputation
+pub struct Progress {
+    total: u64,
+    interval: Duration,
+    start: Instant,
+    // Write choices done before this run, e.g., when resuming
+    initial_done: u64,
+    // Time, write choices and candidates of the previous report
+    last: Option<(Instant, u64, u64)>,
+}
+
+impl Progress {
+    /// Progress of a run starting with `initial_done` write choices, e.g., processed before resuming
+    pub fn new(total: u64, initial_done: u64, interval: Duration) -> Self {
+        Progress { total, interval, start: Instant::now(), initial_done, last: None }
+    }
+
+    pub fn report(&mut self, counts: &HashMap<SkyPieLogEntryType, usize>) -> ProgressReport {
+        let now = Instant::now();
+        let count = |entry_type| *counts.get(&entry_type).unwrap_or(&0) as u64;
+        let done = count(SkyPieLogEntryType::WriteChoiceCount);
+        let candidates = count(SkyPieLogEntryType::CandidateCount);
+        let optimal = count(SkyPieLogEntryType::OptimalCount);
+
+        let initial_done = self.initial_done;
+        let elapsed = (now - self.start).as_secs_f64();
+
+        let (last_time, last_done, last_candidates) = self.last.unwrap_or((self.start, initial_done, 0));
+        let since_last = (now - last_time).as_secs_f64();
+        let rate = |current: u64, previous: u64| if since_last > 0.0 { current.saturating_sub(previous) as f64 / since_last } else { 0.0 };
+        self.last = Some((now, done, candidates));
+
+        let done_in_run = done.saturating_sub(initial_done);
+        let eta_seconds = if done_in_run > 0 {
+            Some(self.total.saturating_sub(done) as f64 * elapsed / done_in_run as f64)
+        } else {
+            None
+        };
+
+        ProgressReport {
+            write_choices_total: self.total,
+            write_choices_done: done,
+            candidates,
+            optimal,
+            fraction_done: if self.total > 0 { done as f64 / self.total as f64 } else { 1.0 },
+            write_choices_per_second: rate(done, last_done),
+            candidates_per_second: rate(candidates, last_candidates),
+            elapsed_seconds: elapsed,
+            eta_seconds,
+        }
+    }
+
+    /// Report if the interval passed since the previous report or all write choices are done
+    pub fn report_if_due(&mut self, counts: &HashMap<SkyPieLogEntryType, usize>) -> Option<ProgressReport> {
+        let due = self.last.map_or(true, |(last_time, _, _)| last_time.elapsed() >= self.interval)
+            || *counts.get(&SkyPieLogEntryType::WriteChoiceCount).unwrap_or(&0) as u64 >= self.total;
+
+        if due { Some(self.report(counts)) } else { None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use crate::log_entry::SkyPieLogEntryType;
+
+    use super::Progress;
+
+    #[test]
+    fn test_progress_eta() {
+        // Resumed with 20 write choices done before
+        let mut progress = Progress::new(100, 20, Duration::from_secs(3600));
+        let mut counts = HashMap::from([(SkyPieLogEntryType::WriteChoiceCount, 20)]);
+        let report = progress.report_if_due(&counts).unwrap();
+        assert_eq!(report.write_choices_done, 20);
+        assert_eq!(report.eta_seconds, None);
+        assert!(progress.report_if_due(&counts).is_none());
+
+        std::thread::sleep(Duration::from_millis(10));
+        counts.insert(SkyPieLogEntryType::WriteChoiceCount, 60);
+        counts.insert(SkyPieLogEntryType::CandidateCount, 1000);
+        let report = progress.report(&counts);
+        assert_eq!(report.fraction_done, 0.6);
+        assert!(report.write_choices_per_second > 0.0);
+        // 40 write choices left at the throughput of 40 write choices in the elapsed time
+        let eta = report.eta_seconds.unwrap();
+        assert!((eta - report.elapsed_seconds).abs() < 1e-9);
+
+        counts.insert(SkyPieLogEntryType::WriteChoiceCount, 100);
+        assert_eq!(progress.report_if_due(&counts).unwrap().eta_seconds, Some(0.0));
+    }
+
+    #[test]
+    fn test_progress_baseline() {
+        // The first report already includes progress of this run beyond the 20 write choices done before resuming
+        let mut progress = Progress::new(100, 20, Duration::from_secs(3600));
+        std::thread::sleep(Duration::from_millis(10));
+        let report = progress.report(&HashMap::from([(SkyPieLogEntryType::WriteChoiceCount, 60)]));
+        assert!(report.write_choices_per_second > 0.0);
+        assert!(report.eta_seconds.is_some());
+    }
+}
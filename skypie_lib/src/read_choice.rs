@@ -26,12 +26,18 @@ impl<K> ReadChoiceVec<K>
 where
     K: Default + Identifier<u16> + PartialEq + std::clone::Clone,
 {
+    /// Empty read choice for the number of app regions.
+    /// The read choices are ordered by the ids of the app regions, which can have gaps, e.g., with an id catalogue.
     pub fn new(num_apps: usize) -> ReadChoiceVec<K> {
         ReadChoiceVec {
-            read_choices: vec![(K::default(), ObjectStore::default()); num_apps],
+            read_choices: Vec::with_capacity(num_apps),
         }
     }
 
+    fn position(&self, key: &K) -> Result<usize, usize> {
+        self.read_choices.binary_search_by_key(&key.get_id(), |(k, _)| k.get_id())
+    }
+
     pub fn len(&self) -> usize {
         self.read_choices.len()
     }
@@ -41,19 +47,19 @@ where
     }
 
     pub fn contains_key(&self, key: &K) -> bool {
-        let pos = key.get_id() as usize;
-        return self.read_choices[pos].0 == *key;
+        self.position(key).is_ok_and(|pos| self.read_choices[pos].0 == *key)
     }
 
+    /// Object store read by the app region, the default object store if the app region has no read choice
     pub fn get(&self, key: &K) -> ObjectStore {
-        let pos = key.get_id() as usize;
-        return self.read_choices[pos].1.clone();
+        self.position(key).map_or_else(|_| ObjectStore::default(), |pos| self.read_choices[pos].1.clone())
     }
 
     pub fn insert(&mut self, key: K, value: ObjectStore) {
-        let pos = key.get_id() as usize;
-        debug_assert!(pos < self.read_choices.len(), "ID {} out-of-bounds!", pos);
-        self.read_choices[pos] = (key, value);
+        match self.position(&key) {
+            Ok(pos) => self.read_choices[pos] = (key, value),
+            Err(pos) => self.read_choices.insert(pos, (key, value)),
+        }
     }
 
     pub fn is_empty(&self) -> bool {
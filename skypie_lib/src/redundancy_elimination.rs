@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use numpy::{ndarray::Dim, PyArray};
+use pyo3::{Python, PyAny, Py, types::{PyModule, PyDict}};
+use tracing::{debug, error, info_span};
+
+use crate::Decision;
+
+/// Redundancy elimination of a batch of decisions via the python bridge
+pub struct RedundancyElimination {
+    fun: Py<PyAny>,
+    // Clarkson's algorithm cannot handle the case of fewer decisions than dimensions
+    no_dimensions: usize,
+}
+
+impl RedundancyElimination {
+    pub fn new(batch_size: usize, optimizer: Option<String>, use_clarkson: bool, no_app_regions: usize) -> Self {
+        let no_dimensions = if use_clarkson {Decision::get_no_dimensions(no_app_regions)} else {0};
+
+        let module = "";
+        let fun_name = "redundancy_elimination";
+        let code = include_str!("python_redundancy_bridge.py");
+        let fun = Python::with_gil(|py| {
+
+            // Load python code as module
+            let module = PyModule::from_code(py, code, "", module).unwrap();
+            // Load arguments via python function "load_args"
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("dsize", batch_size).unwrap();
+            kwargs.set_item("use_clarkson", use_clarkson).unwrap();
+            if let Some(optimizer) = optimizer {
+                kwargs.set_item("optimizer", optimizer).unwrap();
+            }
+            let res = module.call_method("load_args", (), Some(kwargs));
+            if let Err(e) = res {
+                error!(%e, "Error in load_args");
+            }
+
+            // Get reference to python function for redundancy elimination
+            let fun: Py<PyAny> =
+                module.getattr(fun_name)
+                .unwrap()
+                .into();
+            fun
+        });
+
+        RedundancyElimination { fun, no_dimensions }
+    }
+
+    /// Optimal decisions of the batch and the time it took to compute them
+    pub fn reduce(&self, decisions: Vec<Decision>) -> (Duration, Vec<Decision>) {
+        let no_candidates = decisions.len();
+        let _span = info_span!("reduce_batch", no_candidates).entered();
+
+        // Start time of computing optimal decisions
+        let start = std::time::Instant::now();
+
+        let optimal = if decisions.len() < self.no_dimensions {
+            decisions
+        }
+        else {
+
+            // Convert batch of decisions to numpy array
+            let py_array = Decision::to_inequalities_numpy(&decisions);
+
+            // Computing optimal decisions by row IDs in vector
+            let res = self.optimal_ids(py_array);
+
+            // Extract optimal decisions by ids in res
+            let mut optimal = Vec::with_capacity(res.len());
+            for id in res {
+                optimal.push(decisions[id].clone());
+            }
+
+            optimal
+        };
+
+        // End time of computing optimal decisions
+        let end = std::time::Instant::now();
+        let duration = end - start;
+
+        let no_optimal = optimal.len();
+        debug!(no_optimal, no_redundant = no_candidates - no_optimal, ?duration, "Reduced batch");
+
+        (duration, optimal)
+    }
+
+    /// Ids of the optimal halfplanes, e.g., the `cost_wl_halfplane` of decisions loaded from oracle files
+    pub fn reduce_halfplanes(&self, halfplanes: &[&[f64]]) -> Vec<usize> {
+        if halfplanes.len() < self.no_dimensions {
+            return (0..halfplanes.len()).collect();
+        }
+
+        let dims = [halfplanes.len(), halfplanes.first().map_or(0, |h| h.len())];
+        let ineqs = halfplanes.iter().flat_map(|h| h.iter().copied()).collect::<Vec<f64>>();
+        debug_assert_eq!(ineqs.len(), dims[0] * dims[1]);
+
+        let py_array = Python::with_gil(|py| PyArray::from_vec(py, ineqs).reshape(dims).unwrap().to_owned());
+        self.optimal_ids(py_array)
+    }
+
+    fn optimal_ids(&self, py_array: Py<PyArray<f64, Dim<[usize; 2]>>>) -> Vec<usize> {
+        Python::with_gil(|py| {
+            let py_res = self.fun.call(py, (py_array,), None).unwrap();
+            py_res.extract(py).unwrap()
+        })
+    }
+}
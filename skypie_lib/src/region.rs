@@ -3,6 +3,7 @@ use std::hash::Hash;
 use serde::{Serialize, Deserialize};
 
 use super::identifier::Identifier;
+use crate::region_catalogue::{Continent, RegionInfo, REGION_CATALOGUE};
 
 
 #[derive(Clone, Debug, Deserialize, Serialize, Hash, PartialEq, Eq, Ord, PartialOrd)]
@@ -11,6 +12,26 @@ pub struct Region {
     pub name: String,
 }
 
+impl Region {
+    /// Catalogue entry of the region, None for regions that are not in the region catalogue
+    pub fn info(&self) -> Option<&'static RegionInfo> {
+        REGION_CATALOGUE.get(&self.name)
+    }
+
+    /// Vendor of the region by the region catalogue, else the prefix of the name before the first "-", e.g., "aws" for "aws-us-east-1"
+    pub fn vendor(&self) -> &str {
+        match self.info() {
+            Some(info) => &info.vendor,
+            None => self.name.split('-').next().unwrap_or(""),
+        }
+    }
+
+    /// Continent of the region by the region catalogue
+    pub fn continent(&self) -> Option<Continent> {
+        self.info().map(|info| info.continent)
+    }
+}
+
 impl Identifier<u16> for Region {
     fn get_id(self: &Self) -> u16 {
         self.id
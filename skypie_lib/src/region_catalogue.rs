@@ -0,0 +1,222 @@
+//! Catalogue of the regions of all vendors, with their canonical names, aliases, display names, continents and coordinates.
+//! The canonical name of a region is the name of the price files, e.g., "aws-ca-central-1", other data sources name it differently,
+//! e.g., "aws:ca-central-1" in raw latency measurements. The loader resolves all names to canonical names, so oracles only contain canonical names.
+
+use std::{collections::{HashMap, HashSet}, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// Continent of a region, e.g., for prices that providers charge by geography instead of by region pair
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum Continent {
+    NorthAmerica,
+    SouthAmerica,
+    Europe,
+    Asia,
+    Oceania,
+    MiddleEast,
+    Africa,
+}
+
+impl Continent {
+    pub const ALL: [Continent; 7] = [Continent::NorthAmerica, Continent::SouthAmerica, Continent::Europe, Continent::Asia, Continent::Oceania, Continent::MiddleEast, Continent::Africa];
+
+    /// Continent of the region of the vendor by its naming scheme, None for unknown names, see `RegionCatalogue::continent` for regions of the catalogue
+    pub fn of_region(vendor: &str, region: &str) -> Option<Self> {
+        let prefixes: &[(&str, Continent)] = match vendor {
+            "aws" => &[("ap-southeast-2", Continent::Oceania), ("ap-southeast-4", Continent::Oceania), ("us-", Continent::NorthAmerica), ("ca-", Continent::NorthAmerica),
+                ("mx-", Continent::NorthAmerica), ("sa-", Continent::SouthAmerica), ("eu-", Continent::Europe), ("ap-", Continent::Asia),
+                ("me-", Continent::MiddleEast), ("il-", Continent::MiddleEast), ("af-", Continent::Africa)],
+            "gcp" => &[("us-", Continent::NorthAmerica), ("northamerica-", Continent::NorthAmerica), ("southamerica-", Continent::SouthAmerica),
+                ("europe-", Continent::Europe), ("asia-", Continent::Asia), ("australia-", Continent::Oceania), ("me-", Continent::MiddleEast),
+                ("africa-", Continent::Africa)],
+            // Azure names have no prefix, e.g., "westeurope", check the more specific keywords first, "australia" contains "us"
+            "azure" => return [("australia", Continent::Oceania), ("newzealand", Continent::Oceania), ("brazil", Continent::SouthAmerica),
+                ("southafrica", Continent::Africa), ("uae", Continent::MiddleEast), ("qatar", Continent::MiddleEast), ("israel", Continent::MiddleEast),
+                ("europe", Continent::Europe), ("uk", Continent::Europe), ("france", Continent::Europe), ("germany", Continent::Europe),
+                ("switzerland", Continent::Europe), ("norway", Continent::Europe), ("sweden", Continent::Europe), ("poland", Continent::Europe),
+                ("italy", Continent::Europe), ("spain", Continent::Europe), ("asia", Continent::Asia), ("japan", Continent::Asia),
+                ("korea", Continent::Asia), ("india", Continent::Asia), ("china", Continent::Asia), ("canada", Continent::NorthAmerica),
+                ("mexico", Continent::NorthAmerica), ("us", Continent::NorthAmerica)]
+                .into_iter().find(|(keyword, _)| region.contains(keyword)).map(|(_, c)| c),
+            _ => &[],
+        };
+        prefixes.iter().find(|(prefix, _)| region.starts_with(prefix)).map(|(_, c)| *c)
+    }
+
+    /// Continent of a geography in price descriptions, e.g., "Northern America" or "EMEA"
+    pub fn of_geography(geography: &str) -> Option<Self> {
+        let geography = geography.to_lowercase();
+        [("south america", Continent::SouthAmerica), ("latin america", Continent::SouthAmerica), ("northern america", Continent::NorthAmerica),
+            ("north america", Continent::NorthAmerica), ("americas", Continent::NorthAmerica), ("emea", Continent::Europe), ("europe", Continent::Europe),
+            ("apac", Continent::Asia), ("asia", Continent::Asia), ("oceania", Continent::Oceania), ("australia", Continent::Oceania),
+            ("middle east", Continent::MiddleEast), ("africa", Continent::Africa)]
+            .into_iter().find(|(name, _)| geography.contains(name)).map(|(_, c)| c)
+    }
+}
+
+/// Region of the catalogue
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegionInfo {
+    /// Canonical name, i.e., "{vendor}-{region}"
+    pub id: String,
+    pub vendor: String,
+    /// Name of the region by the vendor
+    pub region: String,
+    pub display_name: String,
+    pub continent: Continent,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Other names of the region, besides "{vendor}:{region}"
+    pub aliases: Vec<String>,
+}
+
+/// Row of a catalogue file, aliases are separated by "|"
+#[derive(Deserialize)]
+struct RegionInfoRaw {
+    id: String,
+    vendor: String,
+    region: String,
+    display_name: String,
+    continent: Continent,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    aliases: String,
+}
+
+impl From<RegionInfoRaw> for RegionInfo {
+    fn from(raw: RegionInfoRaw) -> Self {
+        RegionInfo {
+            id: raw.id,
+            vendor: raw.vendor,
+            region: raw.region,
+            display_name: raw.display_name,
+            continent: raw.continent,
+            latitude: raw.latitude,
+            longitude: raw.longitude,
+            aliases: raw.aliases.split('|').filter(|a| !a.is_empty()).map(|a| a.to_string()).collect(),
+        }
+    }
+}
+
+/// Regions by their canonical names and aliases
+#[derive(Debug, Default)]
+pub struct RegionCatalogue {
+    regions: Vec<RegionInfo>,
+    by_name: HashMap<String, usize>,
+}
+
+impl RegionCatalogue {
+    pub fn new(regions: Vec<RegionInfo>) -> Self {
+        let mut by_name = HashMap::new();
+        for (i, region) in regions.iter().enumerate() {
+            by_name.insert(format!("{}:{}", region.vendor, region.region), i);
+            by_name.extend(region.aliases.iter().map(|a| (a.clone(), i)));
+        }
+        // Canonical names take precedence over aliases
+        by_name.extend(regions.iter().enumerate().map(|(i, r)| (r.id.clone(), i)));
+        RegionCatalogue { regions, by_name }
+    }
+
+    /// Catalogue of the regions of the price files in `data/`
+    pub fn builtin() -> Self {
+        Self::from_reader(include_str!("region_catalogue.csv").as_bytes()).expect("Invalid builtin region catalogue")
+    }
+
+    /// Catalogue of a CSV file in the format of `region_catalogue.csv`.
+    /// Fails with `InvalidData` for malformed rows, duplicate names or coordinates out of range.
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        Self::from_reader(std::fs::File::open(path)?)
+    }
+
+    fn from_reader<R: io::Read>(reader: R) -> io::Result<Self> {
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+        let regions = csv::Reader::from_reader(reader).into_deserialize::<RegionInfoRaw>()
+            .map(|r| r.map(RegionInfo::from))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| invalid(format!("Invalid region catalogue: {}", e)))?;
+
+        let mut names = HashSet::new();
+        for region in &regions {
+            if region.latitude.abs() > 90.0 || region.longitude.abs() > 180.0 {
+                return Err(invalid(format!("Region {} has invalid coordinates {}, {}", region.id, region.latitude, region.longitude)));
+            }
+            if !names.insert(&region.id) {
+                return Err(invalid(format!("Region {} is listed twice in the region catalogue", region.id)));
+            }
+        }
+        Ok(Self::new(regions))
+    }
+
+    pub fn regions(&self) -> &[RegionInfo] {
+        &self.regions
+    }
+
+    /// Region of a canonical name or alias
+    pub fn get(&self, name: &str) -> Option<&RegionInfo> {
+        self.by_name.get(name).map(|i| &self.regions[*i])
+    }
+
+    /// Canonical name of a name or alias, names that are not in the catalogue are kept
+    pub fn canonical_name(&self, name: &str) -> String {
+        self.get(name).map_or_else(|| name.to_string(), |r| r.id.clone())
+    }
+
+    /// Continent of the region of the vendor, by its naming scheme for regions that are not in the catalogue
+    pub fn continent(&self, vendor: &str, region: &str) -> Option<Continent> {
+        self.get(&format!("{}-{}", vendor, region)).map(|r| r.continent).or_else(|| Continent::of_region(vendor, region))
+    }
+}
+
+lazy_static! {
+    /// Builtin catalogue, used to resolve the names of regions of all inputs
+    pub static ref REGION_CATALOGUE: RegionCatalogue = RegionCatalogue::builtin();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_catalogue() {
+        let catalogue = RegionCatalogue::builtin();
+
+        let region = catalogue.get("aws:ca-central-1").unwrap();
+        assert_eq!((region.id.as_str(), region.vendor.as_str(), region.continent), ("aws-ca-central-1", "aws", Continent::NorthAmerica));
+        assert_eq!(catalogue.canonical_name("aws-ca-central-1"), "aws-ca-central-1");
+        // Regions of GCP are named by a zone in the price files
+        assert_eq!(catalogue.canonical_name("gcp-us-east1"), "gcp-us-east1-b");
+        assert_eq!(catalogue.canonical_name("unknown-region"), "unknown-region");
+
+        assert_eq!(catalogue.continent("azure", "australiaeast"), Some(Continent::Oceania));
+        assert_eq!(catalogue.continent("aws", "il-central-1"), Some(Continent::MiddleEast));
+        assert!(catalogue.regions().iter().all(|r| r.latitude.abs() <= 90.0 && r.longitude.abs() <= 180.0));
+    }
+
+    #[test]
+    fn test_invalid_catalogue() {
+        let header = "id,vendor,region,display_name,continent,latitude,longitude,aliases\n";
+        let row = "aws-eu-west-1,aws,eu-west-1,Europe (Ireland),Europe,53.35,-6.26,IE1\n";
+        let from_str = |rows: &str| RegionCatalogue::from_reader(format!("{}{}", header, rows).as_bytes());
+
+        let catalogue = from_str(row).unwrap();
+        assert_eq!(catalogue.canonical_name("IE1"), "aws-eu-west-1");
+
+        let invalid = [
+            // Unknown continent, missing column, and coordinates that are not numbers or out of range
+            row.replace("Europe,", "Atlantis,"),
+            row.replace(",IE1", ""),
+            row.replace("53.35", "north"),
+            row.replace("-6.26", "-186.26"),
+            // Duplicate region
+            format!("{}{}", row, row.replace("IE1", "")),
+        ];
+        for rows in invalid {
+            assert_eq!(from_str(&rows).unwrap_err().kind(), io::ErrorKind::InvalidData, "{}", rows);
+        }
+
+        let missing = std::env::temp_dir().join(format!("missing_region_catalogue_{}.csv", std::process::id()));
+        assert_eq!(RegionCatalogue::from_file(&missing).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
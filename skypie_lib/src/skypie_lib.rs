@@ -6,6 +6,7 @@ extern crate test;
 extern crate lazy_static;
 
 pub mod region;
+pub mod region_catalogue;
 pub mod object_store;
 pub mod application_region;
 
@@ -24,6 +25,8 @@ pub mod range;
 pub mod decision;
 pub mod write_choice;
 pub mod args;
+pub mod input_format;
+pub mod id_catalogue;
 pub mod loader;
 //pub mod skypie_precomputation;
 //pub mod candidate_policies_hydroflow;
@@ -31,8 +34,6 @@ pub mod candidate_policies_and_reduce_hydroflow;
 pub mod monitor;
 //pub mod reduce_oracle_hydroflow;
 //pub mod combinations_wrapper;
-//pub mod influx_logger;
-//pub mod noop_logger;
 pub mod log_entry;
 pub mod output;
 pub mod iter_stream_batches;
@@ -40,6 +41,28 @@ pub mod optimizer_stats;
 pub mod tombstone;
 pub mod compatibility_checker;
 pub mod compatibility_checker_network_slos;
+pub mod compatibility_checker_write_slos;
+pub mod write_choice_constraints;
+pub mod combination_ranking;
+pub mod chunks;
+pub mod progress;
+pub mod metrics;
+pub mod logging;
+pub mod checkpoint;
+pub mod redundancy_elimination;
+mod write_choice_candidates;
+pub mod stats;
+pub mod local_precomputation;
+pub mod incremental_precomputation;
+pub mod oracle_merge;
+pub mod oracle_diff;
+pub mod oracle_reduction;
+pub mod oracle_projection;
+pub mod oracle_reprice;
+pub mod price_import;
+pub mod price_import_aws;
+pub mod price_import_azure;
+pub mod price_import_gcp;
 mod batcher;
 
 pub use loader::Loader;
@@ -50,7 +73,6 @@ pub use region::Region;
 pub use write_choice::WriteChoice;
 pub use decision::Decision;
 pub use application_region::ApplicationRegion;
-//pub use influx_logger;
 //pub use log_entry::SkyPieLogEntry;
 pub use tombstone::Tombstone;
 use compatibility_checker::CompatibilityChecker;
@@ -0,0 +1,174 @@
+use std::{collections::HashMap, io, path::PathBuf, time::Duration};
+
+use itertools::Itertools;
+use tracing::{info, warn};
+use skypie_proto_messages::{oracle_container::SCHEMA_VERSION, IdMapping, OracleHeader, Provenance, Wrapper};
+
+use crate::{identifier::Identifier, log_entry::SkyPieLogEntryType, object_store::ObjectStore, read_choice::ReadChoice, write_choice_constraints::WriteChoiceConstraints, ApplicationRegion, Args, Decision, Loader};
+
+/// Extensions of oracle files, compressed first so that ".proto.bin.zst" is not split at ".zst"
+const ORACLE_EXTENSIONS: [&str; 2] = [".proto.bin.zst", ".proto.bin"];
+
+/// File names of the partitions written by the workers, e.g., "optimal_3.proto.bin" for "optimal.proto.bin"
+/// or "optimal_3.proto.bin.zst" for "optimal.proto.bin.zst".
+/// Dots before the oracle extension belong to the name, other files are split at their last extension.
+pub fn partition_file_names(output_file_name: &Option<PathBuf>, no_workers: u32) -> Vec<String> {
+    if let Some(output_file_name) = output_file_name {
+        let file_name_with_ext = output_file_name.file_name().unwrap().to_str().unwrap();
+        let split = ORACLE_EXTENSIONS.iter()
+            .find_map(|ext| file_name_with_ext.strip_suffix(ext).map(|name| name.len()))
+            .or_else(|| file_name_with_ext.rfind('.').filter(|i| *i > 0))
+            .unwrap_or(file_name_with_ext.len());
+        let (file_name, file_extensions) = file_name_with_ext.split_at(split);
+
+        (0..no_workers)
+            .map(|i| format!("{}_{}{}", file_name, i, file_extensions))
+            .collect_vec()
+    } else {
+        vec![]
+    }
+}
+
+pub fn stats_file_name(experiment_name: &str) -> String {
+    format!("{}/stats", experiment_name)
+}
+
+/// Number of dimensions is the number of workload parameters/cost coefficients + 1 for the intercept
+fn no_dimensions(no_app_regions: usize) -> usize {
+    Decision {
+        write_choice: Default::default(),
+        read_choice: ReadChoice::from_iter((0..no_app_regions).map(|_| Default::default())),
+    }.plane_iter().len()
+}
+
+/// Name of the object store in oracles, i.e., its region and name
+pub fn object_store_name(object_store: &ObjectStore) -> String {
+    format!("{}-{}", object_store.region.name, object_store.name)
+}
+
+fn object_store_names(object_stores: &[ObjectStore]) -> Vec<String> {
+    object_stores.iter().map(object_store_name).collect_vec()
+}
+
+/// Header of the oracle containers written by the workers.
+/// The dictionaries are ordered by id and list the ids, so that decisions can refer to object stores by their ids,
+/// which can have gaps with an id catalogue.
+pub fn oracle_header(app_regions: &[ApplicationRegion], object_stores: &[ObjectStore]) -> OracleHeader {
+    let app_regions = app_regions.iter().sorted_by_key(|r| r.get_id()).collect_vec();
+    let object_stores = object_stores.iter().sorted_by_key(|o| o.get_id()).cloned().collect_vec();
+
+    OracleHeader {
+        schema_version: SCHEMA_VERSION,
+        no_dimensions: no_dimensions(app_regions.len()) as u64,
+        app_regions: app_regions.iter().map(|r| r.region.name.clone()).collect_vec(),
+        object_stores: object_store_names(&object_stores),
+        app_region_ids: app_regions.iter().map(|r| r.get_id() as u32).collect_vec(),
+        object_store_ids: object_stores.iter().map(|o| o.get_id() as u32).collect_vec(),
+    }
+}
+
+/// Ids of the app regions and object stores of the loader by name, i.e., their catalogue ids if the loader has an id catalogue
+pub fn id_mapping(loader: &Loader) -> IdMapping {
+    IdMapping {
+        app_regions: loader.app_regions.iter().map(|r| (r.region.name.clone(), r.get_id() as u32)).collect(),
+        object_stores: loader.object_stores.iter().map(|o| (object_store_name(o), o.get_id() as u32)).collect(),
+    }
+}
+
+/// Basic stats of the precomputation, returns the stats and the name of the optimizer
+pub fn new_stats(loader: &Loader, args: &Args) -> io::Result<(Wrapper, String)> {
+    let no_app_regions = loader.app_regions.len() as i64;
+    let no_dimensions = no_dimensions(loader.app_regions.len()) as i64;
+
+    let optimal_partitions = partition_file_names(&args.output_file_name, args.redundancy_elimination_workers);
+    let candidate_partitions = partition_file_names(&args.output_candidates_file_name, args.redundancy_elimination_workers);
+
+    let (optimizer_name, optimizer_type) = crate::optimizer_stats::get_optimizer_json(args.batch_size, args.optimizer.clone(), args.use_clarkson);
+
+    let mut stats = Wrapper::new(
+        object_store_names(&loader.object_stores),
+        candidate_partitions,
+        optimal_partitions,
+        args.replication_factor as u64,
+        no_app_regions,
+        no_dimensions,
+        optimizer_name.clone(),
+        optimizer_type
+    );
+    stats.provenance = Some(Provenance {
+        write_latency_slo: args.write_latency_slo,
+        write_quorum: args.write_quorum.map(|q| q as u64),
+        prune_unread_replicas: args.prune_unread_replicas,
+        write_choice_constraints: WriteChoiceConstraints::from_args(args)?.flags(),
+        ..loader.provenance.clone()
+    });
+    stats.id_mapping = Some(id_mapping(loader));
+
+    Ok((stats, optimizer_name))
+}
+
+/// Update the run of the stats with the accumulated times and counts of all workers.
+/// Returns false if there is nothing to report yet, i.e., no redundancy elimination happened.
+pub fn update_stats(stats: &mut Wrapper, replication_factor: u64, optimizer_name: &str, times: &HashMap<SkyPieLogEntryType, Duration>, counts: &HashMap<SkyPieLogEntryType, usize>) -> bool {
+    let zero_duration = Duration::from_secs(0);
+    let total_time = times.get(&SkyPieLogEntryType::Total).unwrap_or(&zero_duration);
+    let redundancy_elimination_time = times.get(&SkyPieLogEntryType::RedundancyElimination).unwrap_or(&zero_duration);
+    let write_chioce_time = times.get(&SkyPieLogEntryType::WriteChoiceGeneration).unwrap_or(&zero_duration);
+    let scheduling_time = times.get(&SkyPieLogEntryType::ChunkScheduling).unwrap_or(&zero_duration);
+    let total_time = *total_time + *write_chioce_time + *scheduling_time;
+
+    let optimal_placements = counts.get(&SkyPieLogEntryType::OptimalCount).unwrap_or(&0);
+    let pruned_write_choices = counts.get(&SkyPieLogEntryType::PrunedWriteChoiceCount).unwrap_or(&0);
+    let pruned_candidates = counts.get(&SkyPieLogEntryType::PrunedCandidateCount).unwrap_or(&0);
+
+    if *redundancy_elimination_time == zero_duration {
+        return false;
+    }
+
+    let enumerator_time = if total_time < *redundancy_elimination_time {
+        warn!("Temporary inconsistency, total time is less than redundancy elimination time!");
+        Duration::default()
+    } else {
+        total_time - (*redundancy_elimination_time)
+    };
+    info!(?total_time, ?enumerator_time, ?redundancy_elimination_time, write_choice_time = ?write_chioce_time, ?scheduling_time, optimal_placements, pruned_write_choices, pruned_candidates, "Stats updated");
+
+    let partitioner_time_ns = redundancy_elimination_time.as_secs() as i64 * 1_000_000_000 + redundancy_elimination_time.subsec_nanos() as i64;
+
+    let run = stats.tier_advise.as_mut().unwrap()
+        .replication_factor.entry(replication_factor).or_default()
+        .runs.entry("place_holder".to_string()).or_default();
+    run.enumerator_time_ns = Some(enumerator_time.as_secs() as i64 * 1_000_000_000 + enumerator_time.subsec_nanos() as i64);
+    run.partitioner_time_ns = Some(partitioner_time_ns);
+    run.no_facets = Some(*optimal_placements as i64);
+    run.no_pruned_write_choices = Some(*pruned_write_choices as i64);
+    run.no_pruned_candidates = Some(*pruned_candidates as i64);
+
+    let optimizer = run.optimal_partitions_by_optimizer.entry(optimizer_name.to_string()).or_default();
+
+    optimizer.partitioner_computation_time_ns = Some(partitioner_time_ns);
+    optimizer.partitioner_time_ns = Some(partitioner_time_ns);
+    optimizer.no_facets = Some(*optimal_placements as i64);
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::partition_file_names;
+
+    #[test]
+    fn test_partition_file_names() {
+        let names = |file_name: &str| partition_file_names(&Some(PathBuf::from(file_name)), 2);
+        assert_eq!(names("out/optimal.proto.bin"), vec!["optimal_0.proto.bin", "optimal_1.proto.bin"]);
+        assert_eq!(names("optimal.proto.bin.zst"), vec!["optimal_0.proto.bin.zst", "optimal_1.proto.bin.zst"]);
+        // Dots in the name of the experiment
+        assert_eq!(names("my.exp.proto.bin"), vec!["my.exp_0.proto.bin", "my.exp_1.proto.bin"]);
+        assert_eq!(names("run.v2.bin"), vec!["run.v2_0.bin", "run.v2_1.bin"]);
+        assert_eq!(names("optimal"), vec!["optimal_0", "optimal_1"]);
+        assert_eq!(names(".hidden"), vec![".hidden_0", ".hidden_1"]);
+        assert!(partition_file_names(&None, 2).is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+
+use crate::{
+    compatibility_checker::{CompatibilityChecker, WriteCompatibilityChecker},
+    merge_policies::MergeIterator,
+    object_store::ObjectStore,
+    opt_assignments::opt_assignments,
+    ApplicationRegion, WriteChoice
+};
+
+/// Candidate decisions of a write choice given by object store ids.
+/// Returns the candidates and 1 if the whole write choice is pruned since some replica is never read, otherwise 0.
+pub(crate) fn write_choice_candidates(
+    write_choice_ids: &[u16],
+    regions: &Vec<ApplicationRegion>,
+    object_store_id_map: &HashMap<u16, ObjectStore>,
+    compatibility_checker_slos: &dyn CompatibilityChecker,
+    write_compatibility_checker: &dyn WriteCompatibilityChecker,
+    prune_unread_replicas: bool,
+) -> (MergeIterator, usize) {
+    let object_stores = write_choice_ids.iter().map(|id|{
+        object_store_id_map.get(id).unwrap().clone()
+    }).collect_vec();
+    let write_choice = WriteChoice{object_stores};
+
+    let write_choice = Box::<WriteChoice>::new(write_choice);
+    let assignments = regions.iter()
+        .map(|r|{
+            // Write choice is infeasible for region r if writes violate the write latency SLO
+            if !write_compatibility_checker.is_write_compatible(&write_choice, r) {
+                return (r.clone(), vec![]);
+            }
+
+            // Get optimal assignments of region r
+            let assignments = opt_assignments(write_choice.clone(), r, compatibility_checker_slos);
+            // Convert to (upper bound, object store) pairs
+            let assignments = assignments.map(|(o, range)|(range.max, o));
+
+            // Collect into vector
+            return (r.clone(), assignments.collect::<Vec<(f64, ObjectStore)>>());
+        })
+        .filter(|(_, v)|{v.len() > 0}); // Filter out empty assignments
+
+    // Merge assignments per region to candidate policies
+    // XXX: Materializing here is not necessary
+    // In HyrdoFlow, use stream of (region, upper bound, object store), then search min per region and then merge?
+    let assignments = HashMap::from_iter(assignments);
+
+    #[cfg(dev)]
+    tracing::trace!(no_assignments = assignments.len(), "Assignments");
+
+    // Each app region must have at least one assignment otherwise there is no feasible candidate placement
+    if assignments.len() == regions.len() {
+        // Skip write choice if some replica is not optimal for any app region, i.e., is never read
+        if prune_unread_replicas {
+            let read = assignments.values().flat_map(|v| v.iter().map(|(_, o)| o.id)).collect::<HashSet<u16>>();
+            if !write_choice.object_stores.iter().all(|o| read.contains(&o.id)) {
+                return (MergeIterator::empty(), 1);
+            }
+        }
+
+        return (MergeIterator::new(write_choice, assignments), 0);
+    } else {
+        // Print number of assignments per application region
+
+        #[cfg(dev)]
+        for (r, v) in assignments.iter() {
+            tracing::trace!(region = crate::identifier::Identifier::get_id(r), no_assignments = v.len(), "Assignments of region");
+        }
+
+        return (MergeIterator::empty(), 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::{compatibility_checker::{DefaultCompatibilityChecker, DefaultWriteCompatibilityChecker}, network_record::NetworkCostMap, object_store::{Cost, ObjectStoreStruct}, write_choice_constraints::{WriteChoiceConstraint, WriteChoiceConstraints}, Args, Region};
+
+    fn object_store(id: u16, region: &Region, app_region: &Region, get_cost: f64, egress_cost: f64) -> ObjectStore {
+        let mut cost = Cost::new(get_cost, "get request");
+        cost.add_egress_costs(NetworkCostMap::from_iter([(app_region.clone(), egress_cost)]));
+        cost.add_ingress_costs(NetworkCostMap::from_iter([(app_region.clone(), 0.0)]));
+        ObjectStore::new(ObjectStoreStruct { id, cost, region: region.clone(), name: "s3".to_string() })
+    }
+
+    #[test]
+    fn test_pruning_with_constraints() {
+        let aws = Region { id: 0, name: "aws-eu-west-1".to_string() };
+        let gcp = Region { id: 1, name: "gcp-europe-west1-b".to_string() };
+        let app_region = ApplicationRegion { region: aws.clone(), egress_cost: NetworkCostMap::from_iter([(aws.clone(), 0.0)]), ingress_cost: NetworkCostMap::from_iter([(aws.clone(), 0.0)]) };
+        // The GCP replica is never read, since the AWS replica is cheaper for all workloads
+        let object_stores = vec![object_store(0, &aws, &aws, 1.0, 0.0), object_store(1, &gcp, &aws, 2.0, 0.1)];
+        let object_store_id_map = object_stores.iter().map(|o| (o.id, o.clone())).collect::<HashMap<_, _>>();
+        let compatibility_checker: Box<dyn CompatibilityChecker> = Box::new(DefaultCompatibilityChecker {});
+        let write_compatibility_checker: Box<dyn WriteCompatibilityChecker> = Box::new(DefaultWriteCompatibilityChecker {});
+
+        // With two vendors, the write choice of both replicas is the only feasible one, but pruning drops it
+        let constraints = WriteChoiceConstraints::new(vec![WriteChoiceConstraint::MinVendors(2)]);
+        assert!(!constraints.is_satisfied_by_ids(&object_store_id_map, &[0]));
+        assert!(constraints.is_satisfied_by_ids(&object_store_id_map, &[0, 1]));
+        let candidates = |prune| write_choice_candidates(&[0, 1], &vec![app_region.clone()], &object_store_id_map, compatibility_checker.as_ref(), write_compatibility_checker.as_ref(), prune);
+        let (pruned, no_pruned) = candidates(true);
+        assert_eq!((pruned.count(), no_pruned), (0, 1));
+        assert!(candidates(false).0.count() > 0);
+
+        // Hence, pruning is rejected with such constraints
+        let args = |extra: &[&str]| Args::parse_from(["skypie", "-r", "", "--object-store-selector", "", "--replication-factor", "1", "-b", "1", "-n", "n.csv", "-s", "s.csv",
+            "--redundancy-elimination-workers", "1", "-e", "e", "--prune-unread-replicas"].iter().chain(extra));
+        assert!(args(&[]).unread_replica_pruning().unwrap());
+        assert!(args(&["--distinct-regions"]).unread_replica_pruning().unwrap());
+        for extra in [&["--min-vendors", "2"][..], &["--required-region", "gcp-"], &["--write-latency-slo", "1.0"]] {
+            assert_eq!(args(extra).unread_replica_pruning().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
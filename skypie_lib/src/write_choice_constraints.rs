@@ -0,0 +1,133 @@
+use std::{collections::{HashMap, HashSet}, io};
+
+use regex::Regex;
+
+use crate::{object_store::ObjectStore, Args};
+
+/// Durability constraint on the replicas of a write choice.
+/// Regions act as fault domains, i.e., replicas in the same region may fail together.
+#[derive(Debug, Clone)]
+pub enum WriteChoiceConstraint {
+    /// Replicas span at least this many vendors
+    MinVendors(usize),
+    /// No two replicas are in the same region
+    DistinctRegions,
+    /// At least one replica is in a region matching the regex, e.g., a jurisdiction such as "aws-eu|azure-.*europe"
+    RequireRegion(Regex),
+}
+
+impl WriteChoiceConstraint {
+    pub fn is_satisfied(&self, object_stores: &[&ObjectStore]) -> bool {
+        match self {
+            WriteChoiceConstraint::MinVendors(min_vendors) => {
+                let vendors: HashSet<&str> = object_stores.iter().map(|o| o.region.vendor()).collect();
+                vendors.len() >= *min_vendors
+            }
+            WriteChoiceConstraint::DistinctRegions => {
+                let regions: HashSet<u16> = object_stores.iter().map(|o| o.region.id).collect();
+                regions.len() == object_stores.len()
+            }
+            WriteChoiceConstraint::RequireRegion(re) => {
+                object_stores.iter().any(|o| re.is_match(&o.region.name))
+            }
+        }
+    }
+}
+
+/// Conjunction of durability constraints that every enumerated write choice has to satisfy
+#[derive(Debug, Clone, Default)]
+pub struct WriteChoiceConstraints {
+    constraints: Vec<WriteChoiceConstraint>,
+}
+
+impl WriteChoiceConstraints {
+    pub fn new(constraints: Vec<WriteChoiceConstraint>) -> Self {
+        Self { constraints }
+    }
+
+    /// Constraints of the arguments, fails with InvalidInput if a required region is not a valid regex
+    pub fn from_args(args: &Args) -> io::Result<Self> {
+        let mut constraints = Vec::new();
+
+        if let Some(min_vendors) = args.min_vendors {
+            constraints.push(WriteChoiceConstraint::MinVendors(min_vendors));
+        }
+        if args.distinct_regions {
+            constraints.push(WriteChoiceConstraint::DistinctRegions);
+        }
+        for pattern in &args.required_region {
+            let re = Regex::new(pattern)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid regex for --required-region {}: {}", pattern, e)))?;
+            constraints.push(WriteChoiceConstraint::RequireRegion(re));
+        }
+
+        Ok(Self::new(constraints))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    /// Command line flags of the constraints, recorded in the provenance of oracles
+    pub fn flags(&self) -> Vec<String> {
+        self.constraints.iter().map(|c| match c {
+            WriteChoiceConstraint::MinVendors(min_vendors) => format!("--min-vendors {}", min_vendors),
+            WriteChoiceConstraint::DistinctRegions => "--distinct-regions".to_string(),
+            WriteChoiceConstraint::RequireRegion(re) => format!("--required-region {}", re),
+        }).collect()
+    }
+
+    pub fn is_satisfied(&self, object_stores: &[&ObjectStore]) -> bool {
+        self.constraints.iter().all(|c| c.is_satisfied(object_stores))
+    }
+
+    /// Check a write choice given by object store ids, object stores are looked up by their id
+    pub fn is_satisfied_by_ids(&self, object_store_id_map: &HashMap<u16, ObjectStore>, ids: &[u16]) -> bool {
+        self.is_empty() || {
+            let write_choice = ids.iter().map(|id| &object_store_id_map[id]).collect::<Vec<_>>();
+            self.is_satisfied(&write_choice)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use crate::{object_store::{ObjectStore, ObjectStoreStruct}, Region};
+
+    use super::{WriteChoiceConstraint, WriteChoiceConstraints};
+
+    fn object_store(id: u16, region_id: u16, region_name: &str) -> ObjectStore {
+        let mut o = ObjectStoreStruct::new(id);
+        o.region = Region{id: region_id, name: region_name.to_string()};
+        o
+    }
+
+    #[test]
+    fn test_write_choice_constraints() {
+        let o0 = object_store(0, 0, "aws-eu-west-1");
+        let o1 = object_store(1, 0, "aws-eu-west-1");
+        let o2 = object_store(2, 1, "aws-us-east-1");
+        let o3 = object_store(3, 2, "gcp-us-east1");
+
+        let distinct = WriteChoiceConstraint::DistinctRegions;
+        assert!(!distinct.is_satisfied(&[&o0, &o1]));
+        assert!(distinct.is_satisfied(&[&o0, &o2]));
+
+        let vendors = WriteChoiceConstraint::MinVendors(2);
+        assert!(!vendors.is_satisfied(&[&o0, &o2]));
+        assert!(vendors.is_satisfied(&[&o0, &o3]));
+
+        let eu = WriteChoiceConstraint::RequireRegion(Regex::new("-eu-").unwrap());
+        assert!(!eu.is_satisfied(&[&o2, &o3]));
+        assert!(eu.is_satisfied(&[&o1, &o3]));
+
+        let all = WriteChoiceConstraints::new(vec![distinct, vendors, eu]);
+        assert!(!all.is_satisfied(&[&o0, &o1, &o3]));
+        assert!(!all.is_satisfied(&[&o0, &o2]));
+        assert!(all.is_satisfied(&[&o0, &o2, &o3]));
+        assert!(WriteChoiceConstraints::default().is_satisfied(&[&o0, &o1]));
+        assert_eq!(all.flags(), ["--distinct-regions", "--min-vendors 2", "--required-region -eu-"]);
+    }
+}
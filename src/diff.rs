@@ -0,0 +1,14 @@
+extern crate skypie_lib;
+
+use clap::Parser;
+use skypie_lib::logging::init_logging;
+use skypie_lib::oracle_diff::{diff, DiffArgs};
+
+/// Compare two precomputed oracles of the same scenario, e.g., before and after a price update
+pub fn main() {
+
+    let args = DiffArgs::parse();
+    init_logging(&args.log_level, args.log_json).unwrap();
+
+    diff(args);
+}
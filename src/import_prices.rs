@@ -0,0 +1,14 @@
+extern crate skypie_lib;
+
+use clap::Parser;
+use skypie_lib::logging::init_logging;
+use skypie_lib::price_import::{import, ImportArgs};
+
+/// Import downloaded provider price lists into the object store and network files of the loader
+pub fn main() {
+
+    let args = ImportArgs::parse();
+    init_logging(&args.log_level, args.log_json).unwrap();
+
+    import(args);
+}
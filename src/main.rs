@@ -1,27 +1,15 @@
-#![feature(test)]
-extern crate test;
-
-//mod skypie_lib;
 extern crate skypie_lib;
 
-//mod args;
-
 use clap::Parser;
-use skypie_lib::{Loader, Args};
-
+use skypie_lib::Args;
+use skypie_lib::logging::init_logging;
+use skypie_lib::local_precomputation::local_precomputation;
 
+/// Run the whole precomputation in a single process, without deploying the hydroflow services
 pub fn main() {
 
     let args = Args::parse();
+    init_logging(&args.log_level, args.log_json).unwrap();
 
-    let _loader = Loader::new(&args.network_file, &args.object_store_file, &args.region_selector);
-    
-    /* let regions = loader.app_regions;
-    let object_stores = loader.object_stores;
-    let replication_factor = args.replication_factor;
-    let batch_size = args.batch_size;
-
-    let decisions = skypie_precomputation(regions, object_stores, replication_factor, batch_size);
-
-    println!("Decisions {:?}", decisions.len()); */
-}
\ No newline at end of file
+    local_precomputation(args);
+}
@@ -0,0 +1,14 @@
+extern crate skypie_lib;
+
+use clap::Parser;
+use skypie_lib::logging::init_logging;
+use skypie_lib::oracle_merge::{merge, MergeArgs};
+
+/// Merge precomputed oracles, e.g., of different replication factors or scenarios, into one oracle
+pub fn main() {
+
+    let args = MergeArgs::parse();
+    init_logging(&args.log_level, args.log_json).unwrap();
+
+    merge(args);
+}
@@ -0,0 +1,14 @@
+extern crate skypie_lib;
+
+use clap::Parser;
+use skypie_lib::logging::init_logging;
+use skypie_lib::oracle_projection::{project, ProjectArgs};
+
+/// Restrict a precomputed oracle to a subset of its app regions
+pub fn main() {
+
+    let args = ProjectArgs::parse();
+    init_logging(&args.log_level, args.log_json).unwrap();
+
+    project(args);
+}
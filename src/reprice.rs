@@ -0,0 +1,14 @@
+extern crate skypie_lib;
+
+use clap::Parser;
+use skypie_lib::logging::init_logging;
+use skypie_lib::oracle_reprice::{reprice, RepriceArgs};
+
+/// Re-price a precomputed oracle under new price sheets without re-enumerating it
+pub fn main() {
+
+    let args = RepriceArgs::parse();
+    init_logging(&args.log_level, args.log_json).unwrap();
+
+    reprice(args);
+}